@@ -1,21 +1,304 @@
-use crate::config::ChartConfig;
+use crate::config::{ChartConfig, ClipShape, Fit, Inset, PageLayout};
 use crate::context::{ChartContext, Datasets};
+use crate::crossmatch::crossmatch_csv;
+use crate::error::{ChartError, Result};
+use crate::json::ChartJson;
+use crate::layers::constellations::{constellation_lines, is_shown};
+use crate::layers::grid::{dec_lines, ra_lines};
+use crate::layers::objects::{plotted_objects, symbol_extent, symbol_size};
+use crate::layers::stars::{drawn_stars, star_radius};
 use crate::layers::{
-    ConstellationsLayer, EclipticLayer, FrameLayer, GridLayer, LabelsLayer, Layer, ObjectsLayer,
-    StarsLayer, ZenithLayer,
+    ArcsLayer, ConstellationsLayer, CornerReadoutLayer, CrosshairLayer, DensityLayer,
+    EclipticLayer, FrameLayer, GridLayer, LabelsLayer, Layer, ObjectsLayer, ProperMotionLayer,
+    RegionsLayer, StarsLayer, TwilightLayer, ZenithLayer,
 };
+use crate::layout::Bounds;
+use crate::png::{css_vars, render_png, resolve_css_vars};
+use crate::types::{EQPoint, Projection};
 use std::fs;
-use svg::node::element::{ClipPath, Definitions, Group, Rectangle, Style};
-use svg::Document;
+use svg::node::element::{Circle, ClipPath, Definitions, Element, Group, Rectangle, Style};
+use svg::node::Text;
+use svg::{Document, Node};
 
 // Load the default css for embedding
 const DEFAULT_CSS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/styles/chart.css"));
 
+/// Where a chart's stylesheet came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CssSource {
+    /// Read from the given path
+    File(String),
+    /// The given path couldn't be read, so the embedded stylesheet was used
+    Fallback { path: String, error: String },
+    /// No path was given
+    Embedded,
+}
+
 pub struct Chart<'a> {
     pub context: ChartContext<'a>,
     css_path: Option<String>,
 }
 
+// Size of the inset relative to the main chart, and its gap from the plot edge
+const INSET_SCALE: f64 = 1.0 / 3.0;
+const INSET_PAD: f64 = 8.0;
+
+// Blank space kept around the content of an autocropped chart
+const AUTOCROP_PAD: f64 = 10.0;
+
+// The extent of what's drawn in the plot area, worked out from the geometry
+// the layers draw: the star and object symbols, the grid and constellation
+// lines, the boxes of the labels placed and the inset. Unless clipping is
+// off, each is cut to the clip path's box, past which nothing shows.
+fn content_bounds(context: &ChartContext<'_>) -> Option<Bounds> {
+    let cfg = &context.cfg;
+    let stars = drawn_stars(context)
+        .into_iter()
+        .map(|(s, p)| Bounds::around(p, star_radius(s.magnitude, cfg.object_scale)));
+    let objects = plotted_objects(context)
+        .into_iter()
+        .map(|(o, p)| Bounds::around(p, symbol_extent(&o.kind, symbol_size(cfg, o))));
+    let figures = context
+        .data
+        .constellations
+        .iter()
+        .filter(|c| is_shown(context, c))
+        .flat_map(|c| constellation_lines(context, c));
+    let lines = ra_lines(context)
+        .into_iter()
+        .chain(dec_lines(context))
+        .chain(figures)
+        .filter_map(|line| Bounds::from_points(&line));
+    let labels = LabelsLayer::new().label_extents(context);
+
+    let l = &context.layout;
+    let clip = match cfg.clip {
+        ClipShape::Rect => Bounds {
+            min_x: l.plot_x,
+            min_y: l.plot_y,
+            max_x: l.plot_x + l.plot_w,
+            max_y: l.plot_y + l.plot_h,
+        },
+        ClipShape::Circle => Bounds::around(l.plot_center(), l.inscribed_radius()),
+    };
+    let shown = |b: Bounds| {
+        if cfg.no_clip {
+            return Some(b);
+        }
+        let shown = Bounds {
+            min_x: b.min_x.max(clip.min_x),
+            min_y: b.min_y.max(clip.min_y),
+            max_x: b.max_x.min(clip.max_x),
+            max_y: b.max_y.min(clip.max_y),
+        };
+        (shown.min_x <= shown.max_x && shown.min_y <= shown.max_y).then_some(shown)
+    };
+    // The inset is drawn over the plot, outside its clip path
+    let inset = cfg.inset.map(|i| {
+        let inset = inset_context(context, i);
+        let (x, y) = inset_origin(context, &inset);
+        let l = &inset.layout;
+        Bounds {
+            min_x: x + l.plot_x * INSET_SCALE,
+            min_y: y + l.plot_y * INSET_SCALE,
+            max_x: x + (l.plot_x + l.plot_w) * INSET_SCALE,
+            max_y: y + (l.plot_y + l.plot_h) * INSET_SCALE,
+        }
+    });
+    stars
+        .chain(objects)
+        .chain(lines)
+        .chain(labels)
+        .filter_map(shown)
+        .chain(inset)
+        .reduce(Bounds::union)
+}
+
+// The inset's own chart, a full chart around its center
+fn inset_context<'a>(main: &ChartContext<'a>, inset: Inset) -> ChartContext<'a> {
+    let mut cfg = main.cfg.clone();
+    cfg.center = inset.center;
+    cfg.fov_deg = inset.fov_deg;
+    cfg.clip = ClipShape::Rect;
+    cfg.inset = None;
+    // Unclipped, the inset would draw over the whole main chart
+    cfg.no_clip = false;
+    cfg.center_offset = (0.0, 0.0);
+    cfg.autocrop = false;
+    // Keep symbols about the same size as the main chart's once scaled
+    cfg.object_scale /= INSET_SCALE;
+    ChartContext::new(main.data, cfg)
+}
+
+// Where the inset's chart is moved to before it's scaled down, so its plot
+// lands in the bottom right corner of the main plot
+fn inset_origin(main: &ChartContext<'_>, inset: &ChartContext<'_>) -> (f64, f64) {
+    let (l, main) = (&inset.layout, &main.layout);
+    (
+        main.plot_x + main.plot_w - (l.plot_x + l.plot_w) * INSET_SCALE - INSET_PAD,
+        main.plot_y + main.plot_h - (l.plot_y + l.plot_h) * INSET_SCALE - INSET_PAD,
+    )
+}
+
+// The plot area's clip path
+fn clip_path(context: &ChartContext<'_>, id: &str) -> ClipPath {
+    let l = &context.layout;
+    let clip = ClipPath::new().set("id", id);
+    match context.cfg.clip {
+        ClipShape::Rect => clip.add(
+            Rectangle::new()
+                .set("x", l.plot_x)
+                .set("y", l.plot_y)
+                .set("width", l.plot_w)
+                .set("height", l.plot_h),
+        ),
+        ClipShape::Circle => clip.add(
+            Circle::new()
+                .set("cx", l.plot_center().x)
+                .set("cy", l.plot_center().y)
+                .set("r", l.inscribed_radius()),
+        ),
+    }
+}
+
+// The number of elements in a node, counting the node itself. Text and other
+// leaf nodes have no children to hold.
+fn element_count(node: &dyn Node) -> usize {
+    node.get_children().map_or(0, |children| {
+        1 + children
+            .iter()
+            .map(|child| element_count(child.as_ref()))
+            .sum::<usize>()
+    })
+}
+
+// Prefix every id under a node, so a layer stack drawn more than once on a
+// page (the inset, or each hemisphere) keeps its ids unique
+fn prefix_ids(node: &mut dyn Node, prefix: &str) {
+    if let Some(id) = node.get_attributes_mut().and_then(|a| a.get_mut("id")) {
+        *id = format!("{prefix}{id}").into();
+    }
+    for child in node.get_children_mut().into_iter().flatten() {
+        prefix_ids(child.as_mut(), prefix);
+    }
+}
+
+// The elements a chart has used of its maximum output size. Each layer is
+// counted as it's drawn, so a runaway chart stops at the layer that takes it
+// over the limit rather than being built in full first. The advice on what
+// to change is about the page as configured, even for the hemisphere charts
+// drawn on it.
+struct ElementBudget {
+    limit: usize,
+    used: usize,
+    page_layout: PageLayout,
+    fov_deg: f64,
+}
+
+impl ElementBudget {
+    fn new(context: &ChartContext<'_>) -> Self {
+        Self {
+            limit: context.cfg.max_output_size,
+            used: 0,
+            page_layout: context.cfg.page_layout,
+            fov_deg: context.cfg.fov_deg,
+        }
+    }
+
+    fn narrower(&self) -> String {
+        match self.page_layout {
+            PageLayout::Single => format!("a narrower field of view than {:.0}°", self.fov_deg),
+            PageLayout::DualHemisphere => "a single chart instead of both hemispheres".to_owned(),
+        }
+    }
+
+    // What would shrink the layer that took the chart over the limit. Stars,
+    // objects and gridlines are drawn out past the plot area for the clip
+    // path to hide, so a narrower view doesn't leave fewer of them.
+    fn advice(&self, layer: &str) -> String {
+        match layer {
+            "stars" | "proper-motion" => "a brighter star magnitude limit".to_owned(),
+            "objects" => "a brighter object magnitude limit".to_owned(),
+            "grid" => "wider grid steps".to_owned(),
+            "labels" => format!("{} or a brighter magnitude limit", self.narrower()),
+            _ => self.narrower(),
+        }
+    }
+
+    fn charge(&mut self, layer: &Group) -> Result<()> {
+        self.used += element_count(layer);
+        if self.used <= self.limit {
+            return Ok(());
+        }
+        let id = layer
+            .get_attributes()
+            .and_then(|a| a.get("id"))
+            .map(|id| id.to_string())
+            .unwrap_or_default();
+        let name = id.strip_prefix("layer-").unwrap_or(&id);
+        Err(ChartError::InvalidConfig(format!(
+            "the chart would have more than {} SVG elements by the time its {name} layer \
+             is drawn; try {}",
+            self.limit,
+            self.advice(name),
+        )))
+    }
+}
+
+// The layers drawn inside the plot area, clipped to it
+fn render_clipped(
+    context: &ChartContext<'_>,
+    clip_id: &str,
+    budget: &mut ElementBudget,
+) -> Result<Group> {
+    let objects: Box<dyn Layer> = Box::new(ObjectsLayer::new());
+    let stars: Box<dyn Layer> = Box::new(StarsLayer::new());
+    let (lower, upper) = if context.cfg.stars_under_objects {
+        (stars, objects)
+    } else {
+        (objects, stars)
+    };
+    // The grid goes under the stars and objects, or over them as an
+    // overlay, though still under the arcs and labels
+    let grid: Box<dyn Layer> = Box::new(GridLayer::new());
+    let (grid_under, grid_over) = if context.cfg.grid_on_top {
+        (None, Some(grid))
+    } else {
+        (Some(grid), None)
+    };
+    // Layer stack, back to front
+    let layers: Vec<Box<dyn Layer>> = [
+        Some(Box::new(DensityLayer::new()) as Box<dyn Layer>),
+        Some(Box::new(TwilightLayer::new())),
+        Some(Box::new(EclipticLayer::new())),
+        Some(Box::new(RegionsLayer::new())),
+        grid_under,
+        Some(Box::new(ConstellationsLayer::new())),
+        // Under the stars, so each arrow starts from the edge of its star
+        Some(Box::new(ProperMotionLayer::new())),
+        Some(lower),
+        Some(upper),
+        grid_over,
+        Some(Box::new(ArcsLayer::new())),
+        Some(Box::new(LabelsLayer::new())),
+        Some(Box::new(ZenithLayer::new())),
+        Some(Box::new(CrosshairLayer::new())),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let mut clipped = Group::new();
+    if !context.cfg.no_clip {
+        clipped = clipped.set("clip-path", format!("url(#{clip_id})"));
+    }
+    for layer in layers {
+        let group = layer.render(context);
+        budget.charge(&group)?;
+        clipped = clipped.add(group);
+    }
+    Ok(clipped)
+}
+
 impl<'a> Chart<'a> {
     pub fn new(data: Datasets<'a>, cfg: ChartConfig, css_path: Option<String>) -> Self {
         Self {
@@ -24,69 +307,816 @@ impl<'a> Chart<'a> {
         }
     }
 
+    /// The stylesheet the chart is drawn with, and where it came from
+    pub fn load_css(&self) -> (String, CssSource) {
+        let Some(path) = &self.css_path else {
+            return (DEFAULT_CSS.to_owned(), CssSource::Embedded);
+        };
+        match fs::read_to_string(path) {
+            Ok(text) => (text, CssSource::File(path.clone())),
+            Err(e) => (
+                DEFAULT_CSS.to_owned(),
+                CssSource::Fallback {
+                    path: path.clone(),
+                    error: e.to_string(),
+                },
+            ),
+        }
+    }
+
     fn load_css_text(&self) -> String {
-        if let Some(path) = &self.css_path {
-            if let Ok(text) = fs::read_to_string(path) {
-                return text;
-            }
+        self.load_css().0
+    }
+
+    /// The chart as an SVG document, or an error if it would have more
+    /// elements than the configured maximum
+    pub fn draw_document(&self) -> Result<Document> {
+        self.draw_document_with_css(&self.load_css_text())
+    }
+
+    fn draw_document_with_css(&self, css: &str) -> Result<Document> {
+        let mut budget = ElementBudget::new(&self.context);
+        match self.context.cfg.page_layout {
+            PageLayout::Single => self.draw_single(css, &mut budget),
+            PageLayout::DualHemisphere => self.draw_hemispheres(css, &mut budget),
         }
-        // Embedded fallback
-        DEFAULT_CSS.to_owned()
     }
-    pub fn draw_document(&self) -> Document {
+
+    fn draw_single(&self, css: &str, budget: &mut ElementBudget) -> Result<Document> {
         let w = self.context.cfg.width;
         let h = self.context.cfg.height;
-        let l = &self.context.layout;
-
-        // Layer stack, back to front
-        let clipped_layers: Vec<Box<dyn Layer>> = vec![
-            Box::new(EclipticLayer::new()),
-            Box::new(GridLayer::new()),
-            Box::new(ConstellationsLayer::new()),
-            Box::new(ObjectsLayer::new()),
-            Box::new(StarsLayer::new()),
-            Box::new(LabelsLayer::new()),
-            Box::new(ZenithLayer::new()),
-        ];
-        let unclipped_layers: Vec<Box<dyn Layer>> = vec![Box::new(FrameLayer::new())];
+
+        let mut unclipped_layers: Vec<Box<dyn Layer>> = vec![Box::new(FrameLayer::new())];
+        if self.context.cfg.corner_readouts {
+            unclipped_layers.push(Box::new(CornerReadoutLayer::new()));
+        }
 
         let mut doc = Document::new()
             .set("xmlns", "http://www.w3.org/2000/svg")
             .set("width", w)
             .set("height", h)
             .set("class", "chart");
+        if self.context.cfg.output_metadata {
+            doc = doc.add(self.metadata()?);
+        }
+
+        // An autocropped chart views just its content. The frame is drawn
+        // around the whole plot area, so it's left out.
+        if self.context.cfg.autocrop
+            && let Some(b) = content_bounds(&self.context)
+        {
+            let (cw, ch) = (
+                (b.width() + 2.0 * AUTOCROP_PAD).ceil(),
+                (b.height() + 2.0 * AUTOCROP_PAD).ceil(),
+            );
+            doc = doc.set("width", cw).set("height", ch).set(
+                "viewBox",
+                (
+                    self.context.fmt_coord(b.min_x - AUTOCROP_PAD),
+                    self.context.fmt_coord(b.min_y - AUTOCROP_PAD),
+                    cw,
+                    ch,
+                ),
+            );
+            unclipped_layers.clear();
+        }
 
-        let css = self.load_css_text();
         if !css.is_empty() {
             doc = doc.add(Style::new(css));
         }
 
-        let clip_rect = Rectangle::new()
-            .set("x", l.plot_x)
-            .set("y", l.plot_y)
-            .set("width", l.plot_w)
-            .set("height", l.plot_h);
-        let clip = ClipPath::new().set("id", "clip-chart").add(clip_rect);
-        let defs = Definitions::new().add(clip);
+        let mut defs = Definitions::new().add(clip_path(&self.context, "clip-chart"));
+        let mut inset = None;
+        if let Some(i) = self.context.cfg.inset {
+            let (clip, group) = self.draw_inset(i, budget)?;
+            defs = defs.add(clip);
+            inset = Some(group);
+        }
         doc = doc.add(defs);
 
         // Clipped layers that are inside the graticle borders
-        let mut clipped = Group::new().set("clip-path", "url(#clip-chart)");
-        for layer in clipped_layers {
-            clipped = clipped.add(layer.render(&self.context));
-        }
-        doc = doc.add(clipped);
+        doc = doc.add(render_clipped(&self.context, "clip-chart", budget)?);
 
         // Unclipped layers outside the graticle borders
         for layer in unclipped_layers {
-            doc = doc.add(layer.render(&self.context));
+            let group = layer.render(&self.context);
+            budget.charge(&group)?;
+            doc = doc.add(group);
+        }
+
+        if let Some(inset) = inset {
+            doc = doc.add(inset);
+        }
+
+        Ok(doc)
+    }
+
+    // The configuration as JSON, so the file records how it was drawn and
+    // can be drawn again
+    fn metadata(&self) -> Result<Element> {
+        let json = serde_json::to_string_pretty(&self.context.cfg).map_err(|e| {
+            ChartError::Render(format!("can't encode the configuration as JSON: {e}"))
+        })?;
+        let mut metadata = Element::new("metadata");
+        metadata.append(Text::new(json));
+        Ok(metadata)
+    }
+
+    // A chart of the hemisphere around the pole at `dec_deg`, out to the
+    // equator. Only the drawing settings carry over from the page's
+    // configuration; the view and any observer don't.
+    fn hemisphere_context(&self, dec_deg: f64) -> ChartContext<'a> {
+        let mut cfg = self.context.cfg.clone();
+        cfg.page_layout = PageLayout::Single;
+        cfg.center = EQPoint {
+            ra_deg: 0.0,
+            dec_deg,
+        };
+        cfg.position_angle_deg = 0.0;
+        cfg.projection = Projection::Stereographic;
+        // Stereographic charts are sized by tan(fov/2), so this puts the
+        // equator, at tan(45°), on the rim
+        cfg.fov_deg = 90.0;
+        cfg.max_zenith_deg = None;
+        cfg.fit = Fit::Contain;
+        cfg.center_offset = (0.0, 0.0);
+        cfg.clip = ClipShape::Circle;
+        cfg.observer = None;
+        cfg.inset = None;
+        cfg.corner_readouts = false;
+        cfg.autocrop = false;
+        ChartContext::new(self.context.data, cfg)
+    }
+
+    // The north and south hemispheres side by side, each a full circular
+    // chart with its own clip path and frame
+    fn draw_hemispheres(&self, css: &str, budget: &mut ElementBudget) -> Result<Document> {
+        let (w, h) = (self.context.cfg.width, self.context.cfg.height);
+        let mut doc = Document::new()
+            .set("xmlns", "http://www.w3.org/2000/svg")
+            .set("width", 2 * w)
+            .set("height", h)
+            .set("class", "chart");
+        if self.context.cfg.output_metadata {
+            doc = doc.add(self.metadata()?);
+        }
+        if !css.is_empty() {
+            doc = doc.add(Style::new(css));
         }
 
-        doc
+        let mut defs = Definitions::new();
+        let mut hemispheres = Vec::new();
+        for (i, (name, dec_deg)) in [("north", 90.0), ("south", -90.0)].into_iter().enumerate() {
+            let context = self.hemisphere_context(dec_deg);
+            let clip_id = format!("clip-{name}");
+            defs = defs.add(clip_path(&context, &clip_id));
+            let mut clipped = render_clipped(&context, &clip_id, budget)?;
+            let mut frame = FrameLayer::new().render(&context);
+            budget.charge(&frame)?;
+            prefix_ids(&mut clipped, &format!("{name}-"));
+            prefix_ids(&mut frame, &format!("{name}-"));
+            hemispheres.push(
+                Group::new()
+                    .set("class", format!("hemisphere {name}"))
+                    .set("transform", format!("translate({},0)", i as u32 * w))
+                    .add(clipped)
+                    .add(frame),
+            );
+        }
+        doc = doc.add(defs);
+        for group in hemispheres {
+            doc = doc.add(group);
+        }
+        Ok(doc)
+    }
+
+    // The inset is a full chart around its own center, scaled down into the
+    // bottom right corner of the plot with a border. It returns its clip path
+    // for the document's definitions along with the group to draw.
+    fn draw_inset(&self, inset: Inset, budget: &mut ElementBudget) -> Result<(ClipPath, Group)> {
+        let context = inset_context(&self.context, inset);
+        let (x, y) = inset_origin(&self.context, &context);
+        let l = &context.layout;
+        let plot_rect = |class: &str| {
+            Rectangle::new()
+                .set("class", class)
+                .set("x", l.plot_x)
+                .set("y", l.plot_y)
+                .set("width", l.plot_w)
+                .set("height", l.plot_h)
+        };
+
+        let mut clipped = render_clipped(&context, "clip-inset", budget)?;
+        prefix_ids(&mut clipped, "inset-");
+        let group = Group::new()
+            .set("class", "inset")
+            .set(
+                "transform",
+                format!(
+                    "translate({},{}) scale({INSET_SCALE})",
+                    context.fmt_coord(x),
+                    context.fmt_coord(y)
+                ),
+            )
+            .add(plot_rect("inset-background"))
+            .add(clipped)
+            .add(plot_rect("inset-border"));
+        Ok((clip_path(&context, "clip-inset"), group))
+    }
+
+    /// The pixel extent of the symbols, lines and labels drawn in the plot
+    /// area, and of any inset, or None if nothing is
+    pub fn content_bounds(&self) -> Option<Bounds> {
+        content_bounds(&self.context)
+    }
+
+    pub fn to_file(&self, path: &str) -> Result<()> {
+        let doc = self.draw_document()?;
+        Ok(svg::save(path, &doc)?)
+    }
+
+    /// The chart rasterized to PNG, at `scale_factor` times its width and
+    /// height. The stylesheet's light color scheme is used.
+    pub fn to_png(&self, scale_factor: f64, supersample: u32) -> Result<Vec<u8>> {
+        let css = self.load_css_text();
+        let vars = css_vars(&css);
+        let doc = self.draw_document_with_css(&resolve_css_vars(&css, &vars))?;
+        render_png(
+            &doc.to_string(),
+            scale_factor,
+            supersample,
+            vars.get("--bg").copied(),
+        )
+    }
+
+    /// The chart's projected geometry as JSON, for drawing elsewhere, or an
+    /// error if it would list more entries than the configured maximum
+    pub fn to_json(&self) -> Result<String> {
+        let json = ChartJson::new(&self.context);
+        let budget = ElementBudget::new(&self.context);
+        let (entries, most) = json.size();
+        if entries > budget.limit {
+            // Unlike the SVG, the JSON lists only the stars and objects on
+            // the plot area, so a narrower view lists fewer
+            let advice = match most {
+                "stars" | "objects" => format!("{} or {}", budget.narrower(), budget.advice(most)),
+                _ => budget.advice(most),
+            };
+            return Err(ChartError::InvalidConfig(format!(
+                "the chart's JSON would have {entries} entries, more than the limit of {}; \
+                 try {advice}",
+                budget.limit,
+            )));
+        }
+        serde_json::to_string(&json)
+            .map_err(|e| ChartError::Render(format!("can't encode the chart as JSON: {e}")))
+    }
+
+    /// A CSV row for each object drawn, with its number in each of the
+    /// Messier, NGC and IC catalogs and its name, for matching the chart
+    /// against other software's catalogs
+    pub fn to_crossmatch_csv(&self) -> Result<String> {
+        crossmatch_csv(&self.context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CenterMarker;
+    use crate::test_utils::{approx, make_object, make_star};
+    use crate::types::CelestialObject;
+
+    #[test]
+    fn charts_share_one_loaded_dataset() {
+        let stars = vec![make_star(0.0, 0.0, 1.0), make_star(100.0, 40.0, 2.0)];
+        let objects = crate::data::load_objects(
+            None,
+            crate::data::ObjectOrder::Catalog,
+            &crate::data::default_catalog_priority(),
+        )
+        .unwrap();
+        let constellations =
+            crate::data::load_constellations(None, crate::data::Figures::Modern).unwrap();
+        let data = Datasets {
+            stars: &stars,
+            objects: &objects,
+            constellations: &constellations,
+        };
+        let chart_at = |data: Datasets<'_>, ra_deg: f64, dec_deg: f64| {
+            let cfg = ChartConfig {
+                center: EQPoint { ra_deg, dec_deg },
+                ..Default::default()
+            };
+            Chart::new(data, cfg, None)
+                .draw_document()
+                .unwrap()
+                .to_string()
+        };
+        let (a, b) = (chart_at(data, 0.0, 0.0), chart_at(data, 100.0, 40.0));
+        assert!(a.contains("class=\"star\"") && b.contains("class=\"star\""));
+        assert_ne!(a, b);
+
+        // Each chart drawn from the shared catalogs matches one drawn from
+        // its own copy
+        let (own_stars, own_objects) = (stars.clone(), objects.clone());
+        let own = Datasets {
+            stars: &own_stars,
+            objects: &own_objects,
+            constellations: &constellations,
+        };
+        assert_eq!(chart_at(own, 0.0, 0.0), a);
+        assert_eq!(chart_at(own, 100.0, 40.0), b);
+    }
+
+    #[test]
+    fn metadata_records_the_configuration() {
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &[],
+        };
+        let cfg = ChartConfig {
+            center: EQPoint {
+                ra_deg: 83.82,
+                dec_deg: -5.39,
+            },
+            projection: Projection::AltAz,
+            ..Default::default()
+        };
+        let doc = Chart::new(data, cfg.clone(), None)
+            .draw_document()
+            .unwrap()
+            .to_string();
+        assert!(!doc.contains("<metadata"));
+
+        let cfg = ChartConfig {
+            output_metadata: true,
+            ..cfg
+        };
+        let doc = Chart::new(data, cfg, None)
+            .draw_document()
+            .unwrap()
+            .to_string();
+        let start = doc.find("<metadata>").unwrap() + "<metadata>".len();
+        let end = doc.find("</metadata>").unwrap();
+        let json: serde_json::Value = serde_json::from_str(&doc[start..end]).unwrap();
+        assert_eq!(json["center"]["ra_deg"], 83.82);
+        assert_eq!(json["center"]["dec_deg"], -5.39);
+        assert_eq!(json["projection"], "altaz");
+        assert_eq!(json["fov_deg"], 60.0);
+        assert_eq!(
+            json["catalog_priority"],
+            serde_json::json!(["M", "NGC", "IC"])
+        );
+    }
+
+    #[test]
+    fn wide_gnomonic_charts_trip_the_output_size_guard() {
+        // A dense field, a star every 0.25° over the hemisphere the chart
+        // faces, which a 179° view takes nearly all of. A quarter of them are
+        // 3rd magnitude, and the rest fainter.
+        let stars: Vec<_> = (-356..=356)
+            .flat_map(|i| {
+                (-356..=356).map(move |j| {
+                    let mag = 3.0 + ((i + j) as f64).rem_euclid(4.0);
+                    make_star((i as f64 * 0.25).rem_euclid(360.0), j as f64 * 0.25, mag)
+                })
+            })
+            .collect();
+        let data = Datasets {
+            stars: &stars,
+            objects: &[],
+            constellations: &[],
+        };
+        let chart = |fov_deg, limit_star_mag| {
+            let cfg = ChartConfig {
+                fov_deg,
+                limit_star_mag,
+                ..Default::default()
+            };
+            Chart::new(data, cfg, None)
+        };
+        let err = chart(179.0, 6.5).draw_document().unwrap_err();
+        assert!(matches!(err, ChartError::InvalidConfig(_)));
+        assert_eq!(
+            err.to_string(),
+            "the chart would have more than 500000 SVG elements by the time its stars layer is \
+             drawn; try a brighter star magnitude limit"
+        );
+        assert!(chart(179.0, 3.0).draw_document().is_ok());
+
+        let err = chart(179.0, 6.5).to_json().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("try a narrower field of view than 179° or a brighter star magnitude limit"));
+        assert!(chart(30.0, 6.5).to_json().is_ok());
+    }
+
+    #[test]
+    fn json_lists_only_visible_stars() {
+        let stars = vec![
+            make_star(0.0, 0.0, 1.0),
+            make_star(5.0, 5.0, 4.0),
+            // Too faint for the default limit
+            make_star(1.0, 1.0, 12.0),
+            // Projected, but off the plot area
+            make_star(50.0, 0.0, 1.0),
+            // Behind the gnomonic horizon
+            make_star(180.0, 0.0, 1.0),
+        ];
+        let data = Datasets {
+            stars: &stars,
+            objects: &[],
+            constellations: &[],
+        };
+        let cfg = ChartConfig {
+            center: crate::types::EQPoint {
+                ra_deg: 0.0,
+                dec_deg: 0.0,
+            },
+            ..Default::default()
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&Chart::new(data, cfg, None).to_json().unwrap()).unwrap();
+
+        let stars = json["stars"].as_array().unwrap();
+        assert_eq!(stars.len(), 2);
+        assert_eq!(stars[0]["x"], 400.0);
+        assert_eq!(json["width"], 800);
+        assert!(!json["grid"]["ra"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_objects_carry_their_drawn_size() {
+        let objects = vec![make_object("galaxy", 0.0, 0.0, 9.0, 10.0)];
+        let data = Datasets {
+            stars: &[],
+            objects: &objects,
+            constellations: &[],
+        };
+        let cfg = ChartConfig {
+            center: crate::types::EQPoint {
+                ra_deg: 0.0,
+                dec_deg: 0.0,
+            },
+            ..Default::default()
+        };
+        let chart = Chart::new(data, cfg, None);
+        let json: serde_json::Value = serde_json::from_str(&chart.to_json().unwrap()).unwrap();
+
+        let listed = json["objects"].as_array().unwrap();
+        assert_eq!(listed.len(), 1);
+        let size = symbol_size(&chart.context.cfg, &objects[0]);
+        assert!(approx(
+            listed[0]["r"].as_f64().unwrap(),
+            symbol_extent("galaxy", size),
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn json_leaves_out_stars_merged_into_a_brighter_one() {
+        let stars = vec![make_star(0.0, 0.0, 1.0), make_star(0.0, 0.0, 2.0)];
+        let data = Datasets {
+            stars: &stars,
+            objects: &[],
+            constellations: &[],
+        };
+        let count = |merge_coincident_stars| {
+            let cfg = ChartConfig {
+                center: crate::types::EQPoint {
+                    ra_deg: 0.0,
+                    dec_deg: 0.0,
+                },
+                merge_coincident_stars,
+                ..Default::default()
+            };
+            let json: serde_json::Value =
+                serde_json::from_str(&Chart::new(data, cfg, None).to_json().unwrap()).unwrap();
+            json["stars"].as_array().unwrap().len()
+        };
+        assert_eq!(count(false), 2);
+        assert_eq!(count(true), 1);
+    }
+
+    // A chart autocropped to a small patch around a star, in the top left
+    // corner, with no grid lines crossing it and no center marker
+    fn lone_star_chart(stars: &[CelestialObject]) -> Chart<'_> {
+        let data = Datasets {
+            stars,
+            objects: &[],
+            constellations: &[],
+        };
+        let cfg = ChartConfig {
+            ra_range: Some((19.0, 21.0)),
+            dec_range: Some((19.0, 21.0)),
+            step_dec_deg: 8.0,
+            center_marker: CenterMarker::None,
+            autocrop: true,
+            ..Default::default()
+        };
+        Chart::new(data, cfg, None)
+    }
+
+    #[test]
+    fn content_bounds_hug_a_lone_star() {
+        // Too faint to be labeled
+        let stars = vec![make_star(20.0, 20.0, 1.5)];
+        let chart = lone_star_chart(&stars);
+        let p = chart.context.project_to_pixels(stars[0].coords).unwrap();
+        assert!(p.x < 200.0 && p.y < 200.0);
+
+        let r = star_radius(1.5, 1.0);
+        let b = chart.content_bounds().unwrap();
+        assert_eq!(b, Bounds::around(p, r));
+
+        let doc = chart.draw_document().unwrap().to_string();
+        let size = (b.width() + 2.0 * AUTOCROP_PAD).ceil();
+        assert!(doc.contains(&format!("width=\"{size}\"")), "{doc}");
+        assert!(!doc.contains("layer-frame"));
+    }
+
+    #[test]
+    fn content_bounds_take_in_labels() {
+        let stars = vec![make_star(20.0, 20.0, 1.0)];
+        let chart = lone_star_chart(&stars);
+        let p = chart.context.project_to_pixels(stars[0].coords).unwrap();
+        let r = star_radius(1.0, 1.0);
+
+        // The label sits above the star, and is wider than it
+        let b = chart.content_bounds().unwrap();
+        assert!(chart.draw_document().unwrap().to_string().contains("HIP 1"));
+        assert!(b.min_y < p.y - r - 5.0, "{b:?}");
+        assert!(b.width() > 2.0 * r + 5.0, "{b:?}");
+        assert!(b.max_y >= p.y + r - 0.01);
+    }
+
+    #[test]
+    fn content_bounds_take_in_the_inset() {
+        let stars = vec![make_star(20.0, 20.0, 1.5)];
+        let mut chart = lone_star_chart(&stars);
+        let alone = chart.content_bounds().unwrap();
+        chart.context.cfg.inset = Some(Inset {
+            center: stars[0].coords,
+            fov_deg: 5.0,
+        });
+
+        // The inset sits in the bottom right corner of the plot
+        let l = chart.context.layout;
+        let b = chart.content_bounds().unwrap();
+        assert_eq!((b.min_x, b.min_y), (alone.min_x, alone.min_y));
+        assert!(
+            approx(b.max_x, l.plot_x + l.plot_w - INSET_PAD, 1e-9),
+            "{b:?}"
+        );
+        assert!(
+            approx(b.max_y, l.plot_y + l.plot_h - INSET_PAD, 1e-9),
+            "{b:?}"
+        );
+    }
+
+    #[test]
+    fn css_reports_where_it_came_from() {
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &[],
+        };
+        let chart = |css_path: Option<String>| Chart::new(data, ChartConfig::default(), css_path);
+
+        let (css, source) = chart(None).load_css();
+        assert_eq!(css, DEFAULT_CSS);
+        assert_eq!(source, CssSource::Embedded);
+
+        let missing = "/nonexistent/chart.css".to_string();
+        let (css, source) = chart(Some(missing.clone())).load_css();
+        assert_eq!(css, DEFAULT_CSS);
+        assert!(matches!(source, CssSource::Fallback { path, .. } if path == missing));
+
+        let path = std::env::temp_dir().join(format!("charter-css-{}.css", std::process::id()));
+        fs::write(&path, ".star { fill: red; }").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let (css, source) = chart(Some(path.clone())).load_css();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(css, ".star { fill: red; }");
+        assert_eq!(source, CssSource::File(path));
+    }
+
+    // Every id in the document, each once
+    fn assert_unique_ids(doc: &str) {
+        let ids: Vec<&str> = doc
+            .split(" id=\"")
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        let unique: std::collections::HashSet<&str> = ids.iter().copied().collect();
+        assert_eq!(unique.len(), ids.len(), "{ids:?}");
+    }
+
+    #[test]
+    fn inset_adds_a_bordered_group() {
+        let stars = vec![make_star(5.0, 5.0, 1.0)];
+        let data = Datasets {
+            stars: &stars,
+            objects: &[],
+            constellations: &[],
+        };
+        let inset = Inset {
+            center: crate::types::EQPoint {
+                ra_deg: 5.0,
+                dec_deg: 5.0,
+            },
+            fov_deg: 10.0,
+        };
+        let render = |inset: Option<Inset>| {
+            let cfg = ChartConfig {
+                inset,
+                ..Default::default()
+            };
+            Chart::new(data, cfg, None)
+                .draw_document()
+                .unwrap()
+                .to_string()
+        };
+
+        let plain = render(None);
+        assert!(!plain.contains("class=\"inset\""));
+
+        let doc = render(Some(inset));
+        assert_eq!(doc.matches("<g class=\"inset\"").count(), 1);
+        assert!(doc.contains("class=\"inset-border\""));
+        assert!(doc.contains("<clipPath id=\"clip-inset\""));
+        // The inset draws its own layers, apart from the main plot
+        assert!(doc.contains("url(#clip-inset)"));
+        assert!(doc.contains("id=\"layer-stars\"") && doc.contains("id=\"inset-layer-stars\""));
+        assert_unique_ids(&doc);
+    }
+
+    #[test]
+    fn no_clip_leaves_the_plot_layers_unclipped() {
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &[],
+        };
+        let render = |no_clip: bool| {
+            let cfg = ChartConfig {
+                no_clip,
+                ..Default::default()
+            };
+            Chart::new(data, cfg, None)
+                .draw_document()
+                .unwrap()
+                .to_string()
+        };
+
+        assert!(render(false).contains("clip-path=\"url(#clip-chart)\""));
+        let doc = render(true);
+        assert!(!doc.contains("clip-path="));
+        // The frame is still drawn
+        assert!(doc.contains("id=\"layer-frame\""));
+    }
+
+    #[test]
+    fn dual_hemisphere_layout_draws_two_circular_charts() {
+        let stars = vec![make_star(0.0, 45.0, 1.0), make_star(0.0, -45.0, 1.0)];
+        let data = Datasets {
+            stars: &stars,
+            objects: &[],
+            constellations: &[],
+        };
+        let cfg = ChartConfig {
+            page_layout: PageLayout::DualHemisphere,
+            width: 400,
+            height: 400,
+            ..Default::default()
+        };
+        let doc = Chart::new(data, cfg, None)
+            .draw_document()
+            .unwrap()
+            .to_string();
+
+        assert!(doc.contains("width=\"800\""));
+        for name in ["north", "south"] {
+            let clip = doc
+                .split(&format!("<clipPath id=\"clip-{name}\">"))
+                .nth(1)
+                .unwrap();
+            assert!(clip.trim_start().starts_with("<circle"), "{name}");
+            assert!(doc.contains(&format!("url(#clip-{name})")));
+        }
+        assert_eq!(doc.matches("<clipPath").count(), 2);
+        assert!(
+            doc.contains("id=\"north-layer-frame\"") && doc.contains("id=\"south-layer-frame\"")
+        );
+        assert_unique_ids(&doc);
+        assert!(doc.contains("translate(400,0)"));
+        // Each star is drawn once, in its own hemisphere
+        assert_eq!(doc.matches("class=\"star\"").count(), 2);
+    }
+
+    #[test]
+    fn stars_can_go_under_objects() {
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &[],
+        };
+        let render = |stars_under_objects: bool| {
+            let cfg = ChartConfig {
+                stars_under_objects,
+                ..Default::default()
+            };
+            let doc = Chart::new(data, cfg, None)
+                .draw_document()
+                .unwrap()
+                .to_string();
+            let at = |id: &str| doc.find(&format!("id=\"{id}\"")).unwrap();
+            at("layer-stars") < at("layer-objects")
+        };
+        assert!(!render(false));
+        assert!(render(true));
+    }
+
+    #[test]
+    fn grid_can_go_over_stars_and_objects() {
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &[],
+        };
+        let order = |grid_on_top: bool| {
+            let cfg = ChartConfig {
+                grid_on_top,
+                ..Default::default()
+            };
+            let doc = Chart::new(data, cfg, None)
+                .draw_document()
+                .unwrap()
+                .to_string();
+            let at = |id: &str| doc.find(&format!("id=\"{id}\"")).unwrap();
+            (
+                at("layer-grid") > at("layer-stars") && at("layer-grid") > at("layer-objects"),
+                at("layer-grid") < at("layer-labels"),
+            )
+        };
+        assert_eq!(order(false), (false, true));
+        assert_eq!(order(true), (true, true));
+    }
+
+    #[test]
+    fn scale_factor_multiplies_the_png_size() {
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &[],
+        };
+        let cfg = ChartConfig {
+            width: 600,
+            height: 800,
+            ..Default::default()
+        };
+        let png = Chart::new(data, cfg, None).to_png(2.0, 1).unwrap();
+        // The IHDR chunk holds the big-endian width and height
+        assert_eq!(&png[1..4], b"PNG");
+        let dimension = |i: usize| u32::from_be_bytes(png[i..i + 4].try_into().unwrap());
+        assert_eq!((dimension(16), dimension(20)), (1200, 1600));
+    }
+
+    // Decimal places of every number inside the document's attribute values
+    fn attribute_decimals(doc: &str) -> Vec<usize> {
+        doc.split("=\"")
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+            .flat_map(|value| value.split(|c: char| !(c.is_ascii_digit() || c == '.')))
+            .filter_map(|num| num.split_once('.').map(|(_, frac)| frac.len()))
+            .collect()
     }
 
-    pub fn to_file(&self, path: &str) -> std::io::Result<()> {
-        let doc = self.draw_document();
-        svg::save(path, &doc)
+    #[test]
+    fn coordinates_respect_the_configured_decimals() {
+        let stars = vec![make_star(1.234567, 2.345678, 1.0)];
+        let data = Datasets {
+            stars: &stars,
+            objects: &[],
+            constellations: &[],
+        };
+        let cfg = ChartConfig {
+            center: crate::types::EQPoint {
+                ra_deg: 0.123,
+                dec_deg: 0.456,
+            },
+            coord_decimals: 1,
+            corner_readouts: true,
+            ..Default::default()
+        };
+        let doc = Chart::new(data, cfg, None)
+            .draw_document()
+            .unwrap()
+            .to_string();
+        let decimals = attribute_decimals(&doc);
+        assert!(!decimals.is_empty());
+        assert!(decimals.iter().all(|&d| d <= 1), "{decimals:?}");
     }
 }
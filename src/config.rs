@@ -1,6 +1,12 @@
-use crate::types::{EQPoint, Projection};
+use serde::Serialize;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy)]
+use crate::data::default_catalog_priority;
+use crate::error::{ChartError, Result};
+use crate::observer::Observer;
+use crate::types::{EQPoint, Projection, RaDirection};
+
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct Margin {
     pub top: u32,
     pub bottom: u32,
@@ -8,30 +14,408 @@ pub struct Margin {
     pub right: u32,
 }
 impl Margin {
-    pub fn uniform(px: u32) -> Self {
+    pub fn new(top: u32, bottom: u32, left: u32, right: u32) -> Self {
         Self {
-            top: px,
-            bottom: px,
-            left: px,
-            right: px,
+            top,
+            bottom,
+            left,
+            right,
         }
     }
+
+    pub fn uniform(px: u32) -> Self {
+        Self::new(px, px, px, px)
+    }
+
+    /// `horizontal` on the left and right, `vertical` on the top and bottom
+    pub fn symmetric(horizontal: u32, vertical: u32) -> Self {
+        Self::new(vertical, vertical, horizontal, horizontal)
+    }
+
+    /// A fraction of a `width` by `height` canvas on each side: of the width
+    /// on the left and right, of the height on the top and bottom. However
+    /// small the canvas, each margin leaves at least a pixel of plot.
+    pub fn percentage(width: u32, height: u32, frac: f64) -> Self {
+        let frac = frac.clamp(0.0, 0.5);
+        let side = |dim: u32| ((dim as f64 * frac).round() as u32).min(dim.saturating_sub(1) / 2);
+        Self::symmetric(side(width), side(height))
+    }
+}
+
+/// How the chart center is marked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CenterMarker {
+    None,
+    Cross,
+    Dot,
+    Circle,
 }
 
-#[derive(Debug, Clone)]
+impl FromStr for CenterMarker {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "cross" => Ok(Self::Cross),
+            "dot" => Ok(Self::Dot),
+            "circle" => Ok(Self::Circle),
+            _ => Err(ChartError::Parse(format!("unknown center marker '{s}'"))),
+        }
+    }
+}
+
+/// How the smallest stars are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FaintStarStyle {
+    Circle,
+    /// A one-pixel square, which renderers don't drop or blur away
+    Point,
+}
+
+impl FromStr for FaintStarStyle {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "circle" => Ok(Self::Circle),
+            "point" => Ok(Self::Point),
+            _ => Err(ChartError::Parse(format!("unknown faint star style '{s}'"))),
+        }
+    }
+}
+
+/// A second, zoomed-in view drawn in a corner of the chart
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Inset {
+    pub center: EQPoint,
+    pub fov_deg: f64,
+}
+
+/// A great-circle arc drawn between two points, labeled with its length
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Arc {
+    pub from: EQPoint,
+    pub to: EQPoint,
+}
+
+/// A marked position, such as where a satellite will pass
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Crosshair {
+    pub position: EQPoint,
+    pub label: Option<String>,
+}
+
+/// A closed polygon on the sky, such as a survey footprint, with great-circle
+/// edges between its vertices
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Region {
+    pub vertices: Vec<EQPoint>,
+}
+
+/// Dec tick spacing out to a distance from the equator, in place of the
+/// frame's uniform spacing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DecTickBand {
+    /// The band covers Dec from minus to plus this, less any narrower band
+    pub max_abs_dec_deg: f64,
+    pub step_deg: f64,
+    /// Step between labeled ticks; None labels at the Dec grid step
+    pub label_step_deg: Option<f64>,
+}
+
+/// How the page is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PageLayout {
+    /// One chart around the configured center
+    Single,
+    /// The north and south celestial hemispheres as two stereographic
+    /// circles side by side
+    DualHemisphere,
+}
+
+impl FromStr for PageLayout {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "single" => Ok(Self::Single),
+            "dual-hemisphere" => Ok(Self::DualHemisphere),
+            _ => Err(ChartError::Parse(format!("unknown layout '{s}'"))),
+        }
+    }
+}
+
+/// Shape of the plot area
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipShape {
+    Rect,
+    /// The circle inscribed in the plot rectangle
+    Circle,
+}
+
+impl FromStr for ClipShape {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "rect" => Ok(Self::Rect),
+            "circle" => Ok(Self::Circle),
+            _ => Err(ChartError::Parse(format!("unknown clip shape '{s}'"))),
+        }
+    }
+}
+
+/// How the border around the plot area is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrameStyle {
+    /// Ticks and labels outside the border
+    Outer,
+    /// Ticks and labels inside the border, leaving the margins empty
+    Inner,
+    /// No border, ticks or labels
+    None,
+}
+
+impl FromStr for FrameStyle {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "outer" => Ok(Self::Outer),
+            "inner" => Ok(Self::Inner),
+            "none" => Ok(Self::None),
+            _ => Err(ChartError::Parse(format!("unknown frame style '{s}'"))),
+        }
+    }
+}
+
+/// What decides which star and object labels are placed first, and so
+/// which survive when there isn't room for them all
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LabelSort {
+    /// Brightest first
+    Magnitude,
+    /// Messier, then NGC, then IC, then other catalogs such as stars', each
+    /// brightest first
+    Catalog,
+    /// Deep-sky objects, then stars, each brightest first
+    Type,
+}
+
+impl FromStr for LabelSort {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "magnitude" => Ok(Self::Magnitude),
+            "catalog" => Ok(Self::Catalog),
+            "type" => Ok(Self::Type),
+            _ => Err(ChartError::Parse(format!("unknown label sort '{s}'"))),
+        }
+    }
+}
+
+/// Which plot dimension the field of view spans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fit {
+    Width,
+    Height,
+    /// The smaller dimension, so the whole field of view is drawn
+    Contain,
+    /// The larger dimension, so the plot is filled with sky
+    Cover,
+}
+
+impl FromStr for Fit {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "width" => Ok(Self::Width),
+            "height" => Ok(Self::Height),
+            // The field of view as the diameter of the inscribed circle
+            "contain" | "diameter" => Ok(Self::Contain),
+            "cover" => Ok(Self::Cover),
+            _ => Err(ChartError::Parse(format!("unknown fit '{s}'"))),
+        }
+    }
+}
+
+/// Estimated label width per character, as a multiple of the font size.
+/// About right for Verdana.
+pub const LABEL_CHAR_WIDTH_PER_PX: f64 = 0.6;
+/// Estimated label height, as a multiple of the font size
+pub const LABEL_LINE_HEIGHT_PER_PX: f64 = 1.2;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ChartConfig {
     pub center: EQPoint,
     pub position_angle_deg: f64,
+    /// One chart, or a pair of hemispheres each `width` wide
+    pub page_layout: PageLayout,
     pub projection: Projection,
     pub fov_deg: f64,
+    /// Drop points farther than this from the center, in degrees
+    pub max_zenith_deg: Option<f64>,
     pub width: u32,
     pub height: u32,
     pub margin: Margin,
-    pub step_ra_deg: u32,
-    pub step_dec_deg: u32,
+    /// Which way RA increases across the chart
+    pub ra_direction: RaDirection,
+    /// Which plot dimension the field of view spans
+    pub fit: Fit,
+    /// Shift of the projection center from the middle of the plot, as
+    /// fractions of the plot's width and height, positive right and down
+    pub center_offset: (f64, f64),
+    pub clip: ClipShape,
+    /// Which side of the border the ticks and labels go, or no frame
+    pub frame_style: FrameStyle,
+    /// Draw the plot-area layers without clipping them to it, for debugging
+    /// geometry that falls outside
+    pub no_clip: bool,
+    pub step_ra_deg: f64,
+    pub step_dec_deg: f64,
+    /// Lighter gridlines split each RA and Dec step into this many parts; 1
+    /// draws none
+    pub minor_grid_divisions: u32,
+    /// Dec tick spacing near the equator; beyond every band, and with none,
+    /// the frame ticks every 2°
+    pub dec_tick_bands: Vec<DecTickBand>,
+    /// Decimal places kept in SVG coordinates
+    pub coord_decimals: u32,
+    /// Sampling step in degrees for curved lines; None picks one from the FOV
+    pub sampling_step_deg: Option<u32>,
+    /// Drop points of grid, ecliptic and constellation lines that lie within
+    /// this many pixels of the line without them; 0 keeps them all
+    pub simplify_tolerance: f64,
     pub limit_star_mag: f64,
-    pub limit_object_mag: f64,
+    /// Dimmest magnitude of objects to draw, or None to pick one from the
+    /// field of view
+    pub limit_object_mag: Option<f64>,
     pub object_scale: f64,
+    /// Objects fainter than this magnitude are drawn with the `faint` class
+    pub faint_object_mag: Option<f64>,
+    /// Objects dimmer than this surface brightness, in magnitudes per square
+    /// arcminute, are left out whatever their total magnitude
+    pub limit_surface_brightness: Option<f64>,
+    /// Objects smaller than this across, in arcseconds, are left out
+    pub min_object_arcsec: Option<f64>,
+    /// Draw Messier objects with the `messier` class, for a heavier outline
+    pub emphasize_messier: bool,
+    /// Draw a line along the major axis of edge-on galaxies, to show their
+    /// orientation
+    pub galaxy_axis_lines: bool,
+    /// Weight of the magnitude term when sizing object symbols
+    pub object_weight_mag: f64,
+    /// Weight of the physical-size term when sizing object symbols
+    pub object_weight_size: f64,
+    /// Observer location and time, for horizon-aware charts
+    pub observer: Option<Observer>,
+    /// With an observer, drop everything below the horizon. Without one
+    /// there's no horizon, and it has no effect.
+    pub horizon_only: bool,
+    /// With an observer, drop everything below this altitude, in degrees
+    pub min_altitude_deg: Option<f64>,
+    /// With an observer, shade the twilight band from -18° up to the horizon
+    pub twilight_band: bool,
+    /// With an observer, shade the civil, nautical and astronomical twilight
+    /// zones separately, in place of the single band
+    pub twilight_zones: bool,
+    /// Draw only the stars and objects that get labels
+    pub only_labeled: bool,
+    /// Abbreviations of the only constellations to draw; empty draws them all
+    pub constellations_only: Vec<String>,
+    /// Mark where each constellation's name is anchored, for tuning where
+    /// the names go
+    pub show_constellation_centroids: bool,
+    /// Font size of star and object labels in pixels, overriding the
+    /// stylesheet's
+    pub label_font_size: Option<f64>,
+    /// Estimated label width per character, in pixels, for placing labels
+    /// clear of each other. About `LABEL_CHAR_WIDTH_PER_PX` times the font
+    /// size.
+    pub label_char_width: f64,
+    /// Estimated label height, in pixels
+    pub label_line_height: f64,
+    /// Clearance kept between labels and star or object symbols, in pixels
+    pub label_symbol_pad: f64,
+    /// Outline star and object labels in the background color
+    pub label_halo: bool,
+    /// Follow object labels with the object's designation in a second
+    /// catalog, as in "M 42 (NGC 1976)"
+    pub label_alt_catalog: bool,
+    /// Catalogs whose objects get labels, such as "M", matched against both
+    /// an object's designation and its alternate one. Empty labels them all.
+    /// The others are still drawn.
+    pub label_only_catalogs: Vec<String>,
+    /// The catalogs objects are known by, most preferred first, such as
+    /// `default_catalog_priority`. The objects take their designations from
+    /// it as they're loaded, so load them with the same list.
+    pub catalog_priority: Vec<String>,
+    /// Outline the boxes label placement steers around, and those of the
+    /// labels it places, to show why a label was or wasn't placed
+    pub label_collision_debug: bool,
+    /// Turn star and object labels to run along the local parallel, square
+    /// to the direction of north where they sit
+    pub align_labels_to_grid: bool,
+    /// Most star and object labels drawn in any one cell of the label grid
+    pub labels_per_cell: Option<usize>,
+    /// Cells across each side of the grid that `labels_per_cell` applies to
+    pub label_cells: u32,
+    /// Which labels are placed first
+    pub sort_labels_by: LabelSort,
+    /// Draw a soft halo behind the brightest stars
+    pub star_glow: bool,
+    /// Shade the plot by how many stars too faint to draw fall in each small
+    /// cell of it, on a log scale
+    pub density_shading: bool,
+    /// How stars too small for a clear circle are drawn
+    pub faint_star_style: FaintStarStyle,
+    /// Draw stars beneath objects instead of over them
+    pub stars_under_objects: bool,
+    /// Draw the grid over the stars and objects instead of under them
+    pub grid_on_top: bool,
+    /// Of stars falling within the same pixel, draw only the brightest
+    pub merge_coincident_stars: bool,
+    /// Draw double and multiple stars with the two-dot-and-bar symbol, the
+    /// dots as far apart as the components where the catalog says
+    pub double_stars: bool,
+    /// Draw an arrow from each star with a known proper motion to where it
+    /// will be after this many years
+    pub proper_motion_years: Option<f64>,
+    /// Marker drawn at the chart center
+    pub center_marker: CenterMarker,
+    /// Size of the center marker, in pixels
+    pub center_marker_size: f64,
+    /// Zoomed-in inset view
+    pub inset: Option<Inset>,
+    /// Great-circle arcs to draw
+    pub arcs: Vec<Arc>,
+    /// Positions marked with a cross, the size of the center marker
+    pub crosshairs: Vec<Crosshair>,
+    /// Polygons shaded on the sky
+    pub regions: Vec<Region>,
+    /// Print the RA/Dec of each plot corner outside the frame
+    pub corner_readouts: bool,
+    /// Crop the image to the symbols, lines and labels drawn in the plot area
+    pub autocrop: bool,
+    /// Record this configuration in the SVG's metadata
+    pub output_metadata: bool,
+    /// The most SVG elements a chart may have, or entries its JSON may list,
+    /// before drawing it is refused
+    pub max_output_size: usize,
+    /// Optional (lo, hi) RA band in degrees; wraps through 0° when lo > hi
+    pub ra_range: Option<(f64, f64)>,
+    /// Optional (lo, hi) Dec band in degrees
+    pub dec_range: Option<(f64, f64)>,
 }
 impl Default for ChartConfig {
     fn default() -> Self {
@@ -41,16 +425,106 @@ impl Default for ChartConfig {
                 dec_deg: 0.0,
             },
             position_angle_deg: 0.0,
+            page_layout: PageLayout::Single,
             projection: Projection::Gnomonic,
             fov_deg: 60.0,
+            max_zenith_deg: None,
             width: 800,
             height: 800,
             margin: Margin::uniform(40),
-            step_ra_deg: 15,
-            step_dec_deg: 10,
+            ra_direction: RaDirection::Left,
+            fit: Fit::Contain,
+            center_offset: (0.0, 0.0),
+            clip: ClipShape::Rect,
+            frame_style: FrameStyle::Outer,
+            no_clip: false,
+            step_ra_deg: 15.0,
+            step_dec_deg: 10.0,
+            minor_grid_divisions: 1,
+            dec_tick_bands: Vec::new(),
+            coord_decimals: 2,
+            sampling_step_deg: None,
+            simplify_tolerance: 0.0,
             limit_star_mag: 10.0,
-            limit_object_mag: 11.0,
+            limit_object_mag: Some(11.0),
             object_scale: 1.0,
+            faint_object_mag: None,
+            limit_surface_brightness: None,
+            min_object_arcsec: None,
+            emphasize_messier: false,
+            galaxy_axis_lines: false,
+            object_weight_mag: 1.0,
+            object_weight_size: 0.3,
+            observer: None,
+            horizon_only: false,
+            min_altitude_deg: None,
+            twilight_band: false,
+            twilight_zones: false,
+            only_labeled: false,
+            constellations_only: Vec::new(),
+            show_constellation_centroids: false,
+            label_font_size: None,
+            label_char_width: 7.0,
+            label_line_height: 12.0,
+            label_symbol_pad: 1.0,
+            label_halo: false,
+            label_alt_catalog: false,
+            label_only_catalogs: Vec::new(),
+            catalog_priority: default_catalog_priority(),
+            label_collision_debug: false,
+            align_labels_to_grid: false,
+            labels_per_cell: None,
+            label_cells: 4,
+            sort_labels_by: LabelSort::Magnitude,
+            star_glow: false,
+            density_shading: false,
+            faint_star_style: FaintStarStyle::Circle,
+            stars_under_objects: false,
+            grid_on_top: false,
+            merge_coincident_stars: false,
+            double_stars: false,
+            proper_motion_years: None,
+            center_marker: CenterMarker::Cross,
+            center_marker_size: 10.0,
+            inset: None,
+            arcs: Vec::new(),
+            crosshairs: Vec::new(),
+            regions: Vec::new(),
+            corner_readouts: false,
+            autocrop: false,
+            output_metadata: false,
+            max_output_size: 500_000,
+            ra_range: None,
+            dec_range: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sides(m: Margin) -> (u32, u32, u32, u32) {
+        (m.top, m.bottom, m.left, m.right)
+    }
+
+    #[test]
+    fn margin_new_takes_each_side() {
+        assert_eq!(sides(Margin::new(1, 2, 3, 4)), (1, 2, 3, 4));
+        assert_eq!(sides(Margin::uniform(5)), (5, 5, 5, 5));
+    }
+
+    #[test]
+    fn symmetric_margin_pairs_opposite_sides() {
+        assert_eq!(sides(Margin::symmetric(10, 20)), (20, 20, 10, 10));
+    }
+
+    #[test]
+    fn percentage_margin_scales_with_the_canvas() {
+        assert_eq!(sides(Margin::percentage(100, 100, 0.1)), (10, 10, 10, 10));
+        assert_eq!(sides(Margin::percentage(800, 400, 0.05)), (20, 20, 40, 40));
+        // A tiny canvas still keeps some plot
+        assert_eq!(sides(Margin::percentage(3, 1, 0.5)), (0, 0, 1, 1));
+        assert_eq!(sides(Margin::percentage(0, 0, 0.25)), (0, 0, 0, 0));
+    }
+}
@@ -1,6 +1,15 @@
-use crate::types::{CelestialObject, Constellation};
-use crate::{config::ChartConfig, layout::ChartLayout};
+use crate::geometry::{from_pixels, orient, project_within, to_pixels, unproject};
+use crate::types::{CelestialObject, Constellation, EQPoint, Point, Projection};
+use crate::{
+    config::{ChartConfig, ClipShape},
+    layout::ChartLayout,
+};
 
+/// The catalogs a chart draws from, borrowed rather than owned. Loading is
+/// the slow part, so to draw many charts, such as the pages of an atlas,
+/// load each catalog once and borrow it into every chart's `Datasets`.
+/// Nothing is copied, and a chart never changes its data.
+#[derive(Clone, Copy)]
 pub struct Datasets<'a> {
     pub stars: &'a [CelestialObject],
     pub objects: &'a [CelestialObject],
@@ -19,6 +28,189 @@ impl<'a> ChartContext<'a> {
         Self { data, cfg, layout }
     }
 
+    /// A context for the same datasets and settings, re-centered on `center`.
+    /// The layout doesn't depend on the center, so it's reused as-is.
+    pub fn with_center(&self, center: EQPoint) -> ChartContext<'a> {
+        let mut cfg = self.cfg.clone();
+        cfg.center = center;
+        Self {
+            data: self.data,
+            cfg,
+            layout: self.layout,
+        }
+    }
+
+    /// The altitude below which an observer's sky is culled, if any. The
+    /// horizon cull and `min_altitude_deg` combine to the higher of the two.
+    pub fn altitude_cutoff_deg(&self) -> Option<f64> {
+        self.cfg.observer?;
+        let horizon = self.cfg.horizon_only.then_some(0.0);
+        match (horizon, self.cfg.min_altitude_deg) {
+            (Some(h), Some(m)) => Some(f64::max(h, m)),
+            (h, m) => h.or(m),
+        }
+    }
+
+    /// Project an equatorial point into pixel space. This is the one place
+    /// every layer projects through, so chart-wide culls (like the observer's
+    /// horizon) apply consistently.
+    pub fn project_to_pixels(&self, eq: EQPoint) -> Option<Point> {
+        if let Some(cutoff) = self.altitude_cutoff_deg()
+            && let Some(observer) = self.cfg.observer
+            && observer.altitude_deg(eq) < cutoff
+        {
+            return None;
+        }
+        self.project_unculled(eq)
+    }
+
+    /// `project_to_pixels` without the altitude cull, for drawing the
+    /// horizon and the sky below it
+    pub(crate) fn project_unculled(&self, eq: EQPoint) -> Option<Point> {
+        let tp = project_within(
+            eq,
+            self.cfg.center,
+            self.cfg.projection,
+            self.cfg.position_angle_deg,
+            self.cfg.max_zenith_deg,
+        )?;
+        let tp = orient(tp, self.cfg.ra_direction);
+        Some(to_pixels(tp, self.layout.center_px, self.layout.scale))
+    }
+
+    /// Inverse of `project_to_pixels`: the equatorial point under a pixel,
+    /// or `None` where the projection doesn't reach
+    pub fn pixel_to_eq(&self, p: Point) -> Option<EQPoint> {
+        let tp = from_pixels(p, self.layout.center_px, self.layout.scale);
+        let tp = orient(tp, self.cfg.ra_direction);
+        unproject(
+            tp,
+            self.cfg.center,
+            self.cfg.projection,
+            self.cfg.position_angle_deg,
+        )
+    }
+
+    /// The greatest angular distance from the center to any part of the
+    /// plot, in degrees: that of its farthest corner. None when a corner lies
+    /// past the edge of the projection, or for cylindrical projections, where
+    /// distance from the center doesn't bound what's drawn.
+    pub fn field_radius_deg(&self) -> Option<f64> {
+        if self.cfg.projection == Projection::Mercator {
+            return None;
+        }
+        let l = &self.layout;
+        [
+            (l.plot_x, l.plot_y),
+            (l.plot_x + l.plot_w, l.plot_y),
+            (l.plot_x, l.plot_y + l.plot_h),
+            (l.plot_x + l.plot_w, l.plot_y + l.plot_h),
+        ]
+        .into_iter()
+        .map(|(x, y)| self.pixel_to_eq(Point { x, y }))
+        .try_fold(0.0, |farthest: f64, corner| {
+            Some(farthest.max(self.cfg.center.separation_deg(corner?)))
+        })
+    }
+
+    /// Whether a symbol of the given radius centered at `p` fits inside a
+    /// circular plot area. Symbols that would only be partly drawn are
+    /// dropped rather than left to the SVG clip path; a rectangular plot
+    /// area leaves everything to the clip path.
+    pub fn fits_plot(&self, p: Point, radius: f64) -> bool {
+        match self.cfg.clip {
+            ClipShape::Rect => true,
+            ClipShape::Circle => {
+                p.distance(self.layout.plot_center()) + radius <= self.layout.inscribed_radius()
+            }
+        }
+    }
+
+    /// The fractions of the loaded stars and of the loaded objects that
+    /// project inside the plot area, whatever their magnitude. Low fractions
+    /// for a chart meant to show a crowded region suggest it's mis-centered.
+    /// An empty catalog gives 0.
+    pub fn visible_fraction(&self) -> (f64, f64) {
+        let fraction = |bodies: &[CelestialObject]| {
+            if bodies.is_empty() {
+                return 0.0;
+            }
+            let visible = bodies
+                .iter()
+                .filter_map(|b| self.project_to_pixels(b.coords))
+                .filter(|&p| self.layout.contains(p) && self.fits_plot(p, 0.0))
+                .count();
+            visible as f64 / bodies.len() as f64
+        };
+        (fraction(self.data.stars), fraction(self.data.objects))
+    }
+
+    /// Whether an object is bright enough to draw, by total magnitude and by
+    /// surface brightness. Objects without a size only need the magnitude.
+    pub fn object_within_limits(&self, o: &CelestialObject) -> bool {
+        if o.magnitude > self.object_mag_limit() {
+            return false;
+        }
+        if let Some(min_arcsec) = self.cfg.min_object_arcsec
+            && o.size.major > 0.0
+            && o.angular_size_deg() * 3600.0 < min_arcsec
+        {
+            return false;
+        }
+        match (self.cfg.limit_surface_brightness, o.surface_brightness()) {
+            (Some(limit), Some(sb)) => sb <= limit,
+            _ => true,
+        }
+    }
+
+    /// Whether a point falls inside the configured RA/Dec clip ranges
+    pub fn in_sky_range(&self, eq: EQPoint) -> bool {
+        if let Some((lo, hi)) = self.cfg.ra_range
+            && !eq.in_ra_range(lo, hi)
+        {
+            return false;
+        }
+        if let Some((lo, hi)) = self.cfg.dec_range
+            && !eq.in_dec_range(lo, hi)
+        {
+            return false;
+        }
+        true
+    }
+
+    /// Round a pixel coordinate or length to the configured number of
+    /// decimals before it's written out as an SVG attribute
+    pub fn fmt_coord(&self, x: f64) -> f64 {
+        let f = 10f64.powi(self.cfg.coord_decimals as i32);
+        (x * f).round() / f
+    }
+
+    /// The dimmest magnitude of objects drawn: the configured limit, or
+    /// without one, `auto_object_mag_limit`
+    pub fn object_mag_limit(&self) -> f64 {
+        self.cfg
+            .limit_object_mag
+            .unwrap_or_else(|| self.auto_object_mag_limit())
+    }
+
+    /// Object magnitude limit based on FOV: narrow fields show fainter objects
+    pub fn auto_object_mag_limit(&self) -> f64 {
+        let fov_deg = self.cfg.fov_deg;
+        if fov_deg <= 5.0 {
+            14.0
+        } else if fov_deg <= 15.0 {
+            13.0
+        } else if fov_deg <= 30.0 {
+            12.0
+        } else if fov_deg <= 60.0 {
+            10.0
+        } else if fov_deg <= 120.0 {
+            9.0
+        } else {
+            8.0
+        }
+    }
+
     /// Adaptive step based on FOV
     pub fn adaptive_step_deg(&self) -> u32 {
         let fov_deg = self.cfg.fov_deg;
@@ -36,7 +228,174 @@ impl<'a> ChartContext<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::test_utils::make_context;
+    use crate::config::ChartConfig;
+    use crate::context::Datasets;
+    use crate::geometry::project;
+    use crate::observer::{parse_utc, Observer};
+    use crate::test_utils::{make_context, make_context_with, make_object, make_star};
+    use crate::types::{EQPoint, Projection};
+
+    #[test]
+    fn visible_fraction_is_higher_centered_on_a_crowd() {
+        // A cluster of stars on the celestial equator and a few scattered
+        // around the sky, none near the pole
+        let stars: Vec<_> = (0..40)
+            .map(|i| make_star(88.0 + (i % 8) as f64 * 0.5, -2.0 + (i / 8) as f64, 5.0))
+            .chain((0..8).map(|i| make_star(i as f64 * 45.0, 30.0, 5.0)))
+            .collect();
+        let objects = vec![make_object("galaxy", 90.0, 0.0, 8.0, 1.0)];
+        let fraction = |dec_deg| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &objects,
+                constellations: &[],
+            };
+            make_context_with(data, |cfg| {
+                cfg.center = EQPoint {
+                    ra_deg: 90.0,
+                    dec_deg,
+                }
+            })
+            .visible_fraction()
+        };
+
+        let (crowd_stars, crowd_objects) = fraction(0.0);
+        let (pole_stars, pole_objects) = fraction(90.0);
+        assert!(crowd_stars > 0.8 && crowd_objects == 1.0);
+        assert!(pole_stars < crowd_stars);
+        assert_eq!((pole_stars, pole_objects), (0.0, 0.0));
+        assert_eq!(make_context(|_| {}).visible_fraction(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn with_center_moves_projection_but_shares_datasets() {
+        let objects = vec![make_object("galaxy", 10.0, 5.0, 8.0, 1.0)];
+        let data = Datasets {
+            stars: &[],
+            objects: &objects,
+            constellations: &[],
+        };
+        let context = make_context_with(data, |_| {});
+        let moved = context.with_center(EQPoint {
+            ra_deg: 20.0,
+            dec_deg: -5.0,
+        });
+
+        assert!(std::ptr::eq(context.data.objects, moved.data.objects));
+        assert_eq!(moved.cfg.center.ra_deg, 20.0);
+        assert_eq!(moved.layout.scale, context.layout.scale);
+
+        let at = |c: &crate::context::ChartContext<'_>| {
+            project(
+                objects[0].coords,
+                c.cfg.center,
+                c.cfg.projection,
+                c.cfg.position_angle_deg,
+            )
+            .unwrap()
+        };
+        assert_ne!(at(&context), at(&moved));
+    }
+
+    #[test]
+    fn horizon_only_culls_stars_below_the_observer_horizon() {
+        // Canopus never rises from latitude 40°N (max altitude ≈ -2.7°),
+        // while Betelgeuse is well up on a January evening.
+        let canopus = EQPoint {
+            ra_deg: 95.988,
+            dec_deg: -52.696,
+        };
+        let betelgeuse = EQPoint {
+            ra_deg: 88.793,
+            dec_deg: 7.407,
+        };
+        let observer = Observer {
+            lat_deg: 40.0,
+            lon_deg: -75.0,
+            jd: parse_utc("2024-01-15T04:00:00Z").unwrap(),
+        };
+        assert!(observer.altitude_deg(canopus) < 0.0);
+        assert!(observer.altitude_deg(betelgeuse) > 0.0);
+
+        let patch = |horizon_only| {
+            move |cfg: &mut ChartConfig| {
+                cfg.projection = Projection::Stereographic;
+                cfg.center = betelgeuse;
+                cfg.fov_deg = 150.0;
+                cfg.observer = Some(observer);
+                cfg.horizon_only = horizon_only;
+            }
+        };
+        let culled = make_context(patch(true));
+        assert!(culled.project_to_pixels(canopus).is_none());
+        assert!(culled.project_to_pixels(betelgeuse).is_some());
+
+        // The projection itself could place it
+        assert!(make_context(patch(false))
+            .project_to_pixels(canopus)
+            .is_some());
+    }
+
+    #[test]
+    fn min_altitude_culls_stars_below_it() {
+        let observer = Observer {
+            lat_deg: 40.0,
+            lon_deg: -75.0,
+            jd: parse_utc("2024-01-15T04:00:00Z").unwrap(),
+        };
+        let low = observer.horizontal_to_eq(3.0, 120.0);
+        let high = observer.horizontal_to_eq(20.0, 120.0);
+        let context = |min_altitude_deg| {
+            make_context(|cfg| {
+                cfg.projection = Projection::AltAz;
+                cfg.center = observer.zenith();
+                cfg.fov_deg = 180.0;
+                cfg.observer = Some(observer);
+                cfg.min_altitude_deg = min_altitude_deg;
+            })
+        };
+        let culled = context(Some(10.0));
+        assert!(culled.project_to_pixels(low).is_none());
+        assert!(culled.project_to_pixels(high).is_some());
+        assert!(context(None).project_to_pixels(low).is_some());
+    }
+
+    #[test]
+    fn pixel_to_eq_round_trips_through_project_to_pixels() {
+        let context = make_context(|cfg| {
+            cfg.center = EQPoint {
+                ra_deg: 120.0,
+                dec_deg: 45.0,
+            };
+        });
+        let eq = EQPoint {
+            ra_deg: 130.0,
+            dec_deg: 50.0,
+        };
+        let p = context.project_to_pixels(eq).unwrap();
+        let back = context.pixel_to_eq(p).unwrap();
+        assert!((back.ra_deg - eq.ra_deg).abs() < 1e-9);
+        assert!((back.dec_deg - eq.dec_deg).abs() < 1e-9);
+        // The plot center is the chart center
+        let c = context.pixel_to_eq(context.layout.center_px).unwrap();
+        assert!((c.ra_deg - 120.0).abs() < 1e-9 && (c.dec_deg - 45.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn in_sky_range_combines_ra_and_dec_bands() {
+        let context = make_context(|cfg| {
+            cfg.ra_range = Some((350.0, 10.0));
+            cfg.dec_range = Some((-5.0, 5.0));
+        });
+        let p = |ra_deg, dec_deg| EQPoint { ra_deg, dec_deg };
+        assert!(context.in_sky_range(p(355.0, 0.0)));
+        assert!(context.in_sky_range(p(5.0, 5.0)));
+        assert!(!context.in_sky_range(p(5.0, 6.0)));
+        assert!(!context.in_sky_range(p(20.0, 0.0)));
+
+        // No ranges configured keeps everything
+        assert!(make_context(|_| {}).in_sky_range(p(200.0, -80.0)));
+    }
 
     #[test]
     fn adaptive_step_deg_matches_buckets_and_clamping() {
@@ -81,6 +440,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn auto_object_mag_limit_matches_buckets() {
+        let limit = |fov: f64| make_context(|cfg| cfg.fov_deg = fov).auto_object_mag_limit();
+        assert_eq!(limit(2.0), 14.0);
+        assert_eq!(limit(5.0), 14.0);
+        assert_eq!(limit(10.0), 13.0);
+        assert_eq!(limit(30.0), 12.0);
+        assert_eq!(limit(60.0), 10.0);
+        assert_eq!(limit(90.0), 9.0);
+        assert_eq!(limit(180.0), 8.0);
+    }
+
+    #[test]
+    fn auto_object_mag_limit_never_brightens_narrower_fields() {
+        let mut prev = f64::INFINITY;
+        for fov in (1..=360).map(|f| f as f64) {
+            let limit = make_context(|cfg| cfg.fov_deg = fov).auto_object_mag_limit();
+            assert!(limit <= prev, "fov={fov}: {limit} > {prev}");
+            prev = limit;
+        }
+    }
+
+    #[test]
+    fn auto_object_mag_applies_only_without_a_configured_limit() {
+        let context = make_context(|cfg| {
+            cfg.fov_deg = 10.0;
+            cfg.limit_object_mag = None;
+        });
+        assert_eq!(context.object_mag_limit(), 13.0);
+        let context = make_context(|cfg| cfg.fov_deg = 10.0);
+        assert_eq!(context.object_mag_limit(), 11.0);
+        assert_eq!(context.cfg.limit_object_mag, Some(11.0));
+    }
+
     #[test]
     fn adaptive_step_is_monotonic_non_decreasing_with_fov() {
         let fovs = [
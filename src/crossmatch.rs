@@ -0,0 +1,88 @@
+use crate::context::ChartContext;
+use crate::error::{ChartError, Result};
+use crate::json::objects_on_plot;
+
+// Catalogs with a column of their own, by the name the loader gives them
+const CATALOGS: [(&str, &str); 3] = [("messier", "M"), ("ngc", "NGC"), ("ic", "IC")];
+
+// The drawn objects' designations as CSV: the one each is labeled with and
+// its number in each catalog (blank where it has none). Columns are part of
+// the output format, like the JSON field names.
+pub(crate) fn crossmatch_csv(context: &ChartContext<'_>) -> Result<String> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    let mut header = vec!["designation"];
+    header.extend(CATALOGS.iter().map(|(column, _)| *column));
+    header.extend(["ra_deg", "dec_deg"]);
+    wtr.write_record(&header)?;
+    for (o, _) in objects_on_plot(context) {
+        let designation = [o.catalog.as_str(), o.identifier.as_str()]
+            .into_iter()
+            .filter(|part| !part.is_empty() && *part != "Unknown")
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut row = vec![designation];
+        row.extend(
+            CATALOGS
+                .iter()
+                .map(|(_, catalog)| o.designation_in(catalog).unwrap_or_default().to_string()),
+        );
+        row.extend([
+            format!("{:.5}", o.coords.ra_deg),
+            format!("{:.5}", o.coords.dec_deg),
+        ]);
+        wtr.write_record(&row)?;
+    }
+    let bytes = wtr
+        .into_inner()
+        .map_err(|e| ChartError::Io(e.into_error()))?;
+    Ok(String::from_utf8(bytes).expect("CSV of UTF-8 fields is UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_object, objects_context};
+
+    #[test]
+    fn rows_list_every_designation_of_drawn_objects() {
+        let mut m42 = make_object("bright-nebula", 0.0, 0.0, 4.0, 60.0);
+        m42.catalog = "M".to_string();
+        m42.identifier = "42".to_string();
+        m42.alt_designation = Some("NGC 1976".to_string());
+        let mut ic = make_object("galaxy", 1.0, 1.0, 8.0, 2.0);
+        ic.catalog = "IC".to_string();
+        ic.identifier = "434".to_string();
+        // Far outside the chart
+        let hidden = make_object("galaxy", 180.0, 0.0, 8.0, 2.0);
+        let objects = vec![m42, ic, hidden];
+        let csv = crossmatch_csv(&objects_context(&objects, |_| {})).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "designation,messier,ngc,ic,ra_deg,dec_deg",
+                "M 42,42,1976,,0.00000,0.00000",
+                "IC 434,,,434,1.00000,1.00000",
+            ]
+        );
+    }
+
+    #[test]
+    fn catalog_numbers_match_without_zero_padding() {
+        let objects = crate::data::load_objects(
+            None,
+            crate::data::ObjectOrder::Catalog,
+            &crate::data::default_catalog_priority(),
+        )
+        .unwrap();
+        let m31 = crate::data::find_object(&objects, "M31").unwrap();
+        let csv = crossmatch_csv(&objects_context(std::slice::from_ref(m31), |cfg| {
+            cfg.center = m31.coords
+        }))
+        .unwrap();
+        assert!(
+            csv.lines().nth(1).unwrap().starts_with("M 31,31,224,,"),
+            "{csv}"
+        );
+    }
+}
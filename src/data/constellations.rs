@@ -1,9 +1,10 @@
-use anyhow::Result;
 use csv::{Reader, ReaderBuilder, Trim};
 use phf::phf_map;
 use std::collections::HashMap;
+use std::str::FromStr;
 
-use crate::types::{hours_to_degrees, Constellation, EQPoint};
+use crate::error::{ChartError, Result};
+use crate::types::{hours_to_degrees, CelestialObject, Constellation, EQPoint};
 
 // Embed the constellation data
 pub const CONSTELLATIONS_CSV: &str = include_str!(concat!(
@@ -11,6 +12,35 @@ pub const CONSTELLATIONS_CSV: &str = include_str!(concat!(
     "/data/constellations.csv"
 ));
 
+// Embed a sample of the H.A. Rey figures, Cassiopeia and Leo so far. The
+// rest fall back to the modern figures.
+pub const CONSTELLATIONS_REY_SAMPLE_CSV: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/data/constellations_rey_sample.csv"
+));
+
+/// Constellation figure traditions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Figures {
+    /// IAU/Sky & Telescope figures (the embedded default)
+    Modern,
+    /// H.A. Rey's figures from "The Stars: A New Way to See Them", for the
+    /// constellations in the embedded sample, and modern ones for the rest
+    Rey,
+}
+
+impl FromStr for Figures {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "modern" => Ok(Self::Modern),
+            "rey" => Ok(Self::Rey),
+            _ => Err(ChartError::Parse(format!("unknown figures '{s}'"))),
+        }
+    }
+}
+
 static CONSTELLATION_NAMES: phf::Map<&'static str, &'static str> = phf_map! {
     "AND" => "Andromeda",
     "ANT" => "Antlia",
@@ -105,22 +135,154 @@ static CONSTELLATION_NAMES: phf::Map<&'static str, &'static str> = phf_map! {
     "VUL" => "Vulpecula",
 };
 
-/// Load constellations
-pub fn load_constellations(path: Option<&str>) -> Result<Vec<Constellation>> {
+/// Load constellations, either from a path override or from the embedded
+/// figure set. Load once and borrow the result into each chart's `Datasets`.
+pub fn load_constellations(path: Option<&str>, figures: Figures) -> Result<Vec<Constellation>> {
     if let Some(p) = path {
-        let rdr = ReaderBuilder::new()
-            .has_headers(false)
-            .flexible(true) // variable-length rows
-            .trim(Trim::All)
-            .from_path(p)?;
-        parse_constellations_from_reader(rdr)
-    } else {
-        let rdr = ReaderBuilder::new()
-            .has_headers(false)
-            .flexible(true) // variable-length rows
-            .trim(Trim::All)
-            .from_reader(CONSTELLATIONS_CSV.as_bytes());
-        parse_constellations_from_reader(rdr)
+        let by_abbr = parse_constellations_from_reader(reader().from_path(p)?)?;
+        return Ok(by_abbr.into_values().collect());
+    }
+
+    let mut by_abbr =
+        parse_constellations_from_reader(reader().from_reader(CONSTELLATIONS_CSV.as_bytes()))?;
+    if figures == Figures::Rey {
+        let rey = parse_constellations_from_reader(
+            reader().from_reader(CONSTELLATIONS_REY_SAMPLE_CSV.as_bytes()),
+        )?;
+        by_abbr.extend(rey);
+    }
+    Ok(by_abbr.into_values().collect())
+}
+
+fn reader() -> ReaderBuilder {
+    let mut builder = ReaderBuilder::new();
+    builder
+        .has_headers(false)
+        .flexible(true) // variable-length rows
+        .trim(Trim::All);
+    builder
+}
+
+/// Move each figure vertex onto the nearest star no fainter than `limit_mag`
+/// within `max_sep_deg`, so figure lines end exactly on the drawn stars.
+/// Vertices with no star nearby are left where they are.
+pub fn snap_to_stars(
+    constellations: &mut [Constellation],
+    stars: &[CelestialObject],
+    limit_mag: f64,
+    max_sep_deg: f64,
+) {
+    let index = StarIndex::new(stars, limit_mag, max_sep_deg);
+    for vertex in constellations
+        .iter_mut()
+        .flat_map(|c| c.lines.iter_mut())
+        .flatten()
+    {
+        if let Some(star) = index.nearest(*vertex) {
+            *vertex = star;
+        }
+    }
+}
+
+/// Find a constellation by its three-letter abbreviation or its full name,
+/// ignoring case
+pub fn find_constellation<'a>(
+    constellations: &'a [Constellation],
+    name: &str,
+) -> Result<&'a Constellation> {
+    constellations
+        .iter()
+        .find(|c| c.abbr.eq_ignore_ascii_case(name) || c.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| ChartError::UnresolvedTarget {
+            kind: "constellation",
+            name: name.to_string(),
+        })
+}
+
+// How much wider than the figure itself a framed chart's field of view is
+const FRAME_MARGIN: f64 = 1.2;
+
+/// A center and field of view that frame a constellation's figure. The
+/// center is the spherical midpoint of its vertices, and the field of view
+/// reaches the farthest vertex with some margin.
+pub fn frame_constellation(c: &Constellation) -> (EQPoint, f64) {
+    let vertices = || c.lines.iter().flatten();
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+    for v in vertices() {
+        let (ra, dec) = (v.ra_deg.to_radians(), v.dec_deg.to_radians());
+        x += dec.cos() * ra.cos();
+        y += dec.cos() * ra.sin();
+        z += dec.sin();
+    }
+    let center = EQPoint {
+        ra_deg: y.atan2(x).to_degrees().rem_euclid(360.0),
+        dec_deg: z.atan2(x.hypot(y)).to_degrees(),
+    };
+    let radius = vertices()
+        .map(|v| center.separation_deg(*v))
+        .fold(0.0, f64::max);
+    (center, 2.0 * radius * FRAME_MARGIN)
+}
+
+// Stars bucketed into declination bands one search radius tall, each sorted
+// by RA, so a lookup only scans a narrow RA window in three bands.
+struct StarIndex {
+    bands: HashMap<i64, Vec<EQPoint>>,
+    radius: f64,
+}
+
+impl StarIndex {
+    fn new(stars: &[CelestialObject], limit_mag: f64, radius: f64) -> Self {
+        let mut bands: HashMap<i64, Vec<EQPoint>> = HashMap::new();
+        for s in stars.iter().filter(|s| s.magnitude <= limit_mag) {
+            let coords = EQPoint {
+                ra_deg: s.coords.ra_deg.rem_euclid(360.0),
+                dec_deg: s.coords.dec_deg,
+            };
+            bands
+                .entry(Self::band(coords.dec_deg, radius))
+                .or_default()
+                .push(coords);
+        }
+        for band in bands.values_mut() {
+            band.sort_by(|a, b| a.ra_deg.total_cmp(&b.ra_deg));
+        }
+        Self { bands, radius }
+    }
+
+    fn band(dec_deg: f64, radius: f64) -> i64 {
+        (dec_deg / radius).floor() as i64
+    }
+
+    fn nearest(&self, p: EQPoint) -> Option<EQPoint> {
+        // How far the search circle reaches in RA at its most poleward edge
+        let max_dec = (p.dec_deg.abs() + self.radius).min(90.0);
+        let ra_reach = if max_dec >= 89.9 {
+            180.0
+        } else {
+            (self.radius / max_dec.to_radians().cos()).min(180.0)
+        };
+        let ra = p.ra_deg.rem_euclid(360.0);
+        let windows = [
+            (ra - ra_reach, ra + ra_reach),
+            (ra - ra_reach + 360.0, ra + ra_reach + 360.0),
+            (ra - ra_reach - 360.0, ra + ra_reach - 360.0),
+        ];
+
+        let band = Self::band(p.dec_deg, self.radius);
+        let mut best: Option<(f64, EQPoint)> = None;
+        for stars in (band - 1..=band + 1).filter_map(|b| self.bands.get(&b)) {
+            for (lo, hi) in windows {
+                let start = stars.partition_point(|s| s.ra_deg < lo);
+                for &s in stars[start..].iter().take_while(|s| s.ra_deg <= hi) {
+                    let sep = p.separation_deg(s);
+                    if sep <= self.radius && best.is_none_or(|(d, _)| sep < d) {
+                        best = Some((sep, s));
+                    }
+                }
+            }
+        }
+        best.map(|(_, s)| s)
     }
 }
 
@@ -130,7 +292,7 @@ pub fn load_constellations(path: Option<&str>) -> Result<Vec<Constellation>> {
 // each row.
 fn parse_constellations_from_reader<R: std::io::Read>(
     mut rdr: Reader<R>,
-) -> Result<Vec<Constellation>> {
+) -> Result<HashMap<String, Constellation>> {
     let mut by_abbr: HashMap<String, Constellation> = HashMap::new();
 
     for result in rdr.records() {
@@ -149,6 +311,7 @@ fn parse_constellations_from_reader<R: std::io::Read>(
         let entry = by_abbr
             .entry(abbr.clone())
             .or_insert_with(|| Constellation {
+                abbr: abbr.clone(),
                 name: name.to_string(),
                 lines: Vec::new(),
             });
@@ -159,12 +322,16 @@ fn parse_constellations_from_reader<R: std::io::Read>(
         while i + 1 < rec.len() {
             let ra_s = rec.get(i).unwrap_or("").trim();
             let dec_s = rec.get(i + 1).unwrap_or("").trim();
-            if !(ra_s.is_empty() || dec_s.is_empty()) {
-                if let (Ok(ra_h), Ok(dec_deg)) = (ra_s.parse::<f64>(), dec_s.parse::<f64>()) {
-                    line.push(EQPoint {
-                        ra_deg: hours_to_degrees(ra_h),
-                        dec_deg,
-                    });
+            if !(ra_s.is_empty() || dec_s.is_empty())
+                && let (Ok(ra_h), Ok(dec_deg)) = (ra_s.parse::<f64>(), dec_s.parse::<f64>())
+            {
+                let p = EQPoint {
+                    ra_deg: hours_to_degrees(ra_h),
+                    dec_deg,
+                }
+                .with_wrapped_ra();
+                if p.is_valid() {
+                    line.push(p);
                 }
             }
             i += 2;
@@ -175,23 +342,138 @@ fn parse_constellations_from_reader<R: std::io::Read>(
         }
     }
 
-    Ok(by_abbr.into_values().collect())
+    Ok(by_abbr)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test_utils::approx;
-    use csv::ReaderBuilder;
 
     // Create a CSV reader from a string and parse it for testing
     fn parse_from_str(s: &str) -> Vec<Constellation> {
-        let rdr = ReaderBuilder::new()
-            .has_headers(false)
-            .flexible(true)
-            .trim(Trim::All)
-            .from_reader(s.as_bytes());
-        parse_constellations_from_reader(rdr).expect("parse constellations")
+        let rdr = reader().from_reader(s.as_bytes());
+        parse_constellations_from_reader(rdr)
+            .expect("parse constellations")
+            .into_values()
+            .collect()
+    }
+
+    fn line_count(constellations: &[Constellation], name: &str) -> usize {
+        constellations
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.lines.len())
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn snapping_moves_vertices_onto_the_nearest_bright_star() {
+        let star = |ra_deg: f64, dec_deg: f64, magnitude: f64| CelestialObject {
+            kind: "star".to_string(),
+            catalog: "HIP".to_string(),
+            identifier: "1".to_string(),
+            coords: EQPoint { ra_deg, dec_deg },
+            magnitude,
+            size: crate::types::Size::zero(),
+            angle: 0.0,
+            name: String::new(),
+            alt_designation: None,
+            proper_motion: None,
+            multiplicity: None,
+        };
+        let stars = vec![
+            star(10.1, 20.1, 3.0),
+            star(10.3, 20.0, 3.0),
+            // Closest, but too faint to be drawn
+            star(10.0, 20.01, 9.0),
+            // Across the RA wrap
+            star(359.9, 0.0, 2.0),
+        ];
+        let mut constellations = vec![Constellation {
+            abbr: "TST".to_string(),
+            name: "Test".to_string(),
+            lines: vec![vec![
+                EQPoint {
+                    ra_deg: 10.0,
+                    dec_deg: 20.0,
+                },
+                EQPoint {
+                    ra_deg: 0.1,
+                    dec_deg: 0.1,
+                },
+                EQPoint {
+                    ra_deg: 50.0,
+                    dec_deg: -30.0,
+                },
+            ]],
+        }];
+        snap_to_stars(&mut constellations, &stars, 6.5, 0.5);
+
+        let line = &constellations[0].lines[0];
+        assert_eq!((line[0].ra_deg, line[0].dec_deg), (10.1, 20.1));
+        assert_eq!((line[1].ra_deg, line[1].dec_deg), (359.9, 0.0));
+        // Nothing within reach: unchanged
+        assert_eq!((line[2].ra_deg, line[2].dec_deg), (50.0, -30.0));
+    }
+
+    #[test]
+    fn framing_orion_centers_on_it_and_covers_every_vertex() {
+        let constellations = load_constellations(None, Figures::Modern).unwrap();
+        let orion = find_constellation(&constellations, "ori").unwrap();
+        assert_eq!(orion.name, "Orion");
+        assert!(std::ptr::eq(
+            find_constellation(&constellations, "Orion").unwrap(),
+            orion
+        ));
+        assert!(matches!(
+            find_constellation(&constellations, "Orio"),
+            Err(ChartError::UnresolvedTarget {
+                kind: "constellation",
+                ..
+            })
+        ));
+
+        let (center, fov) = frame_constellation(orion);
+        // Near the belt, about 5h35m +5°
+        assert!(approx(center.ra_deg, 84.0, 3.0), "{center:?}");
+        assert!(approx(center.dec_deg, 5.0, 5.0), "{center:?}");
+        for v in orion.lines.iter().flatten() {
+            assert!(center.separation_deg(*v) < fov / 2.0);
+        }
+        assert!(fov < 70.0, "{fov}");
+    }
+
+    #[test]
+    fn framing_handles_figures_across_ra_zero() {
+        let c = Constellation {
+            abbr: "TST".to_string(),
+            name: "Test".to_string(),
+            lines: vec![vec![
+                EQPoint {
+                    ra_deg: 355.0,
+                    dec_deg: 0.0,
+                },
+                EQPoint {
+                    ra_deg: 5.0,
+                    dec_deg: 0.0,
+                },
+            ]],
+        };
+        let (center, fov) = frame_constellation(&c);
+        assert!(center.ra_deg < 1e-9 || center.ra_deg > 360.0 - 1e-9);
+        assert!(approx(fov, 2.0 * 5.0 * FRAME_MARGIN, 1e-9));
+    }
+
+    #[test]
+    fn figures_select_the_embedded_dataset() {
+        let modern = load_constellations(None, Figures::Modern).unwrap();
+        let rey = load_constellations(None, Figures::Rey).unwrap();
+        assert_eq!(line_count(&modern, "Leo"), 4);
+        assert_eq!(line_count(&rey, "Leo"), 6);
+        // Constellations without a Rey figure keep the modern one
+        assert_eq!(line_count(&rey, "Orion"), line_count(&modern, "Orion"));
+        assert_eq!(modern.len(), rey.len());
     }
 
     #[test]
@@ -2,6 +2,10 @@ pub mod constellations;
 pub mod objects;
 pub mod stars;
 
-pub use constellations::load_constellations;
-pub use objects::load_objects;
-pub use stars::load_stars;
+pub use constellations::{
+    find_constellation, frame_constellation, load_constellations, snap_to_stars, Figures,
+};
+pub use objects::{
+    default_catalog_priority, find_object, load_objects, merge_objects, ObjectOrder,
+};
+pub use stars::{load_stars, load_stars_filtered, StarFormat};
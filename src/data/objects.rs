@@ -1,9 +1,10 @@
-use anyhow::Result;
 use csv::{Reader, ReaderBuilder};
 use flate2::read::GzDecoder;
 use serde::Deserialize;
+use std::borrow::Borrow;
 use std::collections::HashMap;
 
+use crate::error::{ChartError, Result};
 use crate::types::{
     hours_to_degrees, parse_dms, parse_hms, parse_or, sexagesimal_dms_to_degrees,
     sexagesimal_hms_to_hours, CelestialObject, EQPoint, Size,
@@ -49,7 +50,8 @@ fn ngc_type_map() -> HashMap<&'static str, usize> {
         ("*", 0),
         ("**", 1),
         ("***", 2),
-        ("*Ass", 9),
+        // Stellar associations and star clouds are patches of the Milky Way
+        ("*Ass", 8),
         ("OCl", 4),
         ("GCl", 5),
         ("Cl+N", 4),
@@ -83,15 +85,36 @@ static OBJECT_TYPES: [&str; 10] = [
     "not-used",
 ];
 
-pub fn load_objects(path: Option<&str>) -> Result<Vec<CelestialObject>> {
-    if let Some(p) = path {
+/// The order loaded objects are listed in. `ObjectsLayer` draws the
+/// brightest on top whatever the order, so this only matters to code that
+/// reads the list itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectOrder {
+    /// As the catalog lists them
+    Catalog,
+    /// Faintest first, by magnitude
+    FaintestFirst,
+}
+
+/// Load deep-sky objects from an OpenNGC-format file, or from the embedded
+/// catalog. Each is known by the first catalog in `catalog_priority` that
+/// lists it, such as `default_catalog_priority`. Load once and borrow the
+/// result into each chart's `Datasets`.
+pub fn load_objects(
+    path: Option<&str>,
+    order: ObjectOrder,
+    catalog_priority: &[String],
+) -> Result<Vec<CelestialObject>> {
+    let mut objects = if let Some(p) = path {
         let rdr = ReaderBuilder::new().delimiter(b';').from_path(p)?;
-        parse_objects_from_reader(rdr)
+        parse_objects_from_reader(rdr, catalog_priority)?
     } else {
         let gz = GzDecoder::new(NGC_CSV_GZ);
         let rdr = ReaderBuilder::new().delimiter(b';').from_reader(gz);
-        parse_objects_from_reader(rdr)
-    }
+        parse_objects_from_reader(rdr, catalog_priority)?
+    };
+    order.apply(&mut objects);
+    Ok(objects)
 }
 
 /// Extract the first run of ASCII digits from a string, if any.
@@ -114,7 +137,8 @@ fn first_number(s: &str) -> Option<String> {
 }
 
 /// Parse leading "NGC####" / "IC####" from Name.
-/// Returns (catalog, number) when recognized; otherwise None.
+/// Returns (catalog, number) when recognized; otherwise None. OpenNGC pads
+/// the numbers to four digits, which is dropped, so "NGC0224" is ("NGC", "224").
 fn parse_catalog_number_from_name(name: &str) -> Option<(String, String)> {
     let s = name.trim();
     if s.len() < 3 {
@@ -126,18 +150,28 @@ fn parse_catalog_number_from_name(name: &str) -> Option<(String, String)> {
             .map(|x| x.eq_ignore_ascii_case(prefix))
             .unwrap_or(false)
     };
+    let unpadded = |n: String| match n.trim_start_matches('0') {
+        "" => "0".to_string(),
+        trimmed => trimmed.to_string(),
+    };
     if starts_with("NGC") {
         if let Some(n) = first_number(s[3..].trim_start()) {
-            return Some(("NGC".to_string(), n));
-        }
-    } else if starts_with("IC") {
-        if let Some(n) = first_number(s[2..].trim_start()) {
-            return Some(("IC".to_string(), n));
+            return Some(("NGC".to_string(), unpadded(n)));
         }
+    } else if starts_with("IC")
+        && let Some(n) = first_number(s[2..].trim_start())
+    {
+        return Some(("IC".to_string(), unpadded(n)));
     }
     None
 }
 
+/// The catalogs objects are known by, most preferred first: Messier, then
+/// NGC, then IC
+pub fn default_catalog_priority() -> Vec<String> {
+    ["M", "NGC", "IC"].map(String::from).to_vec()
+}
+
 /// Choose: M column first if it has a number,
 /// otherwise parse name for NGC/IC,
 /// finally fallback to just the name
@@ -152,7 +186,42 @@ fn choose_catalog_and_identifier(m: &Option<String>, name: &str) -> (String, Str
     ("Unknown".to_string(), name.trim().to_string())
 }
 
-fn parse_objects_from_reader<R: std::io::Read>(mut rdr: Reader<R>) -> Result<Vec<CelestialObject>> {
+/// The NGC or IC designation of an object cataloged under its Messier
+/// number, such as "NGC 1976" for M 42. Objects known by their NGC or IC
+/// number already have none.
+fn alternate_designation(catalog: &str, name: &str) -> Option<String> {
+    if catalog != "M" {
+        return None;
+    }
+    parse_catalog_number_from_name(name).map(|(cat, id)| format!("{cat} {id}"))
+}
+
+/// Swap an object's designation for its alternate when `priority` ranks
+/// the alternate's catalog higher, so with NGC first M 42 is known as
+/// NGC 1976. Catalogs missing from `priority` rank after those in it.
+fn prefer_catalog(o: &mut CelestialObject, priority: &[String]) {
+    let rank = |catalog: &str| {
+        priority
+            .iter()
+            .position(|p| p.eq_ignore_ascii_case(catalog))
+            .unwrap_or(priority.len())
+    };
+    let Some((catalog, identifier)) = o.alt_designation.as_deref().and_then(|a| a.split_once(' '))
+    else {
+        return;
+    };
+    if rank(catalog) < rank(&o.catalog) {
+        let (catalog, identifier) = (catalog.to_string(), identifier.to_string());
+        o.alt_designation = Some(format!("{} {}", o.catalog, o.identifier));
+        o.catalog = catalog;
+        o.identifier = identifier;
+    }
+}
+
+fn parse_objects_from_reader<R: std::io::Read>(
+    mut rdr: Reader<R>,
+    priority: &[String],
+) -> Result<Vec<CelestialObject>> {
     let mut out = Vec::new();
     let type_map = ngc_type_map();
 
@@ -194,36 +263,200 @@ fn parse_objects_from_reader<R: std::io::Read>(mut rdr: Reader<R>) -> Result<Vec
         };
         let ra_deg = hours_to_degrees(sexagesimal_hms_to_hours(h, m, s));
         let dec_deg = sexagesimal_dms_to_degrees(d, dm, ds);
+        let coords = EQPoint { ra_deg, dec_deg }.with_wrapped_ra();
+        if !coords.is_valid() {
+            continue;
+        }
 
         // Chose which catalog and label to use for this object.
         // This simply prefers the Messier identifier if it exists.
         let (catalog, identifier) = choose_catalog_and_identifier(&row.m, &row.name);
+        let alt_designation = alternate_designation(&catalog, &row.name);
 
-        out.push(CelestialObject {
+        let mut object = CelestialObject {
             kind: kind.to_string(),
-            catalog: catalog,
-            identifier: identifier,
-            coords: EQPoint { ra_deg, dec_deg },
-            magnitude: magnitude,
-            size: size,
-            angle: angle,
+            catalog,
+            identifier,
+            coords,
+            magnitude,
+            size,
+            angle,
             name: String::new(),
-        });
+            alt_designation,
+            proper_motion: None,
+            multiplicity: None,
+        };
+        prefer_catalog(&mut object, priority);
+        out.push(object);
     }
 
-    // Sort by magnitude, reverse=True, for drawing later
-    out.sort_by(|a, b| {
-        a.magnitude
-            .partial_cmp(&b.magnitude)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
-    out.reverse();
     Ok(out)
 }
 
+impl ObjectOrder {
+    fn apply(self, objects: &mut [CelestialObject]) {
+        if self == ObjectOrder::FaintestFirst {
+            sort_faintest_first(objects);
+        }
+    }
+}
+
+// By magnitude, faintest first, for drawing the brightest on top. The sort is
+// stable, so objects equally bright keep their listed order; sorting
+// brightest first and reversing, as the loader once did, turned them around.
+pub(crate) fn sort_faintest_first<T: Borrow<CelestialObject>>(objects: &mut [T]) {
+    objects.sort_by(|a, b| b.borrow().magnitude.total_cmp(&a.borrow().magnitude));
+}
+
+// Objects closer than this are taken to be the same one, listed twice
+const SAME_POSITION_DEG: f64 = 30.0 / 3600.0;
+
+/// Combine a catalog with a supplementary list. An object in `primary` that
+/// `extra` also lists, by catalog and identifier or by position, is replaced
+/// by the `extra` entry. In catalog order, the rest of `primary` comes first.
+pub fn merge_objects(
+    primary: Vec<CelestialObject>,
+    extra: Vec<CelestialObject>,
+    order: ObjectOrder,
+) -> Vec<CelestialObject> {
+    let mut out: Vec<CelestialObject> = primary
+        .into_iter()
+        .filter(|o| {
+            !extra.iter().any(|e| {
+                (e.catalog == o.catalog && e.identifier == o.identifier)
+                    || e.coords.separation_deg(o.coords) < SAME_POSITION_DEG
+            })
+        })
+        .collect();
+    out.extend(extra);
+    order.apply(&mut out);
+    out
+}
+
+/// Find an object by its designation, such as "M31", "M 31" or "NGC 7000",
+/// its alternate designation, or its name, ignoring case
+pub fn find_object<'a>(objects: &'a [CelestialObject], query: &str) -> Result<&'a CelestialObject> {
+    let unresolved = || ChartError::UnresolvedTarget {
+        kind: "object",
+        name: query.to_string(),
+    };
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(unresolved());
+    }
+    let split = query
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(query.len());
+    let (catalog, identifier) = (&query[..split], query[split..].trim_start());
+    let identifier = identifier.trim_start_matches('0');
+    let is =
+        |c: &str, id: &str| c.eq_ignore_ascii_case(catalog) && id.eq_ignore_ascii_case(identifier);
+    objects.iter().find(|o| {
+        is(&o.catalog, &o.identifier)
+            // So "M 42" finds the Orion Nebula whichever catalog names it
+            || o.alt_designation.as_deref().and_then(|a| a.split_once(' ')).is_some_and(|(c, id)| is(c, id))
+            || o.name.eq_ignore_ascii_case(query)
+            // Objects outside the catalogs are known by their full name
+            || (o.catalog == "Unknown" && o.identifier.eq_ignore_ascii_case(query))
+    })
+    .ok_or_else(unresolved)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::make_object;
+
+    #[test]
+    fn stellar_associations_map_to_milky_way() {
+        let type_map = ngc_type_map();
+        assert_eq!(OBJECT_TYPES[type_map["*Ass"]], "milky-way");
+    }
+
+    #[test]
+    fn merging_prefers_the_extra_list() {
+        let object = |catalog: &str, identifier: &str, ra_deg: f64, magnitude: f64| {
+            let mut o = make_object("galaxy", ra_deg, 0.0, magnitude, 0.0);
+            o.catalog = catalog.to_string();
+            o.identifier = identifier.to_string();
+            o
+        };
+        let primary = vec![
+            object("NGC", "4565", 10.0, 10.4),
+            object("NGC", "1", 20.0, 12.0),
+            object("NGC", "2", 30.0, 13.0),
+        ];
+        let extra = vec![
+            // Same NGC number, with a corrected magnitude
+            object("NGC", "4565", 10.001, 9.6),
+            // Unnamed, but at the same place as NGC 2
+            object("Unknown", "Nova", 30.0 + 5.0 / 3600.0, 8.0),
+            object("Unknown", "Custom", 40.0, 11.0),
+        ];
+
+        let merged = merge_objects(primary, extra, ObjectOrder::FaintestFirst);
+        let ids: Vec<&str> = merged.iter().map(|o| o.identifier.as_str()).collect();
+        assert_eq!(ids, vec!["1", "Custom", "4565", "Nova"]);
+        assert_eq!(merged[2].magnitude, 9.6);
+    }
+
+    #[test]
+    fn faintest_first_keeps_equally_bright_objects_in_listed_order() {
+        let mut objects: Vec<CelestialObject> = [("a", 9.0), ("b", 12.0), ("c", 9.0)]
+            .into_iter()
+            .map(|(identifier, magnitude)| {
+                let mut o = make_object("galaxy", 0.0, 0.0, magnitude, 0.0);
+                o.identifier = identifier.to_string();
+                o
+            })
+            .collect();
+        sort_faintest_first(&mut objects);
+        let ids: Vec<&str> = objects.iter().map(|o| o.identifier.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+    }
+
+    #[test]
+    fn merging_in_catalog_order_lists_the_rest_of_the_catalog_first() {
+        let object = |identifier: &str, ra_deg: f64, magnitude: f64| {
+            let mut o = make_object("galaxy", ra_deg, 0.0, magnitude, 0.0);
+            o.identifier = identifier.to_string();
+            o
+        };
+        let primary = vec![object("4565", 10.0, 10.4), object("1", 20.0, 12.0)];
+        let extra = vec![object("4565", 10.0, 9.6), object("Custom", 40.0, 11.0)];
+
+        let merged = merge_objects(primary, extra, ObjectOrder::Catalog);
+        let ids: Vec<&str> = merged.iter().map(|o| o.identifier.as_str()).collect();
+        assert_eq!(ids, vec!["1", "4565", "Custom"]);
+    }
+
+    #[test]
+    fn objects_are_found_by_designation_or_name() {
+        let object = |catalog: &str, identifier: &str, name: &str| {
+            let mut o = make_object("bright-nebula", 0.0, 0.0, 4.0, 0.0);
+            o.catalog = catalog.to_string();
+            o.identifier = identifier.to_string();
+            o.name = name.to_string();
+            o
+        };
+        let objects = vec![
+            object("M", "42", "Orion Nebula"),
+            object("NGC", "7000", ""),
+            object("Unknown", "SH2123", ""),
+        ];
+        let found = |q| find_object(&objects, q).ok().map(|o| o.identifier.as_str());
+        assert_eq!(found("M42"), Some("42"));
+        assert_eq!(found(" m 042 "), Some("42"));
+        assert_eq!(found("orion nebula"), Some("42"));
+        assert_eq!(found("NGC7000"), Some("7000"));
+        assert_eq!(found("sh2123"), Some("SH2123"));
+        assert_eq!(found("M4"), None);
+        assert_eq!(found(""), None);
+        assert_eq!(
+            find_object(&objects, "M4").unwrap_err().to_string(),
+            "unknown object 'M4'"
+        );
+    }
 
     #[test]
     fn messier_from_m_string() {
@@ -238,6 +471,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ngc_and_ic_numbers_lose_their_zero_padding() {
+        assert_eq!(
+            parse_catalog_number_from_name("NGC0224"),
+            Some(("NGC".into(), "224".into()))
+        );
+        assert_eq!(
+            parse_catalog_number_from_name("IC0010"),
+            Some(("IC".into(), "10".into()))
+        );
+        assert_eq!(
+            alternate_designation("M", "NGC0224"),
+            Some("NGC 224".to_string())
+        );
+
+        // Found in the embedded catalog with or without the padding
+        let objects =
+            load_objects(None, ObjectOrder::Catalog, &default_catalog_priority()).unwrap();
+        let found = |q| {
+            let o = find_object(&objects, q).unwrap();
+            format!("{} {}", o.catalog, o.identifier)
+        };
+        assert_eq!(found("NGC 891"), "NGC 891");
+        assert_eq!(found("NGC0891"), "NGC 891");
+        assert_eq!(found("NGC 224"), "M 31");
+        assert_eq!(found("NGC 0224"), "M 31");
+    }
+
     #[test]
     fn parse_ngc_ic_from_name_when_no_m() {
         assert_eq!(
@@ -250,6 +511,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn messier_objects_keep_their_ngc_or_ic_number() {
+        assert_eq!(
+            alternate_designation("M", "NGC 1976"),
+            Some("NGC 1976".to_string())
+        );
+        assert_eq!(
+            alternate_designation("M", "IC1434"),
+            Some("IC 1434".to_string())
+        );
+        assert_eq!(alternate_designation("NGC", "NGC 7000"), None);
+        assert_eq!(alternate_designation("M", "Mel022"), None);
+    }
+
+    #[test]
+    fn catalog_priority_picks_the_designation() {
+        let m42 = |priority: &[&str]| {
+            let priority: Vec<String> = priority.iter().map(|p| p.to_string()).collect();
+            let mut o = crate::test_utils::make_object("bright-nebula", 0.0, 0.0, 4.0, 60.0);
+            o.catalog = "M".to_string();
+            o.identifier = "42".to_string();
+            o.alt_designation = Some("NGC 1976".to_string());
+            prefer_catalog(&mut o, &priority);
+            (o.catalog, o.identifier, o.alt_designation)
+        };
+        assert_eq!(
+            m42(&["NGC", "M", "IC"]),
+            ("NGC".into(), "1976".into(), Some("M 42".to_string()))
+        );
+        assert_eq!(
+            m42(&["M", "NGC", "IC"]),
+            ("M".into(), "42".into(), Some("NGC 1976".to_string()))
+        );
+        // Catalogs left out of the list come after those in it
+        assert_eq!(m42(&["ngc"]).0, "NGC");
+        assert_eq!(m42(&[]).0, "M");
+    }
+
+    #[test]
+    fn alternate_designations_find_objects_under_ngc_first() {
+        let mut orion = crate::test_utils::make_object("bright-nebula", 0.0, 0.0, 4.0, 60.0);
+        orion.identifier = "1976".to_string();
+        orion.alt_designation = Some("M 42".to_string());
+        let objects = vec![orion];
+        let found = |q| find_object(&objects, q).ok().map(|o| o.identifier.as_str());
+        assert_eq!(found("M42"), Some("1976"));
+        assert_eq!(found(" m 042 "), Some("1976"));
+        assert_eq!(found("NGC 1976"), Some("1976"));
+        assert_eq!(found("M4"), None);
+    }
+
     #[test]
     fn fallback_to_name_when_unrecognized() {
         assert_eq!(
@@ -1,14 +1,76 @@
-use anyhow::Result;
 use csv::{Reader, ReaderBuilder};
 use flate2::read::GzDecoder;
 use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
-use crate::types::{hours_to_degrees, parse_or, CelestialObject, EQPoint, Size};
+use crate::error::{ChartError, Result};
+use crate::types::{
+    hours_to_degrees, parse_or, sexagesimal_dms_to_degrees, sexagesimal_hms_to_hours,
+    CelestialObject, EQPoint, Multiplicity, ProperMotion, Size,
+};
 
 // Embed the gzipped star catalog
 pub const HYG_CSV_GZ: &[u8] =
     include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/hygdata.csv.gz"));
 
+/// Supported star catalog formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StarFormat {
+    /// HYG database CSV (the embedded default)
+    Hyg,
+    /// Yale Bright Star Catalog, 5th edition, fixed-width `bsc5.dat`
+    Bsc,
+}
+
+impl FromStr for StarFormat {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hyg" => Ok(Self::Hyg),
+            "bsc" => Ok(Self::Bsc),
+            _ => Err(ChartError::Parse(format!("unknown star format '{s}'"))),
+        }
+    }
+}
+
+// Shared by the catalog parsers: stars have no size or orientation
+fn star(
+    catalog: &str,
+    identifier: String,
+    coords: EQPoint,
+    mag: f64,
+    name: String,
+    proper_motion: Option<ProperMotion>,
+) -> CelestialObject {
+    CelestialObject {
+        kind: "star".to_string(),
+        catalog: catalog.to_string(),
+        identifier,
+        coords,
+        magnitude: mag,
+        size: Size::zero(),
+        angle: 0.0,
+        name,
+        alt_designation: None,
+        proper_motion,
+        multiplicity: None,
+    }
+}
+
+// Proper motion from its two components in the catalog's units, which are
+// `per_mas` milliarcseconds. A star missing either has none.
+fn proper_motion(ra: &str, dec: &str, per_mas: f64) -> Option<ProperMotion> {
+    let ra_mas_yr = ra.trim().parse::<f64>().ok()? * per_mas;
+    let dec_mas_yr = dec.trim().parse::<f64>().ok()? * per_mas;
+    Some(ProperMotion {
+        ra_mas_yr,
+        dec_mas_yr,
+    })
+}
+
 #[derive(Debug, Deserialize)]
 struct HygRow {
     id: String,
@@ -16,40 +78,178 @@ struct HygRow {
     dec: String,
     mag: String,
     proper: String,
+    // Hipparcos proper motion in mas/yr, missing from trimmed exports
+    #[serde(default)]
+    pmra: String,
+    #[serde(default)]
+    pmdec: String,
 }
 
-fn parse_stars_from_reader<R: std::io::Read>(mut rdr: Reader<R>) -> Result<Vec<CelestialObject>> {
+// The parsers take `keep`, given each star's position and magnitude, to drop
+// stars as they're read rather than after the whole catalog is in memory
+fn parse_stars_from_reader<R: std::io::Read>(
+    mut rdr: Reader<R>,
+    keep: impl Fn(EQPoint, f64) -> bool,
+) -> Result<Vec<CelestialObject>> {
     let mut out = Vec::new();
     for rec in rdr.deserialize() {
         let row: HygRow = rec?;
         let ra_h: f64 = parse_or(&row.ra, 0.0);
         let dec_deg: f64 = parse_or(&row.dec, 0.0);
         let mag: f64 = parse_or(&row.mag, 99.0);
-        out.push(CelestialObject {
-            kind: "star".to_string(),
-            catalog: "HYG".to_string(),
-            identifier: row.id,
-            coords: EQPoint {
-                ra_deg: hours_to_degrees(ra_h),
-                dec_deg,
-            },
-            magnitude: mag,
-            size: Size::zero(),
-            angle: 0.0,
-            name: row.proper,
-        });
+        let coords = EQPoint {
+            ra_deg: hours_to_degrees(ra_h),
+            dec_deg,
+        }
+        .with_wrapped_ra();
+        if !coords.is_valid() || !keep(coords, mag) {
+            continue;
+        }
+        let pm = proper_motion(&row.pmra, &row.pmdec, 1.0);
+        out.push(star("HYG", row.id, coords, mag, row.proper, pm));
     }
     Ok(out)
 }
 
-pub fn load_stars(path: Option<&str>) -> Result<Vec<CelestialObject>> {
-    if let Some(p) = path {
-        let rdr = ReaderBuilder::new().from_path(p)?;
-        parse_stars_from_reader(rdr)
-    } else {
-        let gz = GzDecoder::new(HYG_CSV_GZ);
-        let rdr = ReaderBuilder::new().from_reader(gz);
-        parse_stars_from_reader(rdr)
+// Fixed-width field by the 1-based, inclusive byte columns of the BSC ReadMe
+fn bsc_field(line: &str, first: usize, last: usize) -> &str {
+    line.get(first - 1..last.min(line.len()))
+        .unwrap_or("")
+        .trim()
+}
+
+// The BSC is fixed-width. Entries withdrawn from the catalog (novae,
+// non-stellar objects) have blank coordinates and are skipped.
+fn parse_bsc_from_reader<R: BufRead>(
+    rdr: R,
+    keep: impl Fn(EQPoint, f64) -> bool,
+) -> Result<Vec<CelestialObject>> {
+    let mut out = Vec::new();
+    for line in rdr.lines() {
+        let line = line?;
+        let (ra_h, ra_m, ra_s) = (
+            bsc_field(&line, 76, 77),
+            bsc_field(&line, 78, 79),
+            bsc_field(&line, 80, 83),
+        );
+        if ra_h.is_empty() {
+            continue;
+        }
+        let sign = if bsc_field(&line, 84, 84) == "-" {
+            -1.0
+        } else {
+            1.0
+        };
+        let (dec_d, dec_m, dec_s) = (
+            bsc_field(&line, 85, 86),
+            bsc_field(&line, 87, 88),
+            bsc_field(&line, 89, 90),
+        );
+
+        let ra_deg = hours_to_degrees(sexagesimal_hms_to_hours(
+            parse_or(ra_h, 0.0),
+            parse_or(ra_m, 0.0),
+            parse_or(ra_s, 0.0),
+        ));
+        let dec_deg = sign
+            * sexagesimal_dms_to_degrees(
+                parse_or(dec_d, 0.0),
+                parse_or(dec_m, 0.0),
+                parse_or(dec_s, 0.0),
+            );
+        let mag = parse_or(bsc_field(&line, 103, 107), 99.0);
+
+        // Bayer/Flamsteed designation, e.g. "9Alp CMa"
+        let name = bsc_field(&line, 5, 14)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let hr = bsc_field(&line, 1, 4).to_string();
+        let coords = EQPoint { ra_deg, dec_deg }.with_wrapped_ra();
+        if !coords.is_valid() || !keep(coords, mag) {
+            continue;
+        }
+        // J2000 proper motion in arcsec/yr
+        let pm = proper_motion(
+            bsc_field(&line, 149, 154),
+            bsc_field(&line, 155, 160),
+            1000.0,
+        );
+        let mut s = star("HR", hr, coords, mag, name, pm);
+        // Doubles and multiples have a separation between components, or
+        // a count of them
+        let separation_arcsec = bsc_field(&line, 185, 190).parse::<f64>().ok();
+        let components = bsc_field(&line, 195, 196).parse::<u32>().ok();
+        if separation_arcsec.is_some() || components.is_some_and(|n| n > 1) {
+            s.multiplicity = Some(Multiplicity {
+                components,
+                separation_arcsec,
+            });
+        }
+        out.push(s);
+    }
+    Ok(out)
+}
+
+// HYG exports aren't always comma-delimited, so pick whichever of comma,
+// semicolon, or tab is most common in the header line
+fn sniff_delimiter(header: &[u8]) -> u8 {
+    let line = header.split(|&b| b == b'\n').next().unwrap_or_default();
+    let count = |d: u8| line.iter().filter(|&&b| b == d).count();
+    [b',', b';', b'\t']
+        .into_iter()
+        .max_by_key(|&d| count(d))
+        .filter(|&d| count(d) > 0)
+        .unwrap_or(b',')
+}
+
+// A CSV reader using the delimiter sniffed from the start of `rdr`
+fn sniffed_reader<R: BufRead>(mut rdr: R) -> Result<Reader<R>> {
+    let delimiter = sniff_delimiter(rdr.fill_buf()?);
+    Ok(ReaderBuilder::new().delimiter(delimiter).from_reader(rdr))
+}
+
+/// Load stars from a catalog file in the given format, or from the embedded
+/// HYG catalog. Unpacking and parsing the embedded catalog takes a while, so
+/// load once and borrow the result into each chart's `Datasets`.
+pub fn load_stars(path: Option<&str>, format: StarFormat) -> Result<Vec<CelestialObject>> {
+    match (format, path) {
+        (StarFormat::Hyg, Some(p)) => {
+            let rdr = sniffed_reader(BufReader::new(File::open(p)?))?;
+            parse_stars_from_reader(rdr, |_, _| true)
+        }
+        (StarFormat::Hyg, None) => {
+            let gz = GzDecoder::new(HYG_CSV_GZ);
+            let rdr = ReaderBuilder::new().from_reader(gz);
+            parse_stars_from_reader(rdr, |_, _| true)
+        }
+        (StarFormat::Bsc, Some(p)) => {
+            parse_bsc_from_reader(BufReader::new(File::open(p)?), |_, _| true)
+        }
+        (StarFormat::Bsc, None) => Err(ChartError::InvalidConfig(
+            "the BSC star format isn't embedded; pass a catalog path".to_string(),
+        )),
+    }
+}
+
+/// Load only the stars of an external catalog within `radius_deg` of
+/// `center` and no fainter than `mag_limit`. Stars are dropped as the file is
+/// read, so a catalog far larger than the chart needs is never held in
+/// memory whole.
+pub fn load_stars_filtered(
+    path: &str,
+    format: StarFormat,
+    center: EQPoint,
+    radius_deg: f64,
+    mag_limit: f64,
+) -> Result<Vec<CelestialObject>> {
+    // Magnitude first, as it's cheaper and culls most of a deep catalog
+    let keep =
+        |coords: EQPoint, mag: f64| mag <= mag_limit && center.separation_deg(coords) <= radius_deg;
+    let file = BufReader::new(File::open(path)?);
+    match format {
+        StarFormat::Hyg => parse_stars_from_reader(sniffed_reader(file)?, keep),
+        StarFormat::Bsc => parse_bsc_from_reader(file, keep),
     }
 }
 
@@ -61,7 +261,7 @@ mod tests {
 
     fn parse_from_str(csv: &str) -> Vec<CelestialObject> {
         let rdr = ReaderBuilder::new().from_reader(csv.as_bytes());
-        parse_stars_from_reader(rdr).expect("parse HYG CSV")
+        parse_stars_from_reader(rdr, |_, _| true).expect("parse HYG CSV")
     }
 
     #[test]
@@ -91,6 +291,154 @@ id,ra,dec,mag,proper
         assert!(approx(s2.magnitude, 0.45, 1e-10));
     }
 
+    #[test]
+    fn reads_proper_motion_when_the_catalog_has_it() {
+        let csv = "\
+id,proper,ra,dec,pmra,pmdec,mag
+32263,Sirius,6.752481,-16.716116,-546.01,-1223.07,-1.44
+27919,Betelgeuse,5.919529,7.407063,,,0.45
+";
+        let stars = parse_from_str(csv);
+        let pm = stars[0].proper_motion.unwrap();
+        assert!(approx(pm.ra_mas_yr, -546.01, 1e-9) && approx(pm.dec_mas_yr, -1223.07, 1e-9));
+        assert!(stars[1].proper_motion.is_none());
+
+        // Exports without the columns load without it
+        let stars =
+            parse_from_str("id,ra,dec,mag,proper\n32263,6.752481,-16.716116,-1.44,Sirius\n");
+        assert!(stars[0].proper_motion.is_none());
+    }
+
+    #[test]
+    fn filtered_loading_keeps_stars_near_the_center() {
+        let csv = "\
+id,ra,dec,mag,proper
+32263,6.752481,-16.716116,-1.44,Sirius
+27919,5.919529,7.407063,0.45,Betelgeuse
+24378,5.242298,-8.20164,0.18,Rigel
+26662,5.679313,-1.942572,1.74,Alnitak
+26366,5.645767,-2.600069,6.2,
+";
+        let path = std::env::temp_dir().join(format!("charter-stars-{}.csv", std::process::id()));
+        std::fs::write(&path, csv).unwrap();
+        let load = |radius_deg: f64, mag_limit: f64| {
+            let path = path.to_str().unwrap();
+            let center = EQPoint {
+                ra_deg: 5.679313 * 15.0,
+                dec_deg: -1.942572,
+            };
+            let stars = load_stars_filtered(path, StarFormat::Hyg, center, radius_deg, mag_limit);
+            let mut ids: Vec<String> = stars.unwrap().into_iter().map(|s| s.identifier).collect();
+            ids.sort();
+            ids
+        };
+
+        // A tight circle around Alnitak takes in only it and its faint
+        // neighbor, then the magnitude limit drops the neighbor
+        assert_eq!(load(1.0, 10.0), vec!["26366", "26662"]);
+        assert_eq!(load(1.0, 6.0), vec!["26662"]);
+        // Wide enough for the rest of Orion, but not Sirius
+        assert_eq!(load(12.0, 6.0), vec!["24378", "26662", "27919"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sniffs_semicolon_and_tab_delimiters() {
+        for sep in [';', '\t'] {
+            let csv = format!(
+                "id{sep}ra{sep}dec{sep}mag{sep}proper\n\
+                 32263{sep}6.752481{sep}-16.716116{sep}-1.44{sep}Sirius\n"
+            );
+            let stars =
+                parse_stars_from_reader(sniffed_reader(csv.as_bytes()).unwrap(), |_, _| true)
+                    .expect("parse delimited HYG");
+            assert_eq!(stars.len(), 1);
+            assert_eq!(stars[0].name, "Sirius");
+            assert!(approx(stars[0].magnitude, -1.44, 1e-10));
+        }
+        // Only the header line counts, and a lone column falls back to commas
+        assert_eq!(sniff_delimiter(b"id,ra,dec\n1;2"), b',');
+        assert_eq!(sniff_delimiter(b"id"), b',');
+    }
+
+    #[test]
+    fn parses_bsc_fixed_width_rows() {
+        let dat = "\
+2491  9Alp CMa                                                             064508.9-164258            -1.46
+2061 58Alp Ori                                                             055510.3+072425             0.50
+  92
+";
+        let stars = parse_bsc_from_reader(dat.as_bytes(), |_, _| true).expect("parse BSC");
+        // The withdrawn entry with no coordinates is skipped
+        assert_eq!(stars.len(), 2);
+
+        let sirius = &stars[0];
+        assert_eq!(sirius.kind, "star");
+        assert_eq!(sirius.catalog, "HR");
+        assert_eq!(sirius.identifier, "2491");
+        assert_eq!(sirius.name, "9Alp CMa");
+        // 06:45:08.9 → 101.287083°, -16:42:58 → -16.716111°
+        assert!(approx(sirius.coords.ra_deg, 101.28708333333333, 1e-9));
+        assert!(approx(sirius.coords.dec_deg, -16.71611111111111, 1e-9));
+        assert!(approx(sirius.magnitude, -1.46, 1e-12));
+
+        let betelgeuse = &stars[1];
+        assert_eq!(betelgeuse.identifier, "2061");
+        assert!(approx(betelgeuse.coords.dec_deg, 7.406944444444444, 1e-9));
+        assert!(approx(betelgeuse.magnitude, 0.5, 1e-12));
+    }
+
+    #[test]
+    fn bsc_proper_motion_is_read_in_arcseconds() {
+        let dat = "\
+2491  9Alp CMa                                                             064508.9-164258            -1.46                                         -0.553-1.205
+2061 58Alp Ori                                                             055510.3+072425             0.50
+";
+        let stars = parse_bsc_from_reader(dat.as_bytes(), |_, _| true).expect("parse BSC");
+        let pm = stars[0].proper_motion.unwrap();
+        assert!(approx(pm.ra_mas_yr, -553.0, 1e-9) && approx(pm.dec_mas_yr, -1205.0, 1e-9));
+        assert!(stars[1].proper_motion.is_none());
+    }
+
+    #[test]
+    fn bsc_doubles_keep_their_separation_and_component_count() {
+        let dat = "\
+2061 58Alp Ori                                                             055510.3+072425             0.50
+1948 50Zet Ori                                                             054045.5-015634             1.77                                                                          2.2   2.4AB   3
+";
+        let stars = parse_bsc_from_reader(dat.as_bytes(), |_, _| true).expect("parse BSC");
+        assert!(stars[0].multiplicity.is_none());
+
+        let alnitak = &stars[1];
+        assert_eq!(alnitak.kind, "star");
+        assert_eq!(
+            alnitak.multiplicity,
+            Some(Multiplicity {
+                components: Some(3),
+                separation_arcsec: Some(2.4),
+            })
+        );
+    }
+
+    #[test]
+    fn bsc_requires_a_path() {
+        assert!(matches!(
+            load_stars(None, StarFormat::Bsc),
+            Err(ChartError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn failures_are_told_apart() {
+        let missing = load_stars(Some("/nonexistent/hyg.csv"), StarFormat::Hyg);
+        assert!(matches!(missing, Err(ChartError::Io(_))));
+
+        // A row with fewer fields than the header
+        let rdr = ReaderBuilder::new().from_reader("id,ra,dec,mag,proper\n1,2.0\n".as_bytes());
+        let malformed = parse_stars_from_reader(rdr, |_, _| true);
+        assert!(matches!(malformed, Err(ChartError::Parse(_))));
+    }
+
     #[test]
     fn empty_magnitude_defaults_to_99() {
         let csv = "\
@@ -0,0 +1,62 @@
+use thiserror::Error;
+
+/// Everything that can go wrong loading catalogs and drawing charts
+#[derive(Debug, Error)]
+pub enum ChartError {
+    /// Input that couldn't be read, such as a malformed coordinate or
+    /// catalog row
+    #[error("{0}")]
+    Parse(String),
+    /// A file that couldn't be read or written
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// Settings that can't make a chart
+    #[error("{0}")]
+    InvalidConfig(String),
+    /// A named object or constellation that isn't in the catalogs
+    #[error("unknown {kind} '{name}'")]
+    UnresolvedTarget { kind: &'static str, name: String },
+    /// A chart that was drawn but couldn't be encoded as an image
+    #[error("{0}")]
+    Render(String),
+}
+
+pub type Result<T> = std::result::Result<T, ChartError>;
+
+// A CSV error is either the underlying reader failing or a row that doesn't
+// fit the catalog's format
+impl From<csv::Error> for ChartError {
+    fn from(e: csv::Error) -> Self {
+        if !e.is_io_error() {
+            return ChartError::Parse(e.to_string());
+        }
+        match e.into_kind() {
+            csv::ErrorKind::Io(io) => ChartError::Io(io),
+            kind => ChartError::Parse(format!("{kind:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_errors_split_into_io_and_parse() {
+        let io = csv::Error::from(std::io::Error::other("disk on fire"));
+        assert!(matches!(ChartError::from(io), ChartError::Io(_)));
+
+        let mut rdr = csv::ReaderBuilder::new().from_reader("a,b\n1,2,3\n".as_bytes());
+        let bad = rdr.records().next().unwrap().unwrap_err();
+        assert!(matches!(ChartError::from(bad), ChartError::Parse(_)));
+    }
+
+    #[test]
+    fn unresolved_targets_name_what_was_missing() {
+        let e = ChartError::UnresolvedTarget {
+            kind: "object",
+            name: "M 110".to_string(),
+        };
+        assert_eq!(e.to_string(), "unknown object 'M 110'");
+    }
+}
@@ -1,5 +1,5 @@
 use crate::context::ChartContext;
-use crate::types::{EQPoint, Point, Projection};
+use crate::types::{EQPoint, Point, Projection, RaDirection};
 use std::f64::consts::PI;
 
 #[inline]
@@ -21,6 +21,106 @@ pub fn to_pixels(tp: Point, center_px: Point, scale: f64) -> Point {
     }
 }
 
+#[inline]
+pub fn from_pixels(p: Point, center_px: Point, scale: f64) -> Point {
+    Point {
+        x: (p.x - center_px.x) / scale,
+        y: (center_px.y - p.y) / scale,
+    }
+}
+
+/// Orient a tangent-plane point for the chart's RA direction. The
+/// projections put east at -x; `Right` mirrors that. It's its own inverse.
+#[inline]
+pub fn orient(tp: Point, direction: RaDirection) -> Point {
+    match direction {
+        RaDirection::Left => tp,
+        RaDirection::Right => Point { x: -tp.x, y: tp.y },
+    }
+}
+
+// Cylindrical projections map RA/Dec straight onto the plane rather than
+// through a zenith distance and azimuth. The chart center's RA is the reference
+// longitude (RA increasing to the left, as on the azimuthal charts) and the
+// center's Dec is shifted to y = 0 so the center lands on the plot center.
+fn project_cylindrical(coords: EQPoint, center: EQPoint, position_angle_deg: f64) -> Option<Point> {
+    // The poles are at infinity
+    if coords.dec_deg.abs() >= 90.0 {
+        return None;
+    }
+    let d_ra = (coords.ra_deg - center.ra_deg + 180.0).rem_euclid(360.0) - 180.0;
+    let mercator_y = |dec_deg: f64| (PI / 4.0 + dec_deg.to_radians() / 2.0).tan().ln();
+
+    let x = -d_ra.to_radians();
+    let y = mercator_y(coords.dec_deg) - mercator_y(center.dec_deg);
+
+    // Same sense of rotation as the azimuthal projections
+    let pa = position_angle_deg.to_radians();
+    Some(Point {
+        x: x * pa.cos() + y * pa.sin(),
+        y: -x * pa.sin() + y * pa.cos(),
+    })
+}
+
+// Distance from the center on the projection plane of a point `zenith`
+// radians away. Cylindrical projections are linear in RA, so for them it's
+// the angle itself. Altaz is equidistant, scaled to put the horizon of a
+// zenith-centered chart at 1; charts are sized to match, so its `--fov` is
+// the angle across rather than going by tan(fov/2) as gnomonic does.
+pub fn radial_distance(projection: Projection, zenith: f64) -> f64 {
+    match projection {
+        Projection::Gnomonic => zenith.tan(),
+        Projection::Stereographic => (zenith / 2.0).tan(),
+        Projection::Spherical => zenith.min(PI / 2.0).sin(),
+        Projection::AltAz => zenith / (PI / 2.0),
+        Projection::Mercator => zenith,
+    }
+}
+
+// The slope of `radial_distance` `zenith` radians from the center: how far
+// the projection plane stretches per radian there
+pub fn radial_slope(projection: Projection, zenith: f64) -> f64 {
+    match projection {
+        Projection::Gnomonic => 1.0 / zenith.cos().powi(2),
+        Projection::Stereographic => 0.5 / (zenith / 2.0).cos().powi(2),
+        Projection::Spherical => zenith.cos(),
+        Projection::AltAz => 2.0 / PI,
+        Projection::Mercator => 1.0,
+    }
+}
+
+// Whether a point `zenith` radians from the center is on the projection.
+// Gnomonic reaches the horizon only at infinity, spherical ends at its limb,
+// and the rest run out at the point opposite the center.
+pub fn within_projection(projection: Projection, zenith: f64) -> bool {
+    match projection {
+        Projection::Gnomonic => zenith < PI / 2.0,
+        Projection::Spherical => zenith <= PI / 2.0,
+        Projection::Stereographic | Projection::AltAz | Projection::Mercator => zenith < PI,
+    }
+}
+
+/// The local scale of an azimuthal projection `zenith` radians from its
+/// center, relative to the scale at the center, as radial and tangential
+/// factors. The radial factor is the slope of the radial mapping and the
+/// tangential one its stretch of a small circle about the center. None for
+/// cylindrical projections, whose scale follows declination rather than
+/// distance from the center. `zenith` must be `within_projection`.
+pub fn scale_factors(projection: Projection, zenith: f64) -> Option<(f64, f64)> {
+    const STEP: f64 = 1e-6;
+    if matches!(projection, Projection::Mercator) {
+        return None;
+    }
+    let center = radial_slope(projection, 0.0);
+    let radial = radial_slope(projection, zenith) / center;
+    let tangential = if zenith < STEP {
+        1.0
+    } else {
+        radial_distance(projection, zenith) / zenith.sin() / center
+    };
+    Some((radial, tangential))
+}
+
 // Project an equatorial point relative to a chart center.
 // - `coords` / `center`: RA/Dec in **degrees**
 // - `projection`: which chart projection to use
@@ -34,6 +134,23 @@ pub fn project(
     projection: Projection,
     position_angle_deg: f64,
 ) -> Option<Point> {
+    project_within(coords, center, projection, position_angle_deg, None)
+}
+
+// `project`, also dropping points more than `max_zenith_deg` from the center,
+// which bounds an otherwise unlimited stereographic chart. Cylindrical
+// projections have no zenith distance, so the cap doesn't apply to them.
+pub fn project_within(
+    coords: EQPoint,
+    center: EQPoint,
+    projection: Projection,
+    position_angle_deg: f64,
+    max_zenith_deg: Option<f64>,
+) -> Option<Point> {
+    if matches!(projection, Projection::Mercator) {
+        return project_cylindrical(coords, center, position_angle_deg);
+    }
+
     // deg -> rad
     let ra = coords.ra_deg.to_radians();
     let dec = coords.dec_deg.to_radians();
@@ -56,18 +173,16 @@ pub fn project(
     let x = cde.cos() * dec.sin() - cde.sin() * dec.cos() * d_ra.cos();
     let az = y.atan2(x) - position_angle_deg.to_radians();
 
-    // If behind the horizon and not stereographic, drop it.
-    if zenith > PI / 2.0 && !matches!(projection, Projection::Stereographic) {
+    // If behind the horizon and not stereographic, drop it. Points on the
+    // horizon itself stay, though rounding can leave them a hair past it.
+    if zenith > PI / 2.0 + 1e-12 && !matches!(projection, Projection::Stereographic) {
+        return None;
+    }
+    if max_zenith_deg.is_some_and(|max| zenith > max.to_radians()) {
         return None;
     }
 
-    // Radial mapping by projection
-    let r = match projection {
-        Projection::Gnomonic => zenith.tan(),
-        Projection::Stereographic => (zenith / 2.0).tan(),
-        Projection::Spherical => zenith.sin(),
-        Projection::AltAz => zenith / (PI / 2.0),
-    };
+    let r = radial_distance(projection, zenith);
 
     Some(Point {
         x: -r * az.sin(),
@@ -75,6 +190,52 @@ pub fn project(
     })
 }
 
+// Inverse of `project`: recover the equatorial point for a tangent-plane
+// point. Returns `None` where the projection doesn't reach (off the sphere).
+pub fn unproject(
+    tp: Point,
+    center: EQPoint,
+    projection: Projection,
+    position_angle_deg: f64,
+) -> Option<EQPoint> {
+    let pa = position_angle_deg.to_radians();
+    let cde = center.dec_deg.to_radians();
+
+    if matches!(projection, Projection::Mercator) {
+        let x = tp.x * pa.cos() - tp.y * pa.sin();
+        let y = tp.x * pa.sin() + tp.y * pa.cos();
+        let y0 = (PI / 4.0 + cde / 2.0).tan().ln();
+        let dec = 2.0 * (y + y0).exp().atan() - PI / 2.0;
+        return Some(EQPoint {
+            ra_deg: (center.ra_deg - x.to_degrees()).rem_euclid(360.0),
+            dec_deg: dec.to_degrees(),
+        });
+    }
+
+    let r = tp.x.hypot(tp.y);
+    let zenith = match projection {
+        Projection::Gnomonic => r.atan(),
+        Projection::Stereographic => 2.0 * r.atan(),
+        Projection::Spherical if r <= 1.0 => r.asin(),
+        Projection::AltAz if r <= 2.0 => r * (PI / 2.0),
+        _ => return None,
+    };
+    let az = (-tp.x).atan2(tp.y) + pa;
+
+    // Destination point along bearing `az` at angular distance `zenith`
+    let dec = clamp(
+        cde.sin() * zenith.cos() + cde.cos() * zenith.sin() * az.cos(),
+        -1.0,
+        1.0,
+    )
+    .asin();
+    let d_ra = (az.sin() * zenith.sin() * cde.cos()).atan2(zenith.cos() - cde.sin() * dec.sin());
+    Some(EQPoint {
+        ra_deg: (center.ra_deg + d_ra.to_degrees()).rem_euclid(360.0),
+        dec_deg: dec.to_degrees(),
+    })
+}
+
 pub fn split_segments(points: &[Point], threshold: f64) -> Vec<Vec<Point>> {
     if points.is_empty() {
         return vec![];
@@ -82,12 +243,12 @@ pub fn split_segments(points: &[Point], threshold: f64) -> Vec<Vec<Point>> {
     let mut segs = Vec::new();
     let mut seg = vec![points[0]];
     for w in points.windows(2) {
-        let (a, b) = (w[0], w[1]);
-        if (b.x - a.x).abs() > threshold || (b.y - a.y).abs() > threshold {
+        let step = w[1] - w[0];
+        if step.x.abs() > threshold || step.y.abs() > threshold {
             segs.push(seg);
-            seg = vec![b];
+            seg = vec![w[1]];
         } else {
-            seg.push(b);
+            seg.push(w[1]);
         }
     }
     if !seg.is_empty() {
@@ -96,6 +257,88 @@ pub fn split_segments(points: &[Point], threshold: f64) -> Vec<Vec<Point>> {
     segs
 }
 
+// Distance from `p` to the segment from `a` to `b`
+fn distance_to_segment(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b - a;
+    let len2 = ab.x * ab.x + ab.y * ab.y;
+    if len2 == 0.0 {
+        return p.distance(a);
+    }
+    let ap = p - a;
+    let t = ((ap.x * ab.x + ap.y * ab.y) / len2).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+/// Ramer–Douglas–Peucker simplification of a polyline: the fewest of its
+/// points, always both ends, that leave none of the dropped ones farther
+/// than `tolerance` pixels from the line through those kept. A tolerance of
+/// 0 or less keeps every point.
+pub fn simplify(points: &[Point], tolerance: f64) -> Vec<Point> {
+    if tolerance <= 0.0 || points.len() < 3 {
+        return points.to_vec();
+    }
+    let last = points.len() - 1;
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[last] = true;
+    // Spans still to check, by the indices of their ends
+    let mut spans = vec![(0, last)];
+    while let Some((first, last)) = spans.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| {
+                (
+                    i,
+                    distance_to_segment(points[i], points[first], points[last]),
+                )
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, d)) = farthest
+            && d > tolerance
+        {
+            keep[i] = true;
+            spans.push((first, i));
+            spans.push((i, last));
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(&p, kept)| kept.then_some(p))
+        .collect()
+}
+
+/// Points along the shorter great-circle arc from `a` to `b`, both included,
+/// at most `step_deg` apart
+pub fn great_circle_points(a: EQPoint, b: EQPoint, step_deg: f64) -> Vec<EQPoint> {
+    let to_vec = |p: EQPoint| {
+        let (ra, dec) = (p.ra_deg.to_radians(), p.dec_deg.to_radians());
+        [dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin()]
+    };
+    let (va, vb) = (to_vec(a), to_vec(b));
+    let theta = a.separation_deg(b).to_radians();
+    // Coincident or antipodal endpoints don't pick out a single arc
+    if theta.sin().abs() < 1e-12 {
+        return vec![a, b];
+    }
+    // Allow for rounding, so an exact multiple of the step isn't split once more
+    let n = (theta.to_degrees() / step_deg - 1e-9).ceil().max(1.0) as usize;
+    (0..=n)
+        .map(|i| {
+            // Spherical linear interpolation between the unit vectors
+            let t = i as f64 / n as f64;
+            let (wa, wb) = (
+                ((1.0 - t) * theta).sin() / theta.sin(),
+                (t * theta).sin() / theta.sin(),
+            );
+            let v: [f64; 3] = std::array::from_fn(|k| wa * va[k] + wb * vb[k]);
+            EQPoint {
+                ra_deg: v[1].atan2(v[0]).to_degrees().rem_euclid(360.0),
+                dec_deg: v[2].atan2(v[0].hypot(v[1])).to_degrees(),
+            }
+        })
+        .collect()
+}
+
 pub fn sample_ra_meridian(
     context: &ChartContext<'_>,
     ra_deg: f64,
@@ -104,22 +347,24 @@ pub fn sample_ra_meridian(
     let step = step_opt.unwrap_or_else(|| context.adaptive_step_deg());
     let ra = ra_deg.rem_euclid(360.0);
     let mut out = Vec::new();
-    let mut d = -90;
-    while d <= 90 {
-        if let Some(tp) = project(
-            EQPoint {
-                ra_deg: ra,
-                dec_deg: d as f64,
-            },
-            context.cfg.center,
-            context.cfg.projection,
-            context.cfg.position_angle_deg,
-        ) {
-            out.push(to_pixels(
-                tp,
-                context.layout.center_px,
-                context.layout.scale,
-            ));
+    if let Some((lo, hi)) = context.cfg.ra_range
+        && !(EQPoint {
+            ra_deg: ra,
+            dec_deg: 0.0,
+        })
+        .in_ra_range(lo, hi)
+    {
+        return out;
+    }
+    let (lo, hi) = context.cfg.dec_range.unwrap_or((-90.0, 90.0));
+    let mut d = lo.max(-90.0).ceil() as i32;
+    let end = hi.min(90.0).floor() as i32;
+    while d <= end {
+        if let Some(p) = context.project_to_pixels(EQPoint {
+            ra_deg: ra,
+            dec_deg: d as f64,
+        }) {
+            out.push(p);
         }
         d += step as i32;
     }
@@ -133,24 +378,35 @@ pub fn sample_dec_parallel(
 ) -> Vec<Point> {
     let step = step_opt.unwrap_or_else(|| context.adaptive_step_deg());
     let mut out = Vec::new();
-    let mut r = 0;
-    while r < 360 {
-        if let Some(tp) = project(
-            EQPoint {
-                ra_deg: r as f64,
-                dec_deg,
-            },
-            context.cfg.center,
-            context.cfg.projection,
-            context.cfg.position_angle_deg,
-        ) {
-            out.push(to_pixels(
-                tp,
-                context.layout.center_px,
-                context.layout.scale,
-            ));
+    if let Some((lo, hi)) = context.cfg.dec_range
+        && !(lo..=hi).contains(&dec_deg)
+    {
+        return out;
+    }
+
+    // Walk RA from the start of the band so the kept samples stay contiguous,
+    // including the band's far edge.
+    let (start, span) = match context.cfg.ra_range {
+        Some((lo, hi)) if hi - lo < 360.0 => (lo.rem_euclid(360.0), (hi - lo).rem_euclid(360.0)),
+        _ => (0.0, 360.0),
+    };
+    let mut ras = Vec::new();
+    let mut r = 0.0;
+    while r < span {
+        ras.push(start + r);
+        r += step as f64;
+    }
+    if span < 360.0 {
+        ras.push(start + span);
+    }
+
+    for ra in ras {
+        if let Some(p) = context.project_to_pixels(EQPoint {
+            ra_deg: ra.rem_euclid(360.0),
+            dec_deg,
+        }) {
+            out.push(p);
         }
-        r += step as i32;
     }
     out
 }
@@ -159,7 +415,57 @@ pub fn sample_dec_parallel(
 mod tests {
     use super::*;
     use crate::test_utils::{approx, make_context};
-    use crate::types::{EQPoint, Point, Projection};
+    use crate::types::{EQPoint, Point, Projection, RaDirection};
+
+    #[test]
+    fn scale_factors_match_each_projections_character() {
+        let z = 40f64.to_radians();
+        // Conformal: the same stretch both ways
+        let (h, k) = scale_factors(Projection::Stereographic, z).unwrap();
+        assert!(approx(h, k, 1e-6));
+        assert!(approx(h, 1.0 / (z / 2.0).cos().powi(2), 1e-6));
+        // Equidistant along the radius
+        let (h, k) = scale_factors(Projection::AltAz, z).unwrap();
+        assert!(approx(h, 1.0, 1e-6));
+        assert!(approx(k, z / z.sin(), 1e-6));
+        let (h, k) = scale_factors(Projection::Gnomonic, z).unwrap();
+        assert!(approx(h, 1.0 / z.cos().powi(2), 1e-6));
+        assert!(approx(k, 1.0 / z.cos(), 1e-6));
+        assert_eq!(scale_factors(Projection::Gnomonic, 0.0), Some((1.0, 1.0)));
+        assert!(scale_factors(Projection::Mercator, z).is_none());
+    }
+
+    #[test]
+    fn projections_end_where_their_radial_distance_does() {
+        let right = PI / 2.0;
+        assert!(within_projection(Projection::Gnomonic, right - 1e-9));
+        assert!(!within_projection(Projection::Gnomonic, right));
+        assert!(within_projection(Projection::Spherical, right));
+        assert!(!within_projection(Projection::Spherical, right + 1e-9));
+        for p in [
+            Projection::Stereographic,
+            Projection::AltAz,
+            Projection::Mercator,
+        ] {
+            assert!(within_projection(p, PI - 1e-9), "{p}");
+            assert!(!within_projection(p, PI), "{p}");
+        }
+    }
+
+    #[test]
+    fn gnomonic_distorts_its_edge_more_than_stereographic() {
+        let edge = 35f64.to_radians();
+        let area = |p: Projection| {
+            let (h, k) = scale_factors(p, edge).unwrap();
+            h * k
+        };
+        let angular = |p: Projection| {
+            let (h, k) = scale_factors(p, edge).unwrap();
+            h.max(k) / h.min(k)
+        };
+        assert!(area(Projection::Gnomonic) > area(Projection::Stereographic));
+        assert!(angular(Projection::Gnomonic) > angular(Projection::Stereographic));
+    }
 
     #[test]
     fn center_projects_to_origin() {
@@ -189,6 +495,84 @@ mod tests {
         assert!(approx(p.y, 0.0, 1e-12));
     }
 
+    #[test]
+    fn ra_direction_sets_which_side_east_lands_on() {
+        let c = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 0.0,
+        };
+        let east = EQPoint {
+            ra_deg: 1.0,
+            dec_deg: 0.0,
+        };
+        for projection in [Projection::Gnomonic, Projection::Mercator] {
+            let tp = project(east, c, projection, 0.0).unwrap();
+            let left = orient(tp, RaDirection::Left);
+            let right = orient(tp, RaDirection::Right);
+            assert!(left.x < 0.0, "{projection:?}");
+            assert!(approx(right.x, -left.x, 1e-12));
+            assert!(approx(right.y, left.y, 1e-12));
+            assert_eq!(orient(right, RaDirection::Right), tp);
+        }
+    }
+
+    #[test]
+    fn max_zenith_caps_the_stereographic_back_hemisphere() {
+        let c = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 90.0,
+        };
+        let far = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: -30.0,
+        };
+        let near = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: -5.0,
+        };
+        let stereo = |p, cap| project_within(p, c, Projection::Stereographic, 0.0, cap);
+        // 120° from the pole
+        assert!(stereo(far, None).is_some());
+        assert!(stereo(far, Some(100.0)).is_none());
+        assert!(stereo(near, Some(100.0)).is_some());
+    }
+
+    #[test]
+    fn great_circle_points_follow_the_arc() {
+        let a = EQPoint {
+            ra_deg: 350.0,
+            dec_deg: 0.0,
+        };
+        let b = EQPoint {
+            ra_deg: 20.0,
+            dec_deg: 0.0,
+        };
+        let pts = great_circle_points(a, b, 4.0);
+        // 30° in 4° steps, taking the short way through 0h
+        assert_eq!(pts.len(), 9);
+        assert!(approx(pts[0].ra_deg, 350.0, 1e-9));
+        assert!(approx(pts[8].ra_deg, 20.0, 1e-9));
+        for w in pts.windows(2) {
+            assert!(w[0].separation_deg(w[1]) <= 4.0 + 1e-9);
+            assert!(approx(w[1].dec_deg, 0.0, 1e-9));
+        }
+
+        // A meridian arc over the pole
+        let pts = great_circle_points(
+            EQPoint {
+                ra_deg: 0.0,
+                dec_deg: 80.0,
+            },
+            EQPoint {
+                ra_deg: 180.0,
+                dec_deg: 80.0,
+            },
+            1.0,
+        );
+        assert_eq!(pts.len(), 21);
+        assert!(approx(pts[10].dec_deg, 90.0, 1e-9));
+    }
+
     #[test]
     fn position_angle_rotates_counterclockwise() {
         // Same as above but PA = 90°; az -> az - 90°, point rotates to +y axis
@@ -226,6 +610,76 @@ mod tests {
         assert!(approx(p.y, 0.0, 1e-12));
     }
 
+    #[test]
+    fn mercator_is_finite_near_the_poles() {
+        let c = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 0.0,
+        };
+        for dec_deg in [85.0, -85.0] {
+            let p = project(
+                EQPoint {
+                    ra_deg: 0.0,
+                    dec_deg,
+                },
+                c,
+                Projection::Mercator,
+                0.0,
+            )
+            .unwrap();
+            assert!(p.y.is_finite());
+            assert!(
+                p.y.abs() > 3.0,
+                "y={} should be large at dec={dec_deg}",
+                p.y
+            );
+            assert_eq!(p.y.is_sign_positive(), dec_deg > 0.0);
+        }
+        // The poles themselves are at infinity and are dropped
+        assert!(project(
+            EQPoint {
+                ra_deg: 0.0,
+                dec_deg: 90.0
+            },
+            c,
+            Projection::Mercator,
+            0.0
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn mercator_ra_spacing_is_uniform() {
+        let c = EQPoint {
+            ra_deg: 350.0,
+            dec_deg: 20.0,
+        };
+        // Walk across RA=0 to make sure the reference longitude wraps
+        let xs: Vec<f64> = [340.0, 350.0, 0.0, 10.0]
+            .into_iter()
+            .map(|ra_deg| {
+                project(
+                    EQPoint {
+                        ra_deg,
+                        dec_deg: 40.0,
+                    },
+                    c,
+                    Projection::Mercator,
+                    0.0,
+                )
+                .unwrap()
+                .x
+            })
+            .collect();
+        let step = 10.0_f64.to_radians();
+        for w in xs.windows(2) {
+            // RA increases to the left
+            assert!(approx(w[0] - w[1], step, 1e-12));
+        }
+        // The center RA is the reference longitude
+        assert!(approx(xs[1], 0.0, 1e-12));
+    }
+
     #[test]
     fn ra_wrap_equivalent_delta_produces_same_tangent_point() {
         // Case A: center 359°, star 1° → ΔRA = -358° ≡ +2°
@@ -262,6 +716,96 @@ mod tests {
         assert!((p1.y - p2.y).abs() <= 1e-12);
     }
 
+    #[test]
+    fn unproject_inverts_project_for_every_projection() {
+        let center = EQPoint {
+            ra_deg: 83.8,
+            dec_deg: 35.0,
+        };
+        let targets = [(80.0, 40.0), (95.0, 20.0), (70.0, 33.0), (84.0, 60.0)];
+        for projection in [
+            Projection::Gnomonic,
+            Projection::Stereographic,
+            Projection::Spherical,
+            Projection::AltAz,
+            Projection::Mercator,
+        ] {
+            for pa in [0.0, 30.0] {
+                for (ra_deg, dec_deg) in targets {
+                    let eq = EQPoint { ra_deg, dec_deg };
+                    let tp = project(eq, center, projection, pa).unwrap();
+                    let back = unproject(tp, center, projection, pa).unwrap();
+                    assert!(
+                        approx(back.ra_deg, ra_deg, 1e-9) && approx(back.dec_deg, dec_deg, 1e-9),
+                        "{projection:?} pa={pa}: {eq:?} came back as {back:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spherical_is_orthographic_out_to_the_limb() {
+        let center = EQPoint {
+            ra_deg: 30.0,
+            dec_deg: 20.0,
+        };
+        let (ra0, dec0) = (center.ra_deg.to_radians(), center.dec_deg.to_radians());
+        for (ra_deg, dec_deg) in [(30.0, 20.0), (50.0, 10.0), (-20.0, 60.0), (100.0, -30.0)] {
+            let eq = EQPoint { ra_deg, dec_deg };
+            let (ra, dec) = (ra_deg.to_radians(), dec_deg.to_radians());
+            // The textbook orthographic projection, with east to the left
+            let x = -dec.cos() * (ra - ra0).sin();
+            let y = dec0.cos() * dec.sin() - dec0.sin() * dec.cos() * (ra - ra0).cos();
+            let p = project(eq, center, Projection::Spherical, 0.0).unwrap();
+            assert!(approx(p.x, x, 1e-12) && approx(p.y, y, 1e-12), "{eq:?}");
+        }
+
+        // The limb, exactly 90° out, is kept at r = 1; past it is culled
+        let origin = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 0.0,
+        };
+        for (ra_deg, dec_deg) in [(90.0, 0.0), (270.0, 0.0), (0.0, 90.0), (0.0, -90.0)] {
+            let p = project(
+                EQPoint { ra_deg, dec_deg },
+                origin,
+                Projection::Spherical,
+                0.0,
+            )
+            .unwrap_or_else(|| panic!("{ra_deg},{dec_deg} was culled"));
+            assert!(approx(p.x.hypot(p.y), 1.0, 1e-12));
+        }
+        let behind = EQPoint {
+            ra_deg: 90.001,
+            dec_deg: 0.0,
+        };
+        assert!(project(behind, origin, Projection::Spherical, 0.0).is_none());
+    }
+
+    #[test]
+    fn unproject_is_undefined_off_the_sphere() {
+        let c = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 0.0,
+        };
+        let far = Point { x: 1.5, y: 0.0 };
+        assert!(unproject(far, c, Projection::Spherical, 0.0).is_none());
+        assert!(unproject(Point { x: 2.5, y: 0.0 }, c, Projection::AltAz, 0.0).is_none());
+        // Gnomonic and stereographic reach the whole plane
+        assert!(unproject(far, c, Projection::Gnomonic, 0.0).is_some());
+    }
+
+    #[test]
+    fn from_pixels_inverts_to_pixels() {
+        let context = make_context(|_| {});
+        let l = context.layout;
+        let tp = Point { x: 0.1, y: -0.2 };
+        let back = from_pixels(to_pixels(tp, l.center_px, l.scale), l.center_px, l.scale);
+        assert!(approx(back.x, tp.x, 1e-12));
+        assert!(approx(back.y, tp.y, 1e-12));
+    }
+
     #[test]
     fn to_pixels_applies_center_and_scale_with_y_flip() {
         // Prepare a simple context to get scale and center
@@ -301,6 +845,65 @@ mod tests {
         assert_eq!(pts.len(), 3);
     }
 
+    #[test]
+    fn sampling_respects_ra_and_dec_ranges() {
+        let context = make_context(|cfg| {
+            cfg.projection = Projection::Stereographic;
+            cfg.ra_range = Some((350.0, 10.0));
+            cfg.dec_range = Some((-30.0, 30.0));
+        });
+
+        // Meridian inside the RA band is trimmed to the Dec band: -30..=30 at 10° → 7
+        assert_eq!(super::sample_ra_meridian(&context, 0.0, Some(10)).len(), 7);
+        // Meridian outside the RA band is dropped entirely
+        assert!(super::sample_ra_meridian(&context, 90.0, Some(10)).is_empty());
+
+        // Parallel walks the wrapping band 350..10 at 5° → 350,355,0,5,10
+        assert_eq!(super::sample_dec_parallel(&context, 0.0, Some(5)).len(), 5);
+        // Parallel outside the Dec band is dropped entirely
+        assert!(super::sample_dec_parallel(&context, 60.0, Some(5)).is_empty());
+    }
+
+    #[test]
+    fn simplify_drops_only_points_within_the_tolerance() {
+        let pt = |x: f64, y: f64| Point { x, y };
+        // Nearly straight, wobbling by a tenth of a pixel
+        let wobbly: Vec<Point> = (0..50)
+            .map(|i| pt(i as f64 * 10.0, if i % 2 == 0 { 0.1 } else { -0.1 }))
+            .collect();
+        assert_eq!(simplify(&wobbly, 0.5), vec![wobbly[0], wobbly[49]]);
+        assert_eq!(simplify(&wobbly, 0.0), wobbly);
+
+        // A semicircle of radius 100 keeps enough points to stay within the
+        // tolerance of every one it drops
+        let arc: Vec<Point> = (0..=180)
+            .map(|d| {
+                let a = (d as f64).to_radians();
+                pt(100.0 * a.cos(), 100.0 * a.sin())
+            })
+            .collect();
+        let kept = simplify(&arc, 0.5);
+        assert!(kept.len() > 10 && kept.len() < arc.len());
+        assert_eq!((kept[0], kept[kept.len() - 1]), (arc[0], arc[180]));
+        for p in &arc {
+            let off = kept
+                .windows(2)
+                .map(|w| distance_to_segment(*p, w[0], w[1]))
+                .fold(f64::INFINITY, f64::min);
+            assert!(off <= 0.5 + 1e-9);
+        }
+
+        // Closed loops, whose ends coincide, keep their shape too
+        let square = vec![
+            pt(0.0, 0.0),
+            pt(10.0, 0.0),
+            pt(10.0, 10.0),
+            pt(0.0, 10.0),
+            pt(0.0, 0.0),
+        ];
+        assert_eq!(simplify(&square, 1.0), square);
+    }
+
     #[test]
     fn split_segments_splits_on_large_jumps() {
         let pts = vec![
@@ -0,0 +1,151 @@
+use serde::Serialize;
+
+use crate::context::ChartContext;
+use crate::layers::constellations::{constellation_lines, is_shown};
+use crate::layers::grid::{dec_lines, ra_lines};
+use crate::layers::objects::{plotted_objects, symbol_extent, symbol_size};
+use crate::layers::stars::{drawn_stars, star_radius};
+use crate::layout::ChartLayout;
+use crate::types::{CelestialObject, Point};
+
+// The chart's projected geometry, in the same pixel space as the SVG, for
+// front-ends that draw their own canvas. Field names are part of the output
+// schema, so renaming them is a breaking change. Stars and objects are limited
+// to the plot area; polylines are left for the consumer to clip, as the SVG
+// does with its clip path.
+
+#[derive(Serialize)]
+pub(crate) struct ChartJson<'a> {
+    width: u32,
+    height: u32,
+    layout: ChartLayout,
+    stars: Vec<StarJson<'a>>,
+    objects: Vec<ObjectJson<'a>>,
+    grid: GridJson,
+    constellations: Vec<ConstellationJson<'a>>,
+}
+
+#[derive(Serialize)]
+struct StarJson<'a> {
+    x: f64,
+    y: f64,
+    r: f64,
+    mag: f64,
+    name: &'a str,
+}
+
+#[derive(Serialize)]
+struct ObjectJson<'a> {
+    x: f64,
+    y: f64,
+    r: f64,
+    kind: &'a str,
+    catalog: &'a str,
+    id: &'a str,
+    mag: f64,
+    size_arcmin: f64,
+    angle: f64,
+    name: &'a str,
+}
+
+#[derive(Serialize)]
+struct GridJson {
+    ra: Vec<Vec<Point>>,
+    dec: Vec<Vec<Point>>,
+}
+
+#[derive(Serialize)]
+struct ConstellationJson<'a> {
+    name: &'a str,
+    lines: Vec<Vec<Point>>,
+}
+
+/// The objects drawn whose centers land inside the plot area, in catalog
+/// order. Shared with the crossmatch so the two list the same objects.
+pub(crate) fn objects_on_plot<'a>(context: &ChartContext<'a>) -> Vec<(&'a CelestialObject, Point)> {
+    plotted_objects(context)
+        .into_iter()
+        .filter(|&(_, p)| context.layout.contains(p))
+        .collect()
+}
+
+impl<'a> ChartJson<'a> {
+    pub(crate) fn new(context: &ChartContext<'a>) -> Self {
+        let cfg = &context.cfg;
+
+        // The stars the chart draws, merged as the stars layer merges them
+        let stars = drawn_stars(context)
+            .into_iter()
+            .filter(|&(_, p)| context.layout.contains(p))
+            .map(|(s, p)| StarJson {
+                x: p.x,
+                y: p.y,
+                r: star_radius(s.magnitude, cfg.object_scale),
+                mag: s.magnitude,
+                name: &s.name,
+            })
+            .collect();
+
+        // The objects the chart draws, as the objects layer picks them
+        let objects = objects_on_plot(context)
+            .into_iter()
+            .map(|(o, p)| ObjectJson {
+                x: p.x,
+                y: p.y,
+                r: symbol_extent(&o.kind, symbol_size(cfg, o)),
+                kind: &o.kind,
+                catalog: &o.catalog,
+                id: &o.identifier,
+                mag: o.magnitude,
+                size_arcmin: o.size.major,
+                angle: o.angle,
+                name: &o.name,
+            })
+            .collect();
+
+        let constellations = context
+            .data
+            .constellations
+            .iter()
+            .filter(|c| is_shown(context, c))
+            .map(|c| ConstellationJson {
+                name: &c.name,
+                lines: constellation_lines(context, c),
+            })
+            .filter(|c| !c.lines.is_empty())
+            .collect();
+
+        Self {
+            width: cfg.width,
+            height: cfg.height,
+            layout: context.layout,
+            stars,
+            objects,
+            grid: GridJson {
+                ra: ra_lines(context),
+                dec: dec_lines(context),
+            },
+            constellations,
+        }
+    }
+
+    /// How many stars, objects and lines are listed, and which of the layers
+    /// they come from lists the most
+    pub(crate) fn size(&self) -> (usize, &'static str) {
+        let counts = [
+            (self.stars.len(), "stars"),
+            (self.objects.len(), "objects"),
+            (self.grid.ra.len() + self.grid.dec.len(), "grid"),
+            (
+                self.constellations.iter().map(|c| c.lines.len()).sum(),
+                "constellations",
+            ),
+        ];
+        let total = counts.iter().map(|&(n, _)| n).sum();
+        let most = counts
+            .iter()
+            .max_by_key(|&&(n, _)| n)
+            .map_or("", |&(_, layer)| layer);
+        (total, most)
+    }
+}
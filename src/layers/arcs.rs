@@ -0,0 +1,116 @@
+use svg::node::element::path::Data;
+use svg::node::element::{Group, Path};
+
+use crate::context::ChartContext;
+use crate::geometry::{great_circle_points, split_segments};
+use crate::layers::{group_with_id_class, text, Layer};
+
+// Every arc is drawn with at least this many segments, however short
+const MIN_ARC_SEGMENTS: f64 = 32.0;
+
+pub struct ArcsLayer;
+impl ArcsLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+// An angular distance in degrees, or arcminutes below a degree
+fn separation_label(deg: f64) -> String {
+    if deg < 1.0 {
+        format!("{:.1}′", deg * 60.0)
+    } else {
+        format!("{deg:.1}°")
+    }
+}
+
+impl Layer for ArcsLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-arcs", "arcs");
+        let step = context.cfg.sampling_step_deg.unwrap_or(1) as f64;
+
+        for arc in &context.cfg.arcs {
+            let separation = arc.from.separation_deg(arc.to);
+            let eqs =
+                great_circle_points(arc.from, arc.to, step.min(separation / MIN_ARC_SEGMENTS));
+
+            // Samples outside the RA/Dec clip ranges break the arc into runs
+            for run in eqs.split(|&eq| !context.in_sky_range(eq)) {
+                let pts: Vec<_> = run
+                    .iter()
+                    .filter_map(|&eq| context.project_to_pixels(eq))
+                    .collect();
+                for seg in split_segments(&pts, context.layout.split_threshold)
+                    .into_iter()
+                    .filter(|s| s.len() >= 2)
+                {
+                    let mut d = Data::new()
+                        .move_to((context.fmt_coord(seg[0].x), context.fmt_coord(seg[0].y)));
+                    for p in &seg[1..] {
+                        d = d.line_to((context.fmt_coord(p.x), context.fmt_coord(p.y)));
+                    }
+                    g = g.add(
+                        Path::new()
+                            .set("class", "arc")
+                            .set("fill", "none")
+                            .set("d", d),
+                    );
+                }
+            }
+
+            // Label the arc just above its midpoint
+            let mid = eqs[eqs.len() / 2];
+            if context.in_sky_range(mid)
+                && let Some(p) = context.project_to_pixels(mid)
+                && context.fits_plot(p, 0.0)
+            {
+                g = g.add(text(
+                    context,
+                    "arc-label",
+                    p.x,
+                    p.y - 4.0,
+                    "middle",
+                    &separation_label(separation),
+                ));
+            }
+        }
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Arc;
+    use crate::test_utils::make_context;
+    use crate::types::EQPoint;
+
+    #[test]
+    fn arc_is_a_path_labeled_with_its_length() {
+        let context = make_context(|cfg| {
+            cfg.arcs = vec![Arc {
+                from: EQPoint {
+                    ra_deg: 350.0,
+                    dec_deg: -5.0,
+                },
+                to: EQPoint {
+                    ra_deg: 10.0,
+                    dec_deg: 5.0,
+                },
+            }]
+        });
+        let doc = ArcsLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("class=\"arc\"").count(), 1);
+        let d = doc.split(" d=\"").nth(1).unwrap();
+        let d = &d[..d.find('"').unwrap()];
+        assert!(d.matches('L').count() >= 10, "{d}");
+        assert!(doc.contains("class=\"arc-label\""));
+        assert!(doc.contains("22.3°"), "{doc}");
+    }
+
+    #[test]
+    fn short_separations_read_in_arcminutes() {
+        assert_eq!(separation_label(0.5), "30.0′");
+        assert_eq!(separation_label(12.34), "12.3°");
+    }
+}
@@ -1,9 +1,18 @@
 use svg::node::element::path::Data;
-use svg::node::element::{Group, Path, Text};
+use svg::node::element::{Circle, Group, Path, Text};
 
 use crate::context::ChartContext;
-use crate::geometry::{project, split_segments, to_pixels};
-use crate::layers::{group_with_class, Layer};
+use crate::geometry::{simplify, split_segments};
+use crate::layers::{group_with_id_class, Layer};
+use crate::layout::Bounds;
+use crate::types::{Constellation, Point};
+
+// Radius of the marker at a label's anchor, in pixels
+const CENTROID_MARKER_RADIUS: f64 = 2.0;
+
+// Parts of a figure farther apart than this fraction of the plot's smaller
+// side are labeled separately
+const SEPARATE_PART_FRACTION: f64 = 0.25;
 
 pub struct ConstellationsLayer;
 impl ConstellationsLayer {
@@ -12,72 +21,312 @@ impl ConstellationsLayer {
     }
 }
 
-impl Layer for ConstellationsLayer {
-    fn render(&self, context: &ChartContext<'_>) -> Group {
-        let mut g = group_with_class("constellations");
-        let threshold = context.layout.split_threshold;
-
-        for c in context.data.constellations {
-            let mut all_pts: Vec<crate::types::Point> = Vec::new();
-            for line in &c.lines {
-                let mut pts = Vec::with_capacity(line.len());
-                for &eq in line {
-                    if let Some(tp) = project(
-                        eq,
-                        context.cfg.center,
-                        context.cfg.projection,
-                        context.cfg.position_angle_deg,
-                    ) {
-                        let p = to_pixels(tp, context.layout.center_px, context.layout.scale);
-                        pts.push(p);
-                        all_pts.push(p);
-                    }
-                }
-                // segment & emit paths
-                for seg in split_segments(&pts, threshold)
-                    .into_iter()
-                    .filter(|s| s.len() >= 2)
-                {
-                    let mut d = Data::new().move_to((seg[0].x, seg[0].y));
-                    for p in &seg[1..] {
-                        d = d.line_to((p.x, p.y));
+/// Whether a constellation is drawn, given the `constellations_only` filter
+pub(crate) fn is_shown(context: &ChartContext<'_>, c: &Constellation) -> bool {
+    let only = &context.cfg.constellations_only;
+    only.is_empty() || only.iter().any(|abbr| abbr.eq_ignore_ascii_case(&c.abbr))
+}
+
+// Whether a figure lies wholly outside the field, found without projecting
+// it. The figure fits in a cap about its first vertex, but its segments are
+// drawn straight between projected vertices and can bow out of that cap, so
+// the cap must clear the field by its own radius again.
+fn outside_field(context: &ChartContext<'_>, c: &Constellation, field_radius_deg: f64) -> bool {
+    let mut vertices = c.lines.iter().flatten();
+    let Some(&anchor) = vertices.next() else {
+        return true;
+    };
+    let reach = vertices
+        .map(|&v| anchor.separation_deg(v))
+        .fold(0.0, f64::max);
+    context.cfg.center.separation_deg(anchor) > field_radius_deg + 2.0 * reach
+}
+
+// Projected vertices of each figure line. Vertices outside the RA/Dec clip
+// ranges break the line rather than being bridged over. Figures nowhere near
+// the field are skipped before projecting any of their vertices, unless the
+// plot is unclipped and they can be drawn past it.
+fn projected_runs(context: &ChartContext<'_>, c: &Constellation) -> Vec<Vec<Point>> {
+    if !context.cfg.no_clip
+        && let Some(field_radius) = context.field_radius_deg()
+        && outside_field(context, c, field_radius)
+    {
+        return Vec::new();
+    }
+    c.lines
+        .iter()
+        .flat_map(|line| line.split(|&eq| !context.in_sky_range(eq)))
+        .map(|run| {
+            run.iter()
+                .filter_map(|&eq| context.project_to_pixels(eq))
+                .collect()
+        })
+        .collect()
+}
+
+// A constellation's figure as pixel-space polylines, split where they jump
+pub(crate) fn constellation_lines(
+    context: &ChartContext<'_>,
+    c: &Constellation,
+) -> Vec<Vec<Point>> {
+    projected_runs(context, c)
+        .iter()
+        .flat_map(|pts| split_segments(pts, context.layout.split_threshold))
+        .filter(|s| s.len() >= 2)
+        .collect()
+}
+
+/// Where a constellation's name is drawn, centered on each: the middle of the
+/// bounding box of the visible vertices of each part of its figure. Figure
+/// lines join into one part when they come close, so a figure is usually
+/// labeled once, but one split into far-apart pieces, like Serpens either
+/// side of Ophiuchus, is labeled on each piece rather than in the empty sky
+/// between them.
+pub(crate) fn label_positions(context: &ChartContext<'_>, c: &Constellation) -> Vec<Point> {
+    let l = &context.layout;
+    let max_gap = l.plot_w.min(l.plot_h) * SEPARATE_PART_FRACTION;
+    // The bounds and vertex count of each part
+    let mut parts: Vec<(Bounds, usize)> = Vec::new();
+    for run in projected_runs(context, c) {
+        let Some(bounds) = Bounds::from_points(&run) else {
+            continue;
+        };
+        parts.push((bounds, run.len()));
+        // Merge parts that come close until none do
+        let mut merged = true;
+        while merged {
+            merged = false;
+            'pairs: for i in 0..parts.len() {
+                for j in i + 1..parts.len() {
+                    if parts[i].0.distance_to(&parts[j].0) <= max_gap {
+                        let (bounds, count) = parts.remove(j);
+                        parts[i] = (parts[i].0.union(bounds), parts[i].1 + count);
+                        merged = true;
+                        break 'pairs;
                     }
-                    let path = Path::new()
-                        .set("class", "constellation")
-                        .set("fill", "none")
-                        .set("d", d);
-                    g = g.add(path);
                 }
             }
+        }
+    }
+    parts
+        .into_iter()
+        .filter(|&(_, count)| count >= 2)
+        .map(|(bounds, _)| bounds.center())
+        .collect()
+}
 
-            // Add a label at the bbox center of visible points
-            if all_pts.len() >= 2 {
-                let (mut min_x, mut max_x) = (f64::INFINITY, f64::NEG_INFINITY);
-                let (mut min_y, mut max_y) = (f64::INFINITY, f64::NEG_INFINITY);
-                for p in &all_pts {
-                    if p.x < min_x {
-                        min_x = p.x;
-                    }
-                    if p.x > max_x {
-                        max_x = p.x;
-                    }
-                    if p.y < min_y {
-                        min_y = p.y;
-                    }
-                    if p.y > max_y {
-                        max_y = p.y;
-                    }
+impl Layer for ConstellationsLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-constellations", "constellations");
+
+        for c in context
+            .data
+            .constellations
+            .iter()
+            .filter(|c| is_shown(context, c))
+        {
+            for seg in constellation_lines(context, c) {
+                let seg = simplify(&seg, context.cfg.simplify_tolerance);
+                let mut d =
+                    Data::new().move_to((context.fmt_coord(seg[0].x), context.fmt_coord(seg[0].y)));
+                for p in &seg[1..] {
+                    d = d.line_to((context.fmt_coord(p.x), context.fmt_coord(p.y)));
                 }
-                let (cx, cy) = ((min_x + max_x) * 0.5, (min_y + max_y) * 0.5);
+                let path = Path::new()
+                    .set("class", "constellation")
+                    .set("fill", "none")
+                    .set("d", d);
+                g = g.add(path);
+            }
+
+            for p in label_positions(context, c) {
                 let label: Text = svg::node::element::Text::new(&c.name)
                     .set("class", "constellation-label")
-                    .set("x", cx)
-                    .set("y", cy)
+                    .set("x", context.fmt_coord(p.x))
+                    .set("y", context.fmt_coord(p.y))
                     .set("text-anchor", "middle")
                     .set("dominant-baseline", "middle");
                 g = g.add(label);
+                if context.cfg.show_constellation_centroids {
+                    g = g.add(
+                        Circle::new()
+                            .set("class", "centroid-debug")
+                            .set("cx", context.fmt_coord(p.x))
+                            .set("cy", context.fmt_coord(p.y))
+                            .set("r", CENTROID_MARKER_RADIUS),
+                    );
+                }
             }
         }
         g
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Datasets;
+    use crate::test_utils::{attr_values, make_context, make_context_with};
+    use crate::types::{EQPoint, Projection};
+
+    fn figure(abbr: &str, name: &str, ra_deg: f64) -> Constellation {
+        let point = |dec_deg| EQPoint { ra_deg, dec_deg };
+        Constellation {
+            abbr: abbr.to_string(),
+            name: name.to_string(),
+            lines: vec![vec![point(0.0), point(5.0)]],
+        }
+    }
+
+    fn render_one(figure: Constellation) -> String {
+        let constellations = [figure];
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &constellations,
+        };
+        let context = make_context_with(data, |_| {});
+        ConstellationsLayer::new().render(&context).to_string()
+    }
+
+    fn label_xs(doc: &str) -> Vec<f64> {
+        doc.split("class=\"constellation-label\"")
+            .skip(1)
+            .map(|rest| attr_values(rest, "x")[0])
+            .collect()
+    }
+
+    #[test]
+    fn centroid_markers_sit_on_each_label() {
+        let constellations = [
+            figure("ONE", "One", 0.0),
+            figure("TWO", "Two", 10.0),
+            figure("FAR", "Far", 70.0),
+        ];
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &constellations,
+        };
+        let context = make_context_with(data, |cfg| cfg.show_constellation_centroids = true);
+        let doc = ConstellationsLayer::new().render(&context).to_string();
+        let labels = doc.matches("class=\"constellation-label\"").count();
+        assert_eq!(labels, 2);
+        assert_eq!(doc.matches("class=\"centroid-debug\"").count(), labels);
+        let mut xs = label_xs(&doc);
+        let mut cxs = attr_values(&doc, "cx");
+        xs.sort_by(f64::total_cmp);
+        cxs.sort_by(f64::total_cmp);
+        assert_eq!(xs, cxs);
+
+        let doc = render_one(figure("ONE", "One", 0.0));
+        assert!(!doc.contains("centroid-debug"));
+    }
+
+    #[test]
+    fn figures_far_off_the_field_are_skipped() {
+        // Projectable, but 70° out on a 60° gnomonic chart
+        let doc = render_one(figure("FAR", "Far", 70.0));
+        assert!(!doc.contains("<path"));
+        assert!(!doc.contains("Far"));
+
+        // Both vertices are off the plot, but the segment cuts its corner
+        let context = make_context(|_| {});
+        let (x, y) = (context.layout.plot_x, context.layout.plot_y);
+        let vertex = |x, y| context.pixel_to_eq(Point { x, y }).unwrap();
+        let corner = Constellation {
+            abbr: "CNR".to_string(),
+            name: "Corner".to_string(),
+            lines: vec![vec![vertex(x - 10.0, y + 30.0), vertex(x + 30.0, y - 10.0)]],
+        };
+        assert_eq!(render_one(corner).matches("<path").count(), 1);
+    }
+
+    #[test]
+    fn unclipped_charts_keep_figures_off_the_field() {
+        let constellations = [figure("FAR", "Far", 70.0)];
+        let data = Datasets {
+            stars: &[],
+            objects: &[],
+            constellations: &constellations,
+        };
+        let context = make_context_with(data, |cfg| cfg.no_clip = true);
+        let doc = ConstellationsLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("<path").count(), 1);
+    }
+
+    #[test]
+    fn field_radius_reaches_the_plot_corners() {
+        // 60° across a square plot, so the corners are farther than 30°
+        let context = make_context(|_| {});
+        let radius = context.field_radius_deg().unwrap();
+        let corner = (30f64.to_radians().tan() * 2f64.sqrt()).atan().to_degrees();
+        assert!((radius - corner).abs() < 1e-6, "{radius}");
+
+        let context = make_context(|cfg| cfg.projection = Projection::Mercator);
+        assert!(context.field_radius_deg().is_none());
+    }
+
+    #[test]
+    fn far_apart_parts_are_labeled_separately() {
+        let point = |ra_deg, dec_deg| EQPoint { ra_deg, dec_deg };
+        // Two pieces 40° apart, either side of the chart center
+        let split = Constellation {
+            abbr: "SER".to_string(),
+            name: "Serpens".to_string(),
+            lines: vec![
+                vec![point(335.0, 0.0), point(340.0, 5.0)],
+                vec![point(340.0, 5.0), point(342.0, -3.0)],
+                vec![point(20.0, 0.0), point(25.0, 5.0)],
+            ],
+        };
+        let context = make_context(|_| {});
+        let center_x = context.layout.plot_center().x;
+        let xs = label_xs(&render_one(split));
+        assert_eq!(xs.len(), 2, "{xs:?}");
+        // One over each piece, and neither in the gap between
+        assert!(xs.iter().any(|&x| x > center_x + 100.0), "{xs:?}");
+        assert!(xs.iter().any(|&x| x < center_x - 100.0), "{xs:?}");
+
+        // Pieces close together share a label
+        let joined = Constellation {
+            abbr: "ORI".to_string(),
+            name: "Orion".to_string(),
+            lines: vec![
+                vec![point(355.0, 0.0), point(358.0, 5.0)],
+                vec![point(2.0, 0.0), point(5.0, 5.0)],
+            ],
+        };
+        let xs = label_xs(&render_one(joined));
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0] - center_x).abs() < 1.0, "{xs:?}");
+    }
+
+    #[test]
+    fn only_listed_constellations_are_drawn() {
+        let constellations = vec![figure("ORI", "Orion", 85.0), figure("TAU", "Taurus", 75.0)];
+        let render = |only: &[&str]| {
+            let data = Datasets {
+                stars: &[],
+                objects: &[],
+                constellations: &constellations,
+            };
+            let context = make_context_with(data, |cfg| {
+                cfg.center = EQPoint {
+                    ra_deg: 80.0,
+                    dec_deg: 0.0,
+                };
+                cfg.constellations_only = only.iter().map(|s| s.to_string()).collect();
+            });
+            ConstellationsLayer::new().render(&context).to_string()
+        };
+
+        let all = render(&[]);
+        assert!(all.contains("Orion") && all.contains("Taurus"));
+
+        let only_orion = render(&["ori"]);
+        assert!(only_orion.contains("Orion"));
+        assert!(!only_orion.contains("Taurus"));
+        assert_eq!(only_orion.matches("class=\"constellation\"").count(), 1);
+    }
+}
@@ -0,0 +1,80 @@
+use svg::node::element::Group;
+
+use crate::context::ChartContext;
+use crate::layers::{group_with_id_class, text, Layer};
+use crate::types::{format_dms, format_hms, Point};
+
+pub struct CornerReadoutLayer {
+    offset: f64,
+}
+impl CornerReadoutLayer {
+    pub fn new() -> Self {
+        Self { offset: 26.0 }
+    }
+}
+
+impl Layer for CornerReadoutLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-corner-readouts", "corner-readouts");
+        let l = &context.layout;
+        let (left, top) = (l.plot_x, l.plot_y);
+        let (right, bottom) = (left + l.plot_w, top + l.plot_h);
+
+        // Just outside the frame, clear of the tick labels
+        let corners = [
+            (Point { x: left, y: top }, top - self.offset, "start"),
+            (Point { x: right, y: top }, top - self.offset, "end"),
+            (
+                Point { x: left, y: bottom },
+                bottom + self.offset + 8.0,
+                "start",
+            ),
+            (
+                Point {
+                    x: right,
+                    y: bottom,
+                },
+                bottom + self.offset + 8.0,
+                "end",
+            ),
+        ];
+        for (corner, y, anchor) in corners {
+            let Some(eq) = context.pixel_to_eq(corner) else {
+                continue;
+            };
+            let label = format!("{} {}", format_hms(eq.ra_deg), format_dms(eq.dec_deg));
+            g = g.add(text(context, "corner-readout", corner.x, y, anchor, &label));
+        }
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_context;
+    use crate::types::{EQPoint, Projection};
+
+    #[test]
+    fn readouts_for_all_four_corners() {
+        let context = make_context(|cfg| {
+            cfg.center = EQPoint {
+                ra_deg: 83.8,
+                dec_deg: 40.0,
+            };
+        });
+        let doc = CornerReadoutLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("class=\"corner-readout\"").count(), 4);
+    }
+
+    #[test]
+    fn corners_off_the_sphere_are_skipped() {
+        // An orthographic chart wider than a hemisphere leaves its corners off the globe
+        let context = make_context(|cfg| {
+            cfg.projection = Projection::Spherical;
+            cfg.fov_deg = 120.0;
+        });
+        let doc = CornerReadoutLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("class=\"corner-readout\"").count(), 0);
+    }
+}
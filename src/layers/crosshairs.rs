@@ -0,0 +1,78 @@
+use svg::node::element::Group;
+
+use crate::context::ChartContext;
+use crate::layers::zenith::cross;
+use crate::layers::{group_with_id_class, text, Layer};
+
+pub struct CrosshairLayer;
+impl CrosshairLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Layer for CrosshairLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-crosshairs", "crosshairs");
+        let size = context.cfg.center_marker_size;
+
+        for c in &context.cfg.crosshairs {
+            let Some(p) = context
+                .project_to_pixels(c.position)
+                .filter(|&p| context.layout.contains(p) && context.fits_plot(p, 0.0))
+            else {
+                continue;
+            };
+            let [h, v] = cross(context, p, size);
+            g = g.add(h).add(v);
+            if let Some(label) = &c.label {
+                // Up and to the right of the cross
+                g = g.add(text(
+                    context,
+                    "crosshair-label",
+                    p.x + size / 2.0 + 2.0,
+                    p.y - size / 2.0,
+                    "start",
+                    label,
+                ));
+            }
+        }
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Crosshair;
+    use crate::test_utils::make_context;
+    use crate::types::EQPoint;
+
+    #[test]
+    fn visible_crosshairs_are_drawn_and_labeled() {
+        let context = make_context(|cfg| {
+            cfg.crosshairs = vec![
+                Crosshair {
+                    position: EQPoint {
+                        ra_deg: 5.0,
+                        dec_deg: 5.0,
+                    },
+                    label: Some("Pass".to_string()),
+                },
+                // Off the plot
+                Crosshair {
+                    position: EQPoint {
+                        ra_deg: 90.0,
+                        dec_deg: 0.0,
+                    },
+                    label: Some("Hidden".to_string()),
+                },
+            ]
+        });
+        let doc = CrosshairLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("<line class=\"crosshair\"").count(), 2);
+        assert!(doc.contains("class=\"crosshair-label\""));
+        assert!(doc.contains("Pass"));
+        assert!(!doc.contains("Hidden"));
+    }
+}
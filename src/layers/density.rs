@@ -0,0 +1,106 @@
+use svg::node::element::{Group, Rectangle};
+
+use crate::context::ChartContext;
+use crate::layers::{group_with_id_class, Layer};
+
+// Side of a shading cell, in pixels; the plot is split into whole cells of
+// about this size
+const CELL_PX: f64 = 16.0;
+
+// Opacity of the most crowded cell
+const MAX_OPACITY: f64 = 0.35;
+
+/// Shades the plot by how many stars too faint to draw fall in each cell of
+/// a coarse grid, on a log scale, so the Milky Way shows as a glow
+pub struct DensityLayer;
+impl DensityLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Layer for DensityLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-density", "density-shading");
+        if !context.cfg.density_shading {
+            return g;
+        }
+        let l = &context.layout;
+        let cols = (l.plot_w / CELL_PX).ceil().max(1.0) as usize;
+        let rows = (l.plot_h / CELL_PX).ceil().max(1.0) as usize;
+        let (cell_w, cell_h) = (l.plot_w / cols as f64, l.plot_h / rows as f64);
+
+        let mut counts = vec![0u32; cols * rows];
+        for s in context.data.stars {
+            if s.magnitude <= context.cfg.limit_star_mag || !context.in_sky_range(s.coords) {
+                continue;
+            }
+            if let Some(p) = context.project_to_pixels(s.coords)
+                && l.contains(p)
+            {
+                let col = (((p.x - l.plot_x) / cell_w) as usize).min(cols - 1);
+                let row = (((p.y - l.plot_y) / cell_h) as usize).min(rows - 1);
+                counts[row * cols + col] += 1;
+            }
+        }
+
+        let most = counts.iter().copied().max().unwrap_or(0);
+        if most == 0 {
+            return g;
+        }
+        let scale = MAX_OPACITY / (most as f64).ln_1p();
+        for (i, &count) in counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let (row, col) = (i / cols, i % cols);
+            let opacity = ((count as f64).ln_1p() * scale * 1000.0).round() / 1000.0;
+            g = g.add(
+                Rectangle::new()
+                    .set("class", "density")
+                    .set("x", context.fmt_coord(l.plot_x + col as f64 * cell_w))
+                    .set("y", context.fmt_coord(l.plot_y + row as f64 * cell_h))
+                    .set("width", context.fmt_coord(cell_w))
+                    .set("height", context.fmt_coord(cell_h))
+                    .set("fill-opacity", opacity),
+            );
+        }
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Datasets;
+    use crate::test_utils::{attr_values, make_context_with, make_star};
+
+    #[test]
+    fn crowded_cells_are_shaded_more_than_sparse_ones() {
+        // Fifty stars past the default limit of 10 in one cell, two in
+        // another, and a bright star that's drawn rather than counted
+        let mut stars = vec![make_star(10.0, 10.0, 11.0); 50];
+        stars.extend([
+            make_star(-10.0, -10.0, 11.0),
+            make_star(-10.0, -10.0, 11.0),
+            make_star(0.0, 0.0, 2.0),
+        ]);
+        let render = |density_shading| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| cfg.density_shading = density_shading);
+            DensityLayer::new().render(&context).to_string()
+        };
+
+        assert!(!render(false).contains("<rect"));
+        let doc = render(true);
+        assert_eq!(doc.matches("class=\"density\"").count(), 2);
+        let mut opacities = attr_values(&doc, "fill-opacity");
+        opacities.sort_by(f64::total_cmp);
+        let sparse = (3f64.ln() / 51f64.ln() * MAX_OPACITY * 1000.0).round() / 1000.0;
+        assert_eq!(opacities, vec![sparse, MAX_OPACITY]);
+    }
+}
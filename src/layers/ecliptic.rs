@@ -2,10 +2,30 @@ use svg::node::element::path::Data;
 use svg::node::element::{Group, Path};
 
 use crate::context::ChartContext;
-use crate::geometry::{project, split_segments, to_pixels};
-use crate::layers::{group_with_class, Layer};
+use crate::geometry::{simplify, split_segments};
+use crate::layers::{group_with_id_class, Layer};
 use crate::types::EQPoint;
 
+// Points along the ecliptic every `step_deg` of longitude. The last is at
+// 360° whether or not the step divides it, so the ring closes.
+fn ecliptic_points(step_deg: usize) -> Vec<EQPoint> {
+    let eps = 23.43928_f64.to_radians();
+    (0..360)
+        .step_by(step_deg)
+        .map(|lon_deg| lon_deg as f64)
+        .chain([360.0])
+        .map(|lon_deg| {
+            let lon = lon_deg.to_radians();
+            let dec = (lon.sin() * eps.sin()).asin();
+            let ra = (lon.sin() * eps.cos()).atan2(lon.cos());
+            EQPoint {
+                ra_deg: ra.to_degrees().rem_euclid(360.0),
+                dec_deg: dec.to_degrees(),
+            }
+        })
+        .collect()
+}
+
 pub struct EclipticLayer;
 impl EclipticLayer {
     pub fn new() -> Self {
@@ -15,51 +35,56 @@ impl EclipticLayer {
 
 impl Layer for EclipticLayer {
     fn render(&self, context: &ChartContext<'_>) -> Group {
-        let mut g = group_with_class("ecliptic");
-        let eps = 23.43928_f64.to_radians();
+        let mut g = group_with_id_class("layer-ecliptic", "ecliptic");
 
-        // sample longitudes 0..360 step 2°
-        let mut pts = Vec::new();
-        let mut lon_deg = 0usize;
-        while lon_deg <= 360 {
-            let lon = (lon_deg as f64).to_radians();
-            let dec = (lon.sin() * eps.sin()).asin();
-            let ra = (lon.sin() * eps.cos()).atan2(lon.cos());
+        // Every 2° of longitude unless overridden
+        let step = context.cfg.sampling_step_deg.unwrap_or(2) as usize;
+        let eqs = ecliptic_points(step);
 
-            let eq = EQPoint {
-                ra_deg: ra.to_degrees().rem_euclid(360.0),
-                dec_deg: dec.to_degrees(),
-            };
-            if let Some(tp) = project(
-                eq,
-                context.cfg.center,
-                context.cfg.projection,
-                context.cfg.position_angle_deg,
-            ) {
-                pts.push(to_pixels(
-                    tp,
-                    context.layout.center_px,
-                    context.layout.scale,
-                ));
+        // Samples outside the RA/Dec clip ranges break the curve into runs
+        for run in eqs.split(|&eq| !context.in_sky_range(eq)) {
+            let mut pts = Vec::new();
+            for &eq in run {
+                if let Some(p) = context.project_to_pixels(eq) {
+                    pts.push(p);
+                }
             }
-            lon_deg += 2;
-        }
 
-        for seg in split_segments(&pts, context.layout.split_threshold)
-            .into_iter()
-            .filter(|s| s.len() >= 2)
-        {
-            let mut d = Data::new().move_to((seg[0].x, seg[0].y));
-            for p in &seg[1..] {
-                d = d.line_to((p.x, p.y));
+            for seg in split_segments(&pts, context.layout.split_threshold)
+                .into_iter()
+                .filter(|s| s.len() >= 2)
+            {
+                let seg = simplify(&seg, context.cfg.simplify_tolerance);
+                let mut d =
+                    Data::new().move_to((context.fmt_coord(seg[0].x), context.fmt_coord(seg[0].y)));
+                for p in &seg[1..] {
+                    d = d.line_to((context.fmt_coord(p.x), context.fmt_coord(p.y)));
+                }
+                let path = Path::new()
+                    .set("class", "ecliptic")
+                    .set("fill", "none")
+                    .set("d", d);
+                g = g.add(path);
             }
-            let path = Path::new()
-                .set("class", "ecliptic")
-                .set("fill", "none")
-                .set("d", d);
-            g = g.add(path);
         }
 
         g
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::approx;
+
+    #[test]
+    fn ring_closes_whatever_the_step() {
+        for step in [2, 7, 360] {
+            let points = ecliptic_points(step);
+            let (first, last) = (points[0], points[points.len() - 1]);
+            assert!(approx(first.separation_deg(last), 0.0, 1e-9), "step {step}");
+        }
+        // 2° divides the circle, so 360° isn't sampled twice
+        assert_eq!(ecliptic_points(2).len(), 181);
+    }
+}
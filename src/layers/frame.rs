@@ -1,11 +1,17 @@
 use std::collections::HashSet;
-use svg::node::element::{Group, Line, Rectangle};
+use svg::node::element::{Circle, Group, Line, Rectangle};
 
+use crate::config::{ClipShape, DecTickBand, FrameStyle};
 use crate::context::ChartContext;
 use crate::geometry::{sample_dec_parallel, sample_ra_meridian, split_segments};
-use crate::layers::{group_with_class, text, Layer};
+use crate::layers::grid::grid_values;
+use crate::layers::{group_with_id_class, text, Layer};
 use crate::types::Point;
 
+// The most times a grid line crosses a circular border, as a conic meeting a
+// circle
+const MAX_RIM_CROSSINGS: usize = 4;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Side {
     Top,
@@ -22,6 +28,62 @@ struct Mark {
     label: String,
 }
 
+// Tick positions: every fine step, plus every major step in case the major
+// step isn't a multiple of the fine one
+fn tick_values(fine: f64, major: f64, lo: f64, hi: f64) -> Vec<f64> {
+    let mut values = grid_values(fine, lo, hi);
+    values.extend(grid_values(major, lo, hi));
+    values.sort_by(f64::total_cmp);
+    values.dedup();
+    values
+}
+
+// Dec ticks from -80° to 90°, each with whether it's labeled. The bands set
+// the fine and label steps out to their distance from the equator, narrowest
+// first; beyond them the uniform steps apply.
+fn dec_ticks(bands: &[DecTickBand], fine: f64, major: f64) -> Vec<(f64, bool)> {
+    let mut bands = bands.to_vec();
+    bands.sort_by(|a, b| a.max_abs_dec_deg.total_cmp(&b.max_abs_dec_deg));
+    let schedule = bands
+        .iter()
+        .map(|b| {
+            let label_step = b.label_step_deg.unwrap_or(major);
+            (b.max_abs_dec_deg, b.step_deg, label_step)
+        })
+        .chain([(90.0, fine, major)]);
+
+    let mut ticks = Vec::new();
+    // Ticks this close to or closer to the equator belong to a narrower band
+    let mut inner: Option<f64> = None;
+    for (limit, step, label_step) in schedule {
+        for d in tick_values(step, label_step, -80.0, 90.0) {
+            let a = d.abs();
+            if a <= limit + 1e-9 && inner.is_none_or(|i| a > i + 1e-9) {
+                ticks.push((d, is_multiple(d, label_step)));
+            }
+        }
+        inner = Some(inner.map_or(limit, |i| i.max(limit)));
+    }
+    ticks.sort_by(|a, b| a.0.total_cmp(&b.0));
+    ticks
+}
+
+fn is_multiple(value: f64, step: f64) -> bool {
+    let k = (value / step).round();
+    (value - k * step).abs() < 1e-6
+}
+
+// "5h", or "5h30m" for RA steps that aren't whole hours
+fn ra_tick_label(ra_deg: f64) -> String {
+    let minutes = (ra_deg / 15.0 * 60.0).round() as i64;
+    let (h, m) = (minutes / 60, minutes % 60);
+    if m == 0 {
+        format!("{h}h")
+    } else {
+        format!("{h}h{m:02}m")
+    }
+}
+
 fn dedup_marks(marks: Vec<Mark>) -> Vec<Mark> {
     let mut out = Vec::new();
     let mut seen: HashSet<(Side, i32, i32, String)> = HashSet::new();
@@ -118,57 +180,237 @@ fn edge_hits(
     hits
 }
 
+// Where a polyline crosses the circle, solving each segment's quadratic
+fn circle_hits(poly: &[Point], center: Point, radius: f64) -> Vec<Point> {
+    let mut hits = Vec::new();
+    for w in poly.windows(2) {
+        let a = w[0];
+        let (d, f) = (w[1] - a, a - center);
+        let qa = d.x * d.x + d.y * d.y;
+        if qa == 0.0 {
+            continue;
+        }
+        let qb = 2.0 * (f.x * d.x + f.y * d.y);
+        let qc = f.x * f.x + f.y * f.y - radius * radius;
+        let disc = qb * qb - 4.0 * qa * qc;
+        if disc < 0.0 {
+            continue;
+        }
+        for t in [
+            (-qb - disc.sqrt()) / (2.0 * qa),
+            (-qb + disc.sqrt()) / (2.0 * qa),
+        ] {
+            // Half-open, so a crossing on a shared vertex counts once
+            if (0.0..1.0).contains(&t) {
+                hits.push(a + d * t);
+            }
+        }
+    }
+    hits
+}
+
+/// A coordinate label drawn outside the border
+#[derive(Debug, Clone)]
+pub(crate) struct TickLabel {
+    pub x: f64,
+    /// Baseline
+    pub y: f64,
+    pub anchor: &'static str,
+    pub label: String,
+}
+
+// A tick where a grid line crosses a circular border, as a unit vector
+// pointing straight out from the center
+struct RimTick {
+    hit: Point,
+    ux: f64,
+    uy: f64,
+    major: bool,
+    label: Option<TickLabel>,
+}
+
 pub struct FrameLayer {
     fine_step_ra_deg: f64,
-    fine_step_dec_deg: i32,
+    fine_step_dec_deg: f64,
 }
 impl FrameLayer {
     pub fn new() -> Self {
         Self {
             fine_step_ra_deg: 3.75,
-            fine_step_dec_deg: 2,
+            fine_step_dec_deg: 2.0,
         }
     }
-}
 
-impl Layer for FrameLayer {
-    fn render(&self, context: &ChartContext<'_>) -> Group {
-        let mut g = group_with_class("frame");
+    /// Every tick label the frame draws. The labels layer keeps star and
+    /// object labels clear of these.
+    pub(crate) fn tick_labels(&self, context: &ChartContext<'_>) -> Vec<TickLabel> {
+        if context.cfg.frame_style == FrameStyle::None {
+            return Vec::new();
+        }
+        if context.cfg.clip == ClipShape::Circle {
+            return self
+                .rim_ticks(context)
+                .into_iter()
+                .filter_map(|t| t.label)
+                .collect();
+        }
+        let (ra_marks, dec_marks) = self.edge_marks(context);
+        ra_marks
+            .iter()
+            .chain(&dec_marks)
+            .filter_map(|m| edge_label(context, m))
+            .collect()
+    }
+
+    // Ticks where the grid lines cross the circular border, labeling the
+    // first of any that land close together
+    fn rim_ticks(&self, context: &ChartContext<'_>) -> Vec<RimTick> {
         let l = &context.layout;
-        let (px, py, pw, ph) = (l.plot_x, l.plot_y, l.plot_w, l.plot_h);
-        let (top, bottom, left, right) = (py, py + ph, px, px + pw);
+        let (c, r) = (l.plot_center(), l.inscribed_radius());
+        let sampling = context.cfg.sampling_step_deg;
+        let (step_ra, step_dec) = (context.cfg.step_ra_deg, context.cfg.step_dec_deg);
+        let inner = context.cfg.frame_style == FrameStyle::Inner;
+        let mut lines: Vec<(Vec<Point>, String)> = Vec::new();
+        for ra_deg in tick_values(self.fine_step_ra_deg, step_ra, 0.0, 360.0) {
+            if ra_deg >= 360.0 {
+                continue;
+            }
+            let label = if is_multiple(ra_deg, step_ra) {
+                ra_tick_label(ra_deg)
+            } else {
+                String::new()
+            };
+            let pts = sample_ra_meridian(context, ra_deg, sampling);
+            for seg in split_segments(&pts, l.split_threshold) {
+                lines.push((seg, label.clone()));
+            }
+        }
+        for (d, labeled) in dec_ticks(
+            &context.cfg.dec_tick_bands,
+            self.fine_step_dec_deg,
+            step_dec,
+        ) {
+            let label = if labeled {
+                format!("{d}°")
+            } else {
+                String::new()
+            };
+            let pts = sample_dec_parallel(context, d, sampling);
+            for seg in split_segments(&pts, l.split_threshold) {
+                lines.push((seg, label.clone()));
+            }
+        }
 
-        // Border rectangle
+        let mut ticks = Vec::new();
+        let mut seen: HashSet<(i32, i32, String)> = HashSet::new();
+        // Where a meridian and a parallel meet on the rim, the first label wins
+        let mut labeled: Vec<Point> = Vec::new();
+        for (seg, label) in lines {
+            let hits = circle_hits(&seg, c, r);
+            // A line can cross the rim a few times at most. One with more
+            // hits runs along it, like the equator on a hemisphere chart,
+            // and would scatter ticks all around.
+            if hits.len() > MAX_RIM_CROSSINGS {
+                continue;
+            }
+            for hit in hits {
+                let key = (
+                    (hit.x * 10.0).round() as i32,
+                    (hit.y * 10.0).round() as i32,
+                    label.clone(),
+                );
+                if !seen.insert(key) {
+                    continue;
+                }
+                let (ux, uy) = ((hit.x - c.x) / r, (hit.y - c.y) / r);
+                let mut tick = RimTick {
+                    hit,
+                    ux,
+                    uy,
+                    major: !label.is_empty(),
+                    label: None,
+                };
+                if tick.major && !labeled.iter().any(|p| p.distance(hit) < 16.0) {
+                    labeled.push(hit);
+                    // Anchor the text on the side facing the rim, and keep
+                    // it clear of the tick above or below
+                    let (anchor, y_shift) = match (inner, ux) {
+                        (false, ux) if ux > 0.3 => ("start", 4.0 + uy * 4.0),
+                        (false, ux) if ux < -0.3 => ("end", 4.0 + uy * 4.0),
+                        (false, _) => ("middle", 4.0 + uy * 4.0),
+                        (true, ux) if ux > 0.3 => ("end", 4.0 - uy * 4.0),
+                        (true, ux) if ux < -0.3 => ("start", 4.0 - uy * 4.0),
+                        (true, _) => ("middle", 4.0 - uy * 4.0),
+                    };
+                    let dist = if inner { r - 10.0 } else { r + 10.0 };
+                    tick.label = Some(TickLabel {
+                        x: c.x + ux * dist,
+                        y: c.y + uy * dist + y_shift,
+                        anchor,
+                        label: label.clone(),
+                    });
+                }
+                ticks.push(tick);
+            }
+        }
+        ticks
+    }
+
+    fn render_circular(&self, context: &ChartContext<'_>, mut g: Group) -> Group {
+        let l = &context.layout;
+        let (c, r) = (l.plot_center(), l.inscribed_radius());
         g = g.add(
-            Rectangle::new()
-                .set("x", px)
-                .set("y", py)
-                .set("width", pw)
-                .set("height", ph)
+            Circle::new()
+                .set("cx", context.fmt_coord(c.x))
+                .set("cy", context.fmt_coord(c.y))
+                .set("r", context.fmt_coord(r))
                 .set("fill", "none")
                 .set("stroke", "black")
                 .set("class", "border"),
         );
 
-        // RA ticks (top/bottom)
+        for t in self.rim_ticks(context) {
+            let len = tick_direction(context) * if t.major { 6.0 } else { 3.0 };
+            g = g.add(
+                Line::new()
+                    .set("x1", context.fmt_coord(t.hit.x))
+                    .set("y1", context.fmt_coord(t.hit.y))
+                    .set("x2", context.fmt_coord(t.hit.x + t.ux * len))
+                    .set("y2", context.fmt_coord(t.hit.y + t.uy * len))
+                    .set("class", "tick"),
+            );
+            if let Some(label) = t.label {
+                g = g.add(text(
+                    context,
+                    "tick-label",
+                    label.x,
+                    label.y,
+                    label.anchor,
+                    &label.label,
+                ));
+            }
+        }
+        g
+    }
+
+    // Ticks where the grid crosses a rectangular border: RA meridians on the
+    // top and bottom, Dec parallels on the left and right
+    fn edge_marks(&self, context: &ChartContext<'_>) -> (Vec<Mark>, Vec<Mark>) {
+        let l = &context.layout;
+        let (top, bottom, left, right) =
+            (l.plot_y, l.plot_y + l.plot_h, l.plot_x, l.plot_x + l.plot_w);
+
         let mut ra_marks: Vec<Mark> = Vec::new();
-        let ra_step_h = self.fine_step_ra_deg / 15.0;
-        let n = (24.0 / ra_step_h).floor() as usize;
-        let is_major = |ra_deg: f64| -> bool {
-            let step = context.cfg.step_ra_deg as f64;
-            let k = (ra_deg / step).round();
-            (ra_deg - k * step).abs() < 1e-8
-        };
-
-        for i in 0..n {
-            let h = i as f64 * ra_step_h;
-            let ra_deg = h * 15.0;
-
-            let pts = sample_ra_meridian(context, ra_deg, None);
+        let step_ra = context.cfg.step_ra_deg;
+        for ra_deg in tick_values(self.fine_step_ra_deg, step_ra, 0.0, 360.0) {
+            if ra_deg >= 360.0 {
+                continue;
+            }
+            let pts = sample_ra_meridian(context, ra_deg, context.cfg.sampling_step_deg);
             for seg in split_segments(&pts, l.split_threshold) {
                 for mut m in edge_hits(&seg, &[Side::Top, Side::Bottom], top, bottom, left, right) {
-                    if is_major(ra_deg) {
-                        m.label = format!("{:.0}h", h.round());
+                    if is_multiple(ra_deg, step_ra) {
+                        m.label = ra_tick_label(ra_deg);
                         ra_marks.push(m.clone());
                     }
                     ra_marks.push(m);
@@ -176,47 +418,17 @@ impl Layer for FrameLayer {
             }
         }
 
-        for m in dedup_marks(ra_marks) {
-            match m.side {
-                Side::Top => {
-                    let len = if m.label.is_empty() { 3.0 } else { 6.0 };
-                    g = g.add(
-                        Line::new()
-                            .set("x1", m.x)
-                            .set("y1", top)
-                            .set("x2", m.x)
-                            .set("y2", top - len)
-                            .set("class", "tick"),
-                    );
-                    if !m.label.is_empty() {
-                        g = g.add(text("tick-label", m.x, top - 10.0, "middle", &m.label));
-                    }
-                }
-                Side::Bottom => {
-                    let len = 6.0;
-                    g = g.add(
-                        Line::new()
-                            .set("x1", m.x)
-                            .set("y1", bottom)
-                            .set("x2", m.x)
-                            .set("y2", bottom + len)
-                            .set("class", "tick"),
-                    );
-                    if !m.label.is_empty() {
-                        g = g.add(text("tick-label", m.x, bottom + 20.0, "middle", &m.label));
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        // Dec ticks (left/right)
         let mut dec_marks: Vec<Mark> = Vec::new();
-        for d in (-80..=90).step_by(self.fine_step_dec_deg as usize) {
-            let pts = sample_dec_parallel(context, d as f64, None);
+        let step_dec = context.cfg.step_dec_deg;
+        for (d, labeled) in dec_ticks(
+            &context.cfg.dec_tick_bands,
+            self.fine_step_dec_deg,
+            step_dec,
+        ) {
+            let pts = sample_dec_parallel(context, d, context.cfg.sampling_step_deg);
             for seg in split_segments(&pts, l.split_threshold) {
                 for mut m in edge_hits(&seg, &[Side::Left, Side::Right], top, bottom, left, right) {
-                    if d % (context.cfg.step_dec_deg as i32) == 0 {
+                    if labeled {
                         m.label = format!("{d}°");
                         dec_marks.push(m.clone());
                     }
@@ -224,47 +436,272 @@ impl Layer for FrameLayer {
                 }
             }
         }
+        (dedup_marks(ra_marks), dedup_marks(dec_marks))
+    }
+}
 
-        for m in dedup_marks(dec_marks) {
-            match m.side {
-                Side::Left => {
-                    let len = if m.label.is_empty() { 3.0 } else { 6.0 };
-                    g = g.add(
-                        Line::new()
-                            .set("x1", left)
-                            .set("y1", m.y)
-                            .set("x2", left - len)
-                            .set("y2", m.y)
-                            .set("class", "tick"),
-                    );
-                    if !m.label.is_empty() {
-                        g = g.add(text("tick-label", left - 10.0, m.y + 4.0, "end", &m.label));
-                    }
-                }
-                Side::Right => {
-                    let len = if m.label.is_empty() { 3.0 } else { 6.0 };
-                    g = g.add(
-                        Line::new()
-                            .set("x1", right)
-                            .set("y1", m.y)
-                            .set("x2", right + len)
-                            .set("y2", m.y)
-                            .set("class", "tick"),
-                    );
-                    if !m.label.is_empty() {
-                        g = g.add(text(
-                            "tick-label",
-                            right + 10.0,
-                            m.y + 4.0,
-                            "start",
-                            &m.label,
-                        ));
-                    }
-                }
-                _ => {}
+// 1 for ticks pointing out of the plot area, -1 for ticks pointing into it
+fn tick_direction(context: &ChartContext<'_>) -> f64 {
+    if context.cfg.frame_style == FrameStyle::Inner {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+// Where a labeled tick on a rectangular border puts its label
+fn edge_label(context: &ChartContext<'_>, m: &Mark) -> Option<TickLabel> {
+    if m.label.is_empty() {
+        return None;
+    }
+    let l = &context.layout;
+    let (top, bottom, left, right) = (l.plot_y, l.plot_y + l.plot_h, l.plot_x, l.plot_x + l.plot_w);
+    let (x, y, anchor) = if context.cfg.frame_style == FrameStyle::Inner {
+        match m.side {
+            Side::Top => (m.x, top + 20.0, "middle"),
+            Side::Bottom => (m.x, bottom - 10.0, "middle"),
+            Side::Left => (left + 10.0, m.y + 4.0, "start"),
+            Side::Right => (right - 10.0, m.y + 4.0, "end"),
+        }
+    } else {
+        match m.side {
+            Side::Top => (m.x, top - 10.0, "middle"),
+            Side::Bottom => (m.x, bottom + 20.0, "middle"),
+            Side::Left => (left - 10.0, m.y + 4.0, "end"),
+            Side::Right => (right + 10.0, m.y + 4.0, "start"),
+        }
+    };
+    Some(TickLabel {
+        x,
+        y,
+        anchor,
+        label: m.label.clone(),
+    })
+}
+
+impl Layer for FrameLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-frame", "frame");
+        let l = &context.layout;
+        let (px, py, pw, ph) = (l.plot_x, l.plot_y, l.plot_w, l.plot_h);
+        let (top, bottom, left, right) = (py, py + ph, px, px + pw);
+
+        if context.cfg.frame_style == FrameStyle::None {
+            return g;
+        }
+
+        // A circular plot gets a circular border, with its ticks around the rim
+        if context.cfg.clip == ClipShape::Circle {
+            return self.render_circular(context, g);
+        }
+
+        // Border rectangle
+        g = g.add(
+            Rectangle::new()
+                .set("x", context.fmt_coord(px))
+                .set("y", context.fmt_coord(py))
+                .set("width", context.fmt_coord(pw))
+                .set("height", context.fmt_coord(ph))
+                .set("fill", "none")
+                .set("stroke", "black")
+                .set("class", "border"),
+        );
+
+        let (ra_marks, dec_marks) = self.edge_marks(context);
+        for m in ra_marks.iter().chain(&dec_marks) {
+            // Top, left and right ticks are shorter between labels
+            let len = tick_direction(context)
+                * if m.label.is_empty() && m.side != Side::Bottom {
+                    3.0
+                } else {
+                    6.0
+                };
+            let (x1, y1, x2, y2) = match m.side {
+                Side::Top => (m.x, top, m.x, top - len),
+                Side::Bottom => (m.x, bottom, m.x, bottom + len),
+                Side::Left => (left, m.y, left - len, m.y),
+                Side::Right => (right, m.y, right + len, m.y),
+            };
+            g = g.add(
+                Line::new()
+                    .set("x1", context.fmt_coord(x1))
+                    .set("y1", context.fmt_coord(y1))
+                    .set("x2", context.fmt_coord(x2))
+                    .set("y2", context.fmt_coord(y2))
+                    .set("class", "tick"),
+            );
+            if let Some(label) = edge_label(context, m) {
+                g = g.add(text(
+                    context,
+                    "tick-label",
+                    label.x,
+                    label.y,
+                    label.anchor,
+                    &label.label,
+                ));
             }
         }
 
         g
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{attr_values, make_context};
+
+    #[test]
+    fn ticks_include_majors_off_the_fine_grid() {
+        assert_eq!(
+            tick_values(2.0, 3.0, 0.0, 6.0),
+            vec![0.0, 2.0, 3.0, 4.0, 6.0]
+        );
+        assert_eq!(tick_values(2.0, 0.5, 0.0, 1.0), vec![0.0, 0.5, 1.0]);
+        assert!(is_multiple(1.5, 0.5));
+        assert!(!is_multiple(2.0, 3.0));
+    }
+
+    #[test]
+    fn dec_tick_bands_tick_finer_near_the_equator() {
+        let bands = [DecTickBand {
+            max_abs_dec_deg: 20.0,
+            step_deg: 1.0,
+            label_step_deg: Some(5.0),
+        }];
+        let ticks = dec_ticks(&bands, 2.0, 10.0);
+        let within = ticks.iter().filter(|(d, _)| d.abs() <= 20.0).count();
+        let outside = ticks
+            .iter()
+            .filter(|(d, _)| d.abs() > 20.0 && d.abs() <= 40.0)
+            .count();
+        assert_eq!(within, 41);
+        assert_eq!(outside, 20);
+        // Labels every 5° within the band, and on the grid lines beyond
+        let labeled: Vec<f64> = ticks.iter().filter(|t| t.1).map(|t| t.0).collect();
+        assert!(labeled.contains(&15.0) && labeled.contains(&-20.0));
+        assert!(!labeled.contains(&25.0) && labeled.contains(&30.0));
+        // Without bands the spacing is uniform
+        let uniform = dec_ticks(&[], 2.0, 10.0);
+        assert_eq!(
+            uniform,
+            tick_values(2.0, 10.0, -80.0, 90.0)
+                .into_iter()
+                .map(|d| (d, is_multiple(d, 10.0)))
+                .collect::<Vec<_>>()
+        );
+
+        // On the frame, the left edge of an equatorial chart gets more ticks
+        // within the band than beyond it
+        let context = make_context(|cfg| {
+            cfg.dec_tick_bands = bands.to_vec();
+            cfg.fov_deg = 70.0;
+        });
+        let (_, dec_marks) = FrameLayer::new().edge_marks(&context);
+        let left: Vec<&Mark> = dec_marks.iter().filter(|m| m.side == Side::Left).collect();
+        let near = left
+            .iter()
+            .filter(|m| {
+                let eq = context.pixel_to_eq(Point { x: m.x, y: m.y }).unwrap();
+                eq.dec_deg.abs() <= 20.0 + 1e-6
+            })
+            .count();
+        assert!(near > left.len() - near, "{near} of {}", left.len());
+    }
+
+    #[test]
+    fn circular_frame_labels_ra_and_dec_around_the_rim() {
+        let context = make_context(|cfg| cfg.clip = ClipShape::Circle);
+        let doc = FrameLayer::new().render(&context).to_string();
+        assert!(doc.contains("<circle"));
+        assert!(!doc.contains("<rect"));
+        for label in ["23h", "0h", "1h", "-10°", "0°", "10°"] {
+            assert!(doc.contains(label), "{label}");
+        }
+
+        // Every label sits just outside the border
+        let l = &context.layout;
+        let xs = attr_values(&doc, "x");
+        let ys = attr_values(&doc, "y");
+        assert!(!xs.is_empty());
+        for (x, y) in xs.iter().zip(&ys) {
+            let dist = (x - l.plot_center().x).hypot(y - l.plot_center().y);
+            assert!(dist > l.inscribed_radius(), "{dist}");
+        }
+    }
+
+    #[test]
+    fn inner_ticks_and_labels_point_into_the_plot() {
+        let context = make_context(|cfg| cfg.frame_style = FrameStyle::Inner);
+        let doc = FrameLayer::new().render(&context).to_string();
+        let l = &context.layout;
+        // Allowing for a tick found a hair past a corner
+        let inside_x = |x: &f64| (l.plot_x - 1e-6..=l.plot_x + l.plot_w + 1e-6).contains(x);
+        let inside_y = |y: &f64| (l.plot_y - 1e-6..=l.plot_y + l.plot_h + 1e-6).contains(y);
+        let (x2s, y2s) = (attr_values(&doc, "x2"), attr_values(&doc, "y2"));
+        assert!(!x2s.is_empty());
+        assert!(x2s.iter().all(inside_x) && y2s.iter().all(inside_y));
+        let labels = FrameLayer::new().tick_labels(&context);
+        assert!(!labels.is_empty());
+        assert!(labels.iter().all(|t| inside_x(&t.x) && inside_y(&t.y)));
+
+        // Around a circular rim too
+        let context = make_context(|cfg| {
+            cfg.clip = ClipShape::Circle;
+            cfg.frame_style = FrameStyle::Inner;
+        });
+        let (c, r) = (
+            context.layout.plot_center(),
+            context.layout.inscribed_radius(),
+        );
+        let labels = FrameLayer::new().tick_labels(&context);
+        assert!(!labels.is_empty());
+        for t in labels {
+            assert!(Point { x: t.x, y: t.y }.distance(c) < r, "{}", t.label);
+        }
+    }
+
+    #[test]
+    fn no_frame_draws_nothing() {
+        for clip in [ClipShape::Rect, ClipShape::Circle] {
+            let context = make_context(|cfg| {
+                cfg.clip = clip;
+                cfg.frame_style = FrameStyle::None;
+            });
+            let doc = FrameLayer::new().render(&context).to_string();
+            assert!(!doc.contains("<rect") && !doc.contains("<circle"));
+            assert!(!doc.contains("tick"));
+            assert!(FrameLayer::new().tick_labels(&context).is_empty());
+        }
+    }
+
+    #[test]
+    fn a_parallel_along_the_rim_gets_no_ticks() {
+        // The equator is the rim of a hemisphere chart around the pole
+        let context = make_context(|cfg| {
+            cfg.clip = ClipShape::Circle;
+            cfg.projection = crate::types::Projection::Stereographic;
+            cfg.fov_deg = 180.0;
+            cfg.center.dec_deg = 90.0;
+        });
+        let labels = FrameLayer::new().tick_labels(&context);
+        assert!(labels.iter().all(|t| t.label != "0°"));
+        // The meridians still label the rim, once each
+        assert_eq!(labels.iter().filter(|t| t.label == "6h").count(), 1);
+    }
+
+    #[test]
+    fn circle_hits_find_both_crossings() {
+        let c = Point { x: 0.0, y: 0.0 };
+        let line = [Point { x: -10.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }];
+        let hits = circle_hits(&line, c, 5.0);
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0].x + 5.0).abs() < 1e-9 && (hits[1].x - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ra_labels_show_minutes_for_fractional_hours() {
+        assert_eq!(ra_tick_label(75.0), "5h");
+        assert_eq!(ra_tick_label(82.5), "5h30m");
+    }
+}
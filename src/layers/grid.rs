@@ -2,8 +2,9 @@ use svg::node::element::path::Data;
 use svg::node::element::{Group, Path};
 
 use crate::context::ChartContext;
-use crate::geometry::{sample_dec_parallel, sample_ra_meridian, split_segments};
-use crate::layers::{group_with_class, Layer};
+use crate::geometry::{sample_dec_parallel, sample_ra_meridian, simplify, split_segments};
+use crate::layers::{group_with_id_class, Layer};
+use crate::types::Point;
 
 pub struct GridLayer;
 impl GridLayer {
@@ -12,58 +13,211 @@ impl GridLayer {
     }
 }
 
-impl Layer for GridLayer {
-    fn render(&self, context: &ChartContext<'_>) -> Group {
-        let mut g = group_with_class("lines");
-        let threshold = context.layout.split_threshold;
+/// Multiples of `step` within `[lo, hi]`, rounded so fractional steps
+/// like 0.1° don't pick up floating point noise
+pub(crate) fn grid_values(step: f64, lo: f64, hi: f64) -> Vec<f64> {
+    let first = (lo / step - 1e-9).ceil() as i64;
+    let last = (hi / step + 1e-9).floor() as i64;
+    (first..=last)
+        .map(|k| (k as f64 * step * 1e9).round() / 1e9)
+        .collect()
+}
 
-        // RA (hours)
-        let mut ra_step_h = ((context.cfg.step_ra_deg as f64) / 15.0).round() as i32;
-        if ra_step_h < 1 {
-            ra_step_h = 1;
-        }
+/// The values `grid_values` gives for `step` split into `divisions` parts,
+/// less those of `step` itself
+pub(crate) fn minor_grid_values(step: f64, divisions: u32, lo: f64, hi: f64) -> Vec<f64> {
+    if divisions <= 1 {
+        return Vec::new();
+    }
+    let major = grid_values(step, lo, hi);
+    grid_values(step / divisions as f64, lo, hi)
+        .into_iter()
+        .filter(|v| !major.iter().any(|m| (m - v).abs() < 1e-6))
+        .collect()
+}
+
+// Hour-circle polylines in pixel space, split where they jump
+pub(crate) fn ra_lines(context: &ChartContext<'_>) -> Vec<Vec<Point>> {
+    meridians(context, grid_values(context.cfg.step_ra_deg, 0.0, 360.0))
+}
+
+// Declination-parallel polylines in pixel space, split where they jump
+pub(crate) fn dec_lines(context: &ChartContext<'_>) -> Vec<Vec<Point>> {
+    parallels(context, grid_values(context.cfg.step_dec_deg, -90.0, 90.0))
+}
+
+fn meridians(context: &ChartContext<'_>, values: Vec<f64>) -> Vec<Vec<Point>> {
+    let threshold = context.layout.split_threshold;
+    let sampling = context.cfg.sampling_step_deg;
+    let mut lines = Vec::new();
 
-        let mut h = 0;
-        while h < 24 {
-            let ra_deg = (h as f64) * 15.0;
-            for seg in split_segments(&sample_ra_meridian(context, ra_deg, None), threshold) {
-                if seg.len() < 2 {
-                    continue;
-                }
-                let mut d = Data::new().move_to((seg[0].x, seg[0].y));
-                for p in &seg[1..] {
-                    d = d.line_to((p.x, p.y));
-                }
-                let path = Path::new()
-                    .set("class", "graticule ra")
-                    .set("fill", "none")
-                    .set("d", d);
-                g = g.add(path);
-            }
-            h += ra_step_h;
+    for ra_deg in values {
+        if ra_deg >= 360.0 {
+            continue;
         }
+        let pts = sample_ra_meridian(context, ra_deg, sampling);
+        lines.extend(
+            split_segments(&pts, threshold)
+                .into_iter()
+                .filter(|s| s.len() >= 2),
+        );
+    }
+    lines
+}
+
+// The poles are points, so they're skipped
+fn parallels(context: &ChartContext<'_>, values: Vec<f64>) -> Vec<Vec<Point>> {
+    let threshold = context.layout.split_threshold;
+    let sampling = context.cfg.sampling_step_deg;
+    let mut lines = Vec::new();
 
-        // Dec
-        let step_dec = context.cfg.step_dec_deg as i32;
-        let mut dec = -80;
-        while dec <= 90 {
-            for seg in split_segments(&sample_dec_parallel(context, dec as f64, None), threshold) {
-                if seg.len() < 2 {
-                    continue;
-                }
-                let mut d = Data::new().move_to((seg[0].x, seg[0].y));
-                for p in &seg[1..] {
-                    d = d.line_to((p.x, p.y));
-                }
-                let path = Path::new()
-                    .set("class", "graticule dec")
-                    .set("fill", "none")
-                    .set("d", d);
-                g = g.add(path);
-            }
-            dec += step_dec;
+    for dec in values {
+        if dec.abs() >= 90.0 {
+            continue;
         }
+        let pts = sample_dec_parallel(context, dec, sampling);
+        lines.extend(
+            split_segments(&pts, threshold)
+                .into_iter()
+                .filter(|s| s.len() >= 2),
+        );
+    }
+    lines
+}
 
+fn polyline_path(context: &ChartContext<'_>, class: &str, seg: &[Point]) -> Path {
+    let seg = simplify(seg, context.cfg.simplify_tolerance);
+    let mut d = Data::new().move_to((context.fmt_coord(seg[0].x), context.fmt_coord(seg[0].y)));
+    for p in &seg[1..] {
+        d = d.line_to((context.fmt_coord(p.x), context.fmt_coord(p.y)));
+    }
+    Path::new()
+        .set("class", class)
+        .set("fill", "none")
+        .set("d", d)
+}
+
+impl Layer for GridLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-grid", "lines");
+        // Minor lines go first, beneath the major ones they run between
+        let divisions = context.cfg.minor_grid_divisions;
+        let (step_ra, step_dec) = (context.cfg.step_ra_deg, context.cfg.step_dec_deg);
+        for seg in meridians(context, minor_grid_values(step_ra, divisions, 0.0, 360.0)) {
+            g = g.add(polyline_path(context, "graticule minor ra", &seg));
+        }
+        for seg in parallels(context, minor_grid_values(step_dec, divisions, -90.0, 90.0)) {
+            g = g.add(polyline_path(context, "graticule minor dec", &seg));
+        }
+        for seg in ra_lines(context) {
+            g = g.add(polyline_path(context, "graticule ra", &seg));
+        }
+        for seg in dec_lines(context) {
+            g = g.add(polyline_path(context, "graticule dec", &seg));
+        }
         g
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_context;
+
+    fn vertex_count(sampling_step_deg: u32) -> usize {
+        let context = make_context(|cfg| cfg.sampling_step_deg = Some(sampling_step_deg));
+        let doc = GridLayer::new().render(&context).to_string();
+        doc.matches(['M', 'L']).count()
+    }
+
+    #[test]
+    fn simplifying_collapses_straight_gridlines_only() {
+        // The central meridian of a gnomonic chart is a straight line, but
+        // parallels away from the equator curve
+        let context = make_context(|cfg| cfg.sampling_step_deg = Some(1));
+        let meridian = &meridians(&context, vec![0.0])[0];
+        let parallel = &parallels(&context, vec![20.0])[0];
+        assert!(meridian.len() > 10 && parallel.len() > 10);
+        assert_eq!(simplify(meridian, 0.25).len(), 2);
+        let kept = simplify(parallel, 0.25);
+        assert!(kept.len() > 5, "{}", kept.len());
+
+        let simplified = make_context(|cfg| {
+            cfg.sampling_step_deg = Some(1);
+            cfg.simplify_tolerance = 0.25;
+        });
+        let vertices = |context| {
+            GridLayer::new()
+                .render(context)
+                .to_string()
+                .matches(['M', 'L'])
+                .count()
+        };
+        assert!(vertices(&simplified) < vertices(&context));
+    }
+
+    #[test]
+    fn grid_values_handles_fractional_steps() {
+        assert_eq!(grid_values(10.0, -90.0, 90.0).len(), 19);
+        assert_eq!(grid_values(0.1, 0.0, 0.3), vec![0.0, 0.1, 0.2, 0.3]);
+        assert_eq!(grid_values(0.5, -1.2, 0.7), vec![-1.0, -0.5, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn half_degree_dec_step_doubles_the_parallels() {
+        let parallels = |step: f64| {
+            let context = make_context(|cfg| {
+                cfg.fov_deg = 10.0;
+                cfg.step_dec_deg = step;
+                cfg.dec_range = Some((0.0, 9.9));
+            });
+            GridLayer::new()
+                .render(&context)
+                .to_string()
+                .matches("graticule dec")
+                .count()
+        };
+        assert!(parallels(1.0) > 0);
+        assert_eq!(parallels(0.5), 2 * parallels(1.0));
+    }
+
+    #[test]
+    fn minor_grid_values_fall_between_the_major_ones() {
+        assert!(minor_grid_values(10.0, 1, -90.0, 90.0).is_empty());
+        assert_eq!(minor_grid_values(10.0, 2, 0.0, 30.0), vec![5.0, 15.0, 25.0]);
+        assert_eq!(
+            minor_grid_values(1.0, 3, 0.0, 1.0),
+            vec![0.333333333, 0.666666667]
+        );
+    }
+
+    #[test]
+    fn minor_divisions_add_lighter_lines_between_the_major_ones() {
+        let render = |divisions: u32| {
+            let context = make_context(|cfg| {
+                cfg.fov_deg = 10.0;
+                cfg.step_ra_deg = 1.0;
+                cfg.step_dec_deg = 1.0;
+                cfg.ra_range = Some((0.0, 9.9));
+                cfg.dec_range = Some((0.0, 9.9));
+                cfg.minor_grid_divisions = divisions;
+            });
+            GridLayer::new().render(&context).to_string()
+        };
+        let plain = render(1);
+        assert!(!plain.contains("minor"));
+        let doc = render(2);
+        let majors = doc.matches("graticule dec").count();
+        assert_eq!(majors, plain.matches("graticule dec").count());
+        assert_eq!(doc.matches("graticule minor dec").count(), majors);
+        assert_eq!(
+            doc.matches("class=\"graticule").count(),
+            2 * plain.matches("class=\"graticule").count()
+        );
+    }
+
+    #[test]
+    fn sampling_step_overrides_curve_smoothness() {
+        assert!(vertex_count(1) > 5 * vertex_count(10));
+    }
+}
@@ -1,14 +1,76 @@
-use svg::node::element::Group;
+use svg::node::element::{Group, Rectangle};
 
+use crate::config::{ChartConfig, LabelSort};
 use crate::context::ChartContext;
-use crate::geometry::{project, to_pixels};
-use crate::layers::{group_with_class, text, Layer};
-use crate::types::Point;
+use crate::layers::constellations::{is_shown, label_positions};
+use crate::layers::frame::FrameLayer;
+use crate::layers::obstacles::{ObstacleMap, Rect};
+use crate::layers::stars::drawn_stars;
+use crate::layers::{group_with_id_class, text, Layer};
+use crate::layout::Bounds;
+use crate::types::{CelestialObject, EQPoint, Point};
+
+// About the size of a short label
+const OBSTACLE_CELL_SIZE: f64 = 32.0;
+
+// How far north of a label's position to look for the local direction of
+// north, in degrees
+const NORTH_PROBE_DEG: f64 = 0.1;
+
+// The clockwise rotation in degrees that turns a label's up toward north at
+// `coords`, which projects to `p`. It's kept within ±90° so labels never read
+// upside down.
+fn north_rotation(context: &ChartContext<'_>, coords: EQPoint, p: Point) -> Option<f64> {
+    // Near the pole, step south instead and reverse the direction
+    let (dec_deg, sign) = if coords.dec_deg + NORTH_PROBE_DEG <= 90.0 {
+        (coords.dec_deg + NORTH_PROBE_DEG, 1.0)
+    } else {
+        (coords.dec_deg - NORTH_PROBE_DEG, -1.0)
+    };
+    let north = context.project_to_pixels(EQPoint {
+        ra_deg: coords.ra_deg,
+        dec_deg,
+    })?;
+    let (dx, dy) = (sign * (north.x - p.x), sign * (north.y - p.y));
+    if dx == 0.0 && dy == 0.0 {
+        return None;
+    }
+    // Screen up is -y
+    let angle = dx.atan2(-dy).to_degrees();
+    Some(if angle > 90.0 {
+        angle - 180.0
+    } else if angle <= -90.0 {
+        angle + 180.0
+    } else {
+        angle
+    })
+}
+
+// Whether `label_only_catalogs` lets an object be labeled
+fn catalog_labeled(cfg: &ChartConfig, o: &CelestialObject) -> bool {
+    cfg.label_only_catalogs.is_empty()
+        || cfg
+            .label_only_catalogs
+            .iter()
+            .any(|c| o.designation_in(c).is_some())
+}
+
+// Catalogs in the order `LabelSort::Catalog` places their labels; others,
+// such as the star catalogs, follow
+const CATALOG_RANK: [&str; 3] = ["M", "NGC", "IC"];
+
+// An object's place in `CATALOG_RANK` by the best catalog listing it, so a
+// Messier object known by its NGC number still goes first
+fn catalog_rank(o: &CelestialObject) -> usize {
+    CATALOG_RANK
+        .iter()
+        .position(|catalog| o.designation_in(catalog).is_some())
+        .unwrap_or(CATALOG_RANK.len())
+}
 
 pub struct LabelsLayer {
     limit_star_label_mag: f64,
     limit_object_label_mag: f64,
-    symbol_pad: f64,
     offsets: [(f64, f64); 6],
 }
 impl LabelsLayer {
@@ -16,7 +78,6 @@ impl LabelsLayer {
         Self {
             limit_star_label_mag: 1.0,
             limit_object_label_mag: 8.0,
-            symbol_pad: 1.0,
             offsets: [
                 (0.0, -10.0),
                 (0.0, 10.0),
@@ -27,16 +88,25 @@ impl LabelsLayer {
             ],
         }
     }
-    fn boxes_overlap(a: (f64, f64, f64, f64), b: (f64, f64, f64, f64)) -> bool {
-        let (ax, ay, aw, ah) = a;
-        let (bx, by, bw, bh) = b;
-        !(ax + aw <= bx || ax >= bx + bw || ay + ah <= by || ay >= by + bh)
+    fn label_box_centered(&self, cfg: &ChartConfig, x: f64, y_baseline: f64, text: &str) -> Rect {
+        self.label_box(cfg, x, y_baseline, "middle", text)
     }
-    fn label_box_centered(&self, x: f64, y_baseline: f64, text: &str) -> (f64, f64, f64, f64) {
+    fn label_box(
+        &self,
+        cfg: &ChartConfig,
+        x: f64,
+        y_baseline: f64,
+        anchor: &str,
+        text: &str,
+    ) -> Rect {
         let ch = text.chars().count().max(2);
-        let w = (ch as f64 * 7.0).max(16.0);
-        let h = 12.0;
-        let left = x - w / 2.0;
+        let w = (ch as f64 * cfg.label_char_width).max(16.0);
+        let h = cfg.label_line_height;
+        let left = match anchor {
+            "start" => x,
+            "end" => x - w,
+            _ => x - w / 2.0,
+        };
         let top = y_baseline - h;
         (left, top, w, h)
     }
@@ -47,15 +117,24 @@ impl LabelsLayer {
             mag <= self.limit_object_label_mag
         }
     }
-    fn star_symbol_box(&self, p: Point, mag: f64) -> (f64, f64, f64, f64) {
+    /// Whether a star or object gets a label. Shared with the symbol layers
+    /// so `only_labeled` draws exactly the labeled set.
+    pub(crate) fn is_labeled(&self, cfg: &ChartConfig, o: &CelestialObject) -> bool {
+        // `label_only_catalogs` picks among deep-sky objects, not stars
+        let is_star = o.kind.to_lowercase().contains("star");
+        // Messier object labels always fall through to positioning, whichever
+        // catalog the object is known by
+        (is_star || catalog_labeled(cfg, o))
+            && (o.designation_in("M").is_some() || self.should_label(&o.kind, o.magnitude))
+    }
+    fn star_symbol_box(&self, pad: f64, p: Point, mag: f64) -> Rect {
         let mut r = (4.0 - 0.6 * mag).max(0.5);
-        r += self.symbol_pad;
+        r += pad;
         (p.x - r, p.y - r, 2.0 * r, 2.0 * r)
     }
-    fn object_symbol_box(&self, kind: &str, mag: f64, p: Point) -> (f64, f64, f64, f64) {
+    fn object_symbol_box(&self, pad: f64, kind: &str, mag: f64, p: Point) -> Rect {
         let base = 10.0;
         let size = (base - mag).max(4.0);
-        let pad = self.symbol_pad;
         match kind.to_lowercase().as_str() {
             "bright-nebula" => {
                 let half = size / 2.0 + pad;
@@ -79,136 +158,714 @@ impl LabelsLayer {
             }
         }
     }
-    fn seed_symbol_boxes(&self, context: &ChartContext<'_>) -> Vec<(f64, f64, f64, f64)> {
+    fn seed_symbol_boxes(&self, context: &ChartContext<'_>) -> Vec<Rect> {
         let mut boxes = Vec::new();
-        for s in context.data.stars {
-            if s.magnitude > context.cfg.limit_star_mag {
+        let only_labeled = context.cfg.only_labeled;
+        let pad = context.cfg.label_symbol_pad;
+        for (s, p) in drawn_stars(context) {
+            boxes.push(self.star_symbol_box(pad, p, s.magnitude));
+        }
+        for o in context.data.objects {
+            if !context.object_within_limits(o)
+                || !context.in_sky_range(o.coords)
+                || (only_labeled && !self.is_labeled(&context.cfg, o))
+            {
                 continue;
             }
-            if let Some(tp) = project(
-                s.coords,
-                context.cfg.center,
-                context.cfg.projection,
-                context.cfg.position_angle_deg,
-            ) {
-                let p = to_pixels(tp, context.layout.center_px, context.layout.scale);
-                boxes.push(self.star_symbol_box(p, s.magnitude));
+            if let Some(p) = context.project_to_pixels(o.coords) {
+                boxes.push(self.object_symbol_box(pad, &o.kind, o.magnitude, p));
             }
         }
-        for o in context.data.objects {
-            if o.magnitude > context.cfg.limit_object_mag {
+        boxes
+    }
+    // Labels drawn by other layers: the frame's tick labels and the
+    // constellation names
+    fn seed_label_boxes(&self, context: &ChartContext<'_>) -> Vec<Rect> {
+        let cfg = &context.cfg;
+        let mut boxes: Vec<_> = FrameLayer::new()
+            .tick_labels(context)
+            .iter()
+            .map(|t| self.label_box(cfg, t.x, t.y, t.anchor, &t.label))
+            .collect();
+        for c in context.data.constellations {
+            if !is_shown(context, c) {
                 continue;
             }
-            if let Some(tp) = project(
-                o.coords,
-                context.cfg.center,
-                context.cfg.projection,
-                context.cfg.position_angle_deg,
-            ) {
-                let p = to_pixels(tp, context.layout.center_px, context.layout.scale);
-                boxes.push(self.object_symbol_box(&o.kind, o.magnitude, p));
+            // Constellation names are centered vertically on their position
+            for p in label_positions(context, c) {
+                let baseline = p.y + cfg.label_line_height / 2.0;
+                boxes.push(self.label_box(cfg, p.x, baseline, "middle", &c.name));
             }
         }
         boxes
     }
-}
-impl Layer for LabelsLayer {
-    fn render(&self, context: &ChartContext<'_>) -> Group {
-        let mut g = group_with_class("labels");
-        let mut placed = self.seed_symbol_boxes(context);
+    // Everything a label has to stay clear of
+    fn obstacles(&self, context: &ChartContext<'_>) -> Vec<Rect> {
+        self.seed_symbol_boxes(context)
+            .into_iter()
+            .chain(self.seed_label_boxes(context))
+            .collect()
+    }
+    /// The pixel extent of each label placed, turned with the label when
+    /// it's aligned to the grid
+    pub(crate) fn label_extents(&self, context: &ChartContext<'_>) -> Vec<Bounds> {
+        self.place(context, &self.obstacles(context))
+            .iter()
+            .map(|label| {
+                let (x, y, w, h) = label.rect;
+                let center = Point {
+                    x: x + w / 2.0,
+                    y: y + h / 2.0,
+                };
+                let (sin, cos) = label.angle.unwrap_or(0.0).to_radians().sin_cos();
+                let half_w = (w * cos).abs() / 2.0 + (h * sin).abs() / 2.0;
+                let half_h = (w * sin).abs() / 2.0 + (h * cos).abs() / 2.0;
+                Bounds {
+                    min_x: center.x - half_w,
+                    min_y: center.y - half_h,
+                    max_x: center.x + half_w,
+                    max_y: center.y + half_h,
+                }
+            })
+            .collect()
+    }
+    // The labels that find room clear of the obstacles and of each other,
+    // in the order they're placed
+    fn place(&self, context: &ChartContext<'_>, obstacles: &[Rect]) -> Vec<PlacedLabel> {
+        let mut placed = ObstacleMap::new(OBSTACLE_CELL_SIZE);
+        placed.extend(obstacles.iter().copied());
+        let mut labels = Vec::new();
 
         // build candidates (brightest-first)
         #[derive(Clone)]
-        struct Cand {
+        struct Cand<'c> {
             magnitude: f64,
+            // Where the object's catalogs first come in `CATALOG_RANK`
+            catalog_rank: usize,
+            catalog: &'c str,
+            identifier: &'c str,
             is_star: bool,
             text: String,
+            coords: EQPoint,
             p: Point,
         }
         let mut cands: Vec<Cand> = Vec::new();
 
-        for s in context.data.stars {
-            if !self.should_label(&s.kind, s.magnitude) {
+        // Only the stars drawn are labeled, so none merged into a brighter
+        // neighbor gets a label of its own
+        for (s, p) in drawn_stars(context) {
+            if !self.is_labeled(&context.cfg, s) {
                 continue;
             }
-            if let Some(tp) = project(
-                s.coords,
-                context.cfg.center,
-                context.cfg.projection,
-                context.cfg.position_angle_deg,
-            ) {
-                let p = to_pixels(tp, context.layout.center_px, context.layout.scale);
-                let text = if s.name.is_empty() {
-                    format!("{} {}", s.catalog, s.identifier)
-                } else {
-                    s.name.clone()
-                };
-                cands.push(Cand {
-                    magnitude: s.magnitude,
-                    is_star: true,
-                    text,
-                    p,
-                });
+            let text = s.display_label();
+            if text.is_empty() {
+                continue;
             }
+            cands.push(Cand {
+                magnitude: s.magnitude,
+                catalog_rank: catalog_rank(s),
+                catalog: &s.catalog,
+                identifier: &s.identifier,
+                is_star: true,
+                text,
+                coords: s.coords,
+                p,
+            });
         }
         for o in context.data.objects {
-            // Messier object labels always fall through to positioning
-            if o.catalog != "M" && !self.should_label(&o.kind, o.magnitude) {
+            if !self.is_labeled(&context.cfg, o) || !context.in_sky_range(o.coords) {
                 continue;
             }
-            if let Some(tp) = project(
-                o.coords,
-                context.cfg.center,
-                context.cfg.projection,
-                context.cfg.position_angle_deg,
-            ) {
-                let p = to_pixels(tp, context.layout.center_px, context.layout.scale);
-                let text = if o.name.is_empty() {
-                    format!("{} {}", o.catalog, o.identifier)
-                } else {
-                    o.name.clone()
-                };
+            if let Some(p) = context.project_to_pixels(o.coords)
+                && context.fits_plot(p, 0.0)
+            {
+                let mut text = o.display_label();
+                if text.is_empty() {
+                    continue;
+                }
+                if context.cfg.label_alt_catalog
+                    && let Some(alt) = &o.alt_designation
+                {
+                    text = format!("{text} ({alt})");
+                }
                 cands.push(Cand {
                     magnitude: o.magnitude,
+                    catalog_rank: catalog_rank(o),
+                    catalog: &o.catalog,
+                    identifier: &o.identifier,
                     is_star: false,
                     text,
+                    coords: o.coords,
                     p,
                 });
             }
         }
-        cands.sort_by(|a, b| a.magnitude.partial_cmp(&b.magnitude).unwrap());
+        // Candidates ahead in the chosen order go first, whatever their
+        // magnitude
+        let rank = |c: &Cand| match context.cfg.sort_labels_by {
+            LabelSort::Magnitude => 0,
+            LabelSort::Catalog => c.catalog_rank,
+            LabelSort::Type => usize::from(c.is_star),
+        };
+        // Placement is greedy, so order candidates fully (ties broken by
+        // catalog and identifier) to keep the result independent of the
+        // order of the input files
+        cands.sort_by(|a, b| {
+            rank(a)
+                .cmp(&rank(b))
+                .then_with(|| a.magnitude.total_cmp(&b.magnitude))
+                .then_with(|| b.is_star.cmp(&a.is_star))
+                .then_with(|| a.catalog.cmp(b.catalog))
+                .then_with(|| a.identifier.cmp(b.identifier))
+                .then_with(|| a.p.x.total_cmp(&b.p.x))
+                .then_with(|| a.p.y.total_cmp(&b.p.y))
+        });
 
         let l = &context.layout;
         let (left, top) = (l.plot_x, l.plot_y);
         let (right, bottom) = (left + l.plot_w, top + l.plot_h);
 
+        // Labels placed so far in each cell of a coarse grid over the plot,
+        // when they're capped per cell
+        let cells = context.cfg.label_cells.max(1) as usize;
+        let mut per_cell = vec![0; cells * cells];
+        let cell_of = |p: Point| {
+            let index = |offset: f64, extent: f64| {
+                ((offset / extent * cells as f64).floor().max(0.0) as usize).min(cells - 1)
+            };
+            index(p.y - top, l.plot_h) * cells + index(p.x - left, l.plot_w)
+        };
+
         for c in cands {
-            let cls = if c.is_star {
+            let cell = cell_of(c.p);
+            if context
+                .cfg
+                .labels_per_cell
+                .is_some_and(|cap| per_cell[cell] >= cap)
+            {
+                continue;
+            }
+            let mut cls = if c.is_star {
                 "star-label"
             } else {
                 "object-label"
-            };
+            }
+            .to_string();
+            if context.cfg.label_halo {
+                cls += " halo";
+            }
             for (dx, dy) in self.offsets {
                 let ax = c.p.x + dx;
                 let ay = c.p.y + dy;
 
-                let (bx, by, bw, bh) = self.label_box_centered(ax, ay, &c.text);
+                let (bx, by, bw, bh) = self.label_box_centered(&context.cfg, ax, ay, &c.text);
                 if bx < left || bx + bw > right || by < top || by + bh > bottom {
                     continue;
                 }
-                if placed
+                let corners = [(bx, by), (bx + bw, by), (bx, by + bh), (bx + bw, by + bh)];
+                if !corners
                     .iter()
-                    .any(|&b| Self::boxes_overlap((bx, by, bw, bh), b))
+                    .all(|&(x, y)| context.fits_plot(Point { x, y }, 0.0))
                 {
                     continue;
                 }
+                if placed.collides((bx, by, bw, bh)) {
+                    continue;
+                }
 
-                placed.push((bx, by, bw, bh));
-                g = g.add(text(cls, ax, by + bh, "middle", &c.text));
+                placed.insert((bx, by, bw, bh));
+                per_cell[cell] += 1;
+                let angle = if context.cfg.align_labels_to_grid {
+                    north_rotation(context, c.coords, c.p)
+                        .filter(|&angle| context.fmt_coord(angle) != 0.0)
+                } else {
+                    None
+                };
+                labels.push(PlacedLabel {
+                    class: cls,
+                    text: c.text,
+                    rect: (bx, by, bw, bh),
+                    angle,
+                });
                 break;
             }
         }
+        labels
+    }
+}
+
+// A label that found room, centered in its box
+struct PlacedLabel {
+    class: String,
+    text: String,
+    rect: Rect,
+    // Clockwise turn in degrees, about the middle of the box
+    angle: Option<f64>,
+}
+
+impl Layer for LabelsLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-labels", "labels");
+        let obstacles = self.obstacles(context);
+        let placed = self.place(context, &obstacles);
+        for label in &placed {
+            let (x, y, w, h) = label.rect;
+            let (ax, ay) = (x + w / 2.0, y + h);
+            let mut el = text(context, &label.class, ax, ay, "middle", &label.text);
+            // Inline, as the stylesheet's rules would win over an attribute
+            if let Some(px) = context.cfg.label_font_size {
+                el = el.set("style", format!("font-size: {}px", context.fmt_coord(px)));
+            }
+            // Turned about the middle of its box, so it stays about where
+            // it was placed
+            if let Some(angle) = label.angle {
+                el = el.set(
+                    "transform",
+                    format!(
+                        "rotate({} {} {})",
+                        context.fmt_coord(angle),
+                        context.fmt_coord(ax),
+                        context.fmt_coord(y + h / 2.0)
+                    ),
+                );
+            }
+            g = g.add(el);
+        }
+
+        if context.cfg.label_collision_debug {
+            let boxes = obstacles
+                .iter()
+                .map(|&r| (r, "debug-box obstacle"))
+                .chain(placed.iter().map(|l| (l.rect, "debug-box label")));
+            for ((x, y, w, h), class) in boxes {
+                g = g.add(
+                    Rectangle::new()
+                        .set("class", class)
+                        .set("x", context.fmt_coord(x))
+                        .set("y", context.fmt_coord(y))
+                        .set("width", context.fmt_coord(w))
+                        .set("height", context.fmt_coord(h)),
+                );
+            }
+        }
 
         g
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Datasets;
+    use crate::test_utils::{
+        attr_values, make_context, make_context_with, make_object, make_objects, make_star,
+        objects_context,
+    };
+    use crate::types::{Constellation, EQPoint};
+
+    fn render_with(objects: &[CelestialObject], patch: impl FnOnce(&mut ChartConfig)) -> String {
+        LabelsLayer::new()
+            .render(&objects_context(objects, patch))
+            .to_string()
+    }
+
+    fn render(objects: &[CelestialObject]) -> String {
+        render_with(objects, |_| {})
+    }
+
+    #[test]
+    fn collision_debug_outlines_every_obstacle_and_label() {
+        let objects = make_objects((0..4).map(|i| (0.05 * i as f64, 0.02 * i as f64)));
+        assert!(!render(&objects).contains("debug-box"));
+
+        let patch = |cfg: &mut ChartConfig| cfg.label_collision_debug = true;
+        let doc = render_with(&objects, patch);
+        let context = objects_context(&objects, patch);
+        let layer = LabelsLayer::new();
+        let obstacles =
+            layer.seed_symbol_boxes(&context).len() + layer.seed_label_boxes(&context).len();
+        let labels = doc.matches("<text").count();
+        assert!(labels > 0);
+        assert_eq!(doc.matches("debug-box obstacle").count(), obstacles);
+        assert_eq!(doc.matches("debug-box label").count(), labels);
+        assert_eq!(doc.matches("<rect").count(), obstacles + labels);
+    }
+
+    #[test]
+    fn alternate_designations_follow_in_parentheses() {
+        let mut m42 = make_object("bright-nebula", 0.0, 0.0, 7.0, 2.0);
+        m42.catalog = "M".to_string();
+        m42.identifier = "42".to_string();
+        m42.alt_designation = Some("NGC 1976".to_string());
+        let objects = vec![m42];
+
+        let plain = render(&objects);
+        assert!(plain.contains("M 42") && !plain.contains("NGC 1976"));
+        let both = render_with(&objects, |cfg| cfg.label_alt_catalog = true);
+        assert!(both.contains("M 42 (NGC 1976)"));
+
+        // OpenNGC's zero-padded "NGC0224" is shown without the padding
+        let objects = crate::data::load_objects(
+            None,
+            crate::data::ObjectOrder::Catalog,
+            &crate::data::default_catalog_priority(),
+        )
+        .unwrap();
+        let mut m31 = crate::data::find_object(&objects, "M31").unwrap().clone();
+        m31.coords = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 0.0,
+        };
+        let doc = render_with(&[m31], |cfg| cfg.label_alt_catalog = true);
+        assert!(doc.contains("M 31 (NGC 224)"), "{doc}");
+    }
+
+    #[test]
+    fn messier_objects_known_by_ngc_number_are_still_labeled() {
+        // As loaded with --catalog-priority NGC,M,IC
+        // Fainter than objects are labeled, but within the chart's limit
+        let mut m13 = make_object("globular-cluster", 0.0, 0.0, 9.0, 2.0);
+        m13.identifier = "6205".to_string();
+        m13.alt_designation = Some("M 13".to_string());
+        let objects = vec![m13];
+
+        assert!(render(&objects).contains("NGC 6205"));
+    }
+
+    #[test]
+    fn messier_objects_past_the_surface_brightness_limit_keep_their_labels() {
+        // M 33 spreads its light too thin for the limit, but is still named
+        let mut m33 = make_object("galaxy", 0.0, 0.0, 6.0, 60.0);
+        m33.catalog = "M".to_string();
+        m33.identifier = "33".to_string();
+        assert!(m33.surface_brightness().unwrap() > 13.0);
+        let objects = vec![m33];
+
+        let doc = render_with(&objects, |cfg| cfg.limit_surface_brightness = Some(13.0));
+        assert!(doc.contains("M 33"));
+    }
+
+    #[test]
+    fn label_only_catalogs_leaves_other_objects_unlabeled_but_drawn() {
+        let mut m13 = make_object("globular-cluster", 0.0, 0.0, 6.0, 2.0);
+        m13.catalog = "M".to_string();
+        m13.identifier = "13".to_string();
+        let ngc = make_object("galaxy", 2.0, 1.0, 7.0, 2.0);
+        let objects = vec![m13, ngc];
+        let patch = |cfg: &mut ChartConfig| cfg.label_only_catalogs = vec!["m".to_string()];
+
+        let labels = render_with(&objects, patch);
+        assert!(labels.contains("M 13"));
+        assert!(!labels.contains("NGC 1"));
+        assert!(render(&objects).contains("NGC 1"));
+
+        let symbols = crate::layers::ObjectsLayer::new()
+            .render(&objects_context(&objects, patch))
+            .to_string();
+        assert_eq!(symbols.matches("class=\"galaxy object\"").count(), 1);
+    }
+
+    #[test]
+    fn only_labeled_leaves_out_objects_of_unlabeled_catalogs() {
+        let mut m13 = make_object("globular-cluster", 0.0, 0.0, 6.0, 2.0);
+        m13.catalog = "M".to_string();
+        m13.identifier = "13".to_string();
+        let ngc = make_object("galaxy", 2.0, 1.0, 7.0, 2.0);
+        let objects = vec![m13, ngc];
+        let context = objects_context(&objects, |cfg| {
+            cfg.label_only_catalogs = vec!["m".to_string()];
+            cfg.only_labeled = true;
+        });
+
+        let symbols = crate::layers::ObjectsLayer::new()
+            .render(&context)
+            .to_string();
+        assert!(symbols.contains("id=\"13\"") && !symbols.contains("id=\"1\""));
+        let plotted = crate::layers::objects::plotted_objects(&context);
+        assert_eq!(plotted.len(), 1);
+        assert_eq!(plotted[0].0.identifier, "13");
+    }
+
+    #[test]
+    fn placement_does_not_depend_on_input_order() {
+        // Equally bright objects close enough that their labels compete
+        let mut objects = make_objects((0..6).map(|i| (0.05 * i as f64, 0.02 * i as f64)));
+        let expected = render(&objects);
+        assert!(expected.contains("NGC 100"));
+
+        objects.reverse();
+        assert_eq!(render(&objects), expected);
+        objects.rotate_left(2);
+        assert_eq!(render(&objects), expected);
+    }
+
+    #[test]
+    fn wider_characters_widen_the_box_and_move_labels() {
+        let layer = LabelsLayer::new();
+        let mut cfg = ChartConfig::default();
+        let (_, _, narrow, _) = layer.label_box_centered(&cfg, 0.0, 0.0, "NGC 1234");
+        cfg.label_char_width = 10.0;
+        let (_, _, wide, _) = layer.label_box_centered(&cfg, 0.0, 0.0, "NGC 1234");
+        assert!(wide > narrow);
+
+        // Side by side, so only wide labels run into each other
+        let objects = make_objects((0..2).map(|i| (4.0 * i as f64, 0.0)));
+        let narrow = render_with(&objects, |cfg| cfg.label_char_width = 5.0);
+        let wide = render_with(&objects, |cfg| cfg.label_char_width = 12.0);
+        assert_eq!(narrow.matches("<text").count(), 2);
+        assert_ne!(narrow, wide);
+    }
+
+    // The baseline of the one label drawn for a star at this pixel
+    fn star_label_y(
+        at: Point,
+        constellations: &[Constellation],
+        patch: impl Fn(&mut ChartConfig),
+    ) -> f64 {
+        let star_at = make_context(&patch).pixel_to_eq(at).unwrap();
+        let stars = [make_star(star_at.ra_deg, star_at.dec_deg, 0.0)];
+        let data = Datasets {
+            stars: &stars,
+            objects: &[],
+            constellations,
+        };
+        let doc = LabelsLayer::new()
+            .render(&make_context_with(data, patch))
+            .to_string();
+        let ys = attr_values(&doc, "y");
+        assert_eq!(ys.len(), 1, "{doc}");
+        ys[0]
+    }
+
+    #[test]
+    fn star_labels_move_off_tick_labels() {
+        // Tall enough boxes that a bottom tick label reaches into the plot
+        let patch = |cfg: &mut ChartConfig| cfg.label_line_height = 40.0;
+        let context = make_context(patch);
+        let bottom = context.layout.plot_y + context.layout.plot_h;
+        let tick = FrameLayer::new()
+            .tick_labels(&context)
+            .into_iter()
+            .find(|t| t.y > bottom)
+            .unwrap();
+        let at = Point {
+            x: tick.x,
+            y: bottom - 5.0,
+        };
+
+        // The first spot, just above the star, would overlap the tick label
+        let y = star_label_y(at, &[], patch);
+        assert_ne!(y, at.y - 10.0);
+        assert!(y <= bottom + 20.0 - 40.0, "{y}");
+    }
+
+    #[test]
+    fn star_labels_move_off_constellation_names() {
+        let figure = Constellation {
+            abbr: "Tst".to_string(),
+            name: "Test".to_string(),
+            lines: vec![vec![
+                EQPoint {
+                    ra_deg: 10.0,
+                    dec_deg: 0.0,
+                },
+                EQPoint {
+                    ra_deg: 350.0,
+                    dec_deg: 0.0,
+                },
+            ]],
+        };
+        let context = make_context(|_| {});
+        let name = label_positions(&context, &figure)[0];
+        // Just below the name, so a label above the star would cover it
+        let at = Point {
+            x: name.x,
+            y: name.y + 14.0,
+        };
+        let alone = star_label_y(at, &[], |_| {});
+        let y = star_label_y(at, std::slice::from_ref(&figure), |_| {});
+        assert_eq!(alone, at.y - 10.0);
+        assert_ne!(y, alone);
+        // The placed box is clear of the name's
+        let top = y - 12.0;
+        assert!(y <= name.y - 6.0 || top >= name.y + 6.0, "{y}");
+    }
+
+    // The rotation of each label, from its transform, or 0 without one
+    fn label_rotations(doc: &str) -> Vec<f64> {
+        doc.split("<text")
+            .skip(1)
+            .map(|t| {
+                t.split_once("rotate(")
+                    .and_then(|(_, rest)| rest.split(' ').next())
+                    .map_or(0.0, |angle| angle.parse().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aligned_labels_tilt_toward_the_pole() {
+        let mut star = make_star(40.0, 80.0, 0.0);
+        star.name = "Near Pole".to_string();
+        let stars = [star];
+        let render = |align: bool| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| {
+                cfg.center = EQPoint {
+                    ra_deg: 0.0,
+                    dec_deg: 60.0,
+                };
+                cfg.align_labels_to_grid = align;
+            });
+            LabelsLayer::new().render(&context).to_string()
+        };
+
+        assert_eq!(label_rotations(&render(false)), vec![0.0]);
+        let rotations = label_rotations(&render(true));
+        assert_eq!(rotations.len(), 1);
+        // East of the center meridian, so left of it, north leans right
+        assert!(rotations[0] > 5.0 && rotations[0] < 90.0, "{rotations:?}");
+    }
+
+    #[test]
+    fn aligned_labels_stay_upright_on_the_center_meridian() {
+        let context = make_context(|cfg| cfg.align_labels_to_grid = true);
+        let coords = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 10.0,
+        };
+        let p = context.project_to_pixels(coords).unwrap();
+        let angle = north_rotation(&context, coords, p).unwrap();
+        assert!(angle.abs() < 1e-6, "{angle}");
+        // Turned upside down, the chart still reads the right way up
+        let context = make_context(|cfg| cfg.position_angle_deg = 180.0);
+        let p = context.project_to_pixels(coords).unwrap();
+        let angle = north_rotation(&context, coords, p).unwrap();
+        assert!(angle.abs() < 1e-6, "{angle}");
+    }
+
+    #[test]
+    fn label_font_size_sets_the_drawn_size() {
+        let objects = vec![make_object("galaxy", 0.0, 0.0, 7.0, 2.0)];
+        assert!(!render(&objects).contains("font-size"));
+
+        let doc = render_with(&objects, |cfg| cfg.label_font_size = Some(14.0));
+        assert!(doc.contains("style=\"font-size: 14px\""), "{doc}");
+    }
+
+    #[test]
+    fn halo_class_marks_every_label() {
+        let objects = make_objects((0..3).map(|i| (4.0 * i as f64, 0.0)));
+        let plain = render(&objects);
+        assert!(!plain.contains("halo"));
+
+        let doc = render_with(&objects, |cfg| cfg.label_halo = true);
+        assert_eq!(doc.matches("<text").count(), 3);
+        assert_eq!(doc.matches("class=\"object-label halo\"").count(), 3);
+    }
+
+    #[test]
+    fn labels_per_cell_thins_clusters_only() {
+        // Four objects in one cell of a 4x4 grid, with room for every label,
+        // and one more far away
+        let objects = make_objects([
+            (349.0, -5.0),
+            (355.0, -5.0),
+            (349.0, -11.0),
+            (355.0, -11.0),
+            (20.0, 20.0),
+        ]);
+        let labels = |cap: Option<usize>| {
+            render_with(&objects, |cfg| {
+                cfg.label_cells = 4;
+                cfg.labels_per_cell = cap;
+            })
+            .matches("<text")
+            .count()
+        };
+        assert_eq!(labels(None), 5);
+        assert_eq!(labels(Some(2)), 3);
+        let doc = render_with(&objects, |cfg| cfg.labels_per_cell = Some(1));
+        assert!(doc.contains("NGC 104"));
+    }
+
+    #[test]
+    fn catalog_sort_labels_messier_objects_ahead_of_brighter_stars() {
+        // All in one cell of the label grid, clear of the center where four
+        // cells meet
+        let stars = vec![make_star(5.2, 5.1, 0.5), make_star(4.8, 4.9, 0.8)];
+        let mut m13 = make_object("globular-cluster", 5.0, 5.0, 9.5, 2.0);
+        m13.catalog = "M".to_string();
+        m13.identifier = "13".to_string();
+        let objects = vec![m13];
+        let render = |sort| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &objects,
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| {
+                cfg.labels_per_cell = Some(1);
+                cfg.sort_labels_by = sort;
+            });
+            LabelsLayer::new().render(&context).to_string()
+        };
+
+        let by_magnitude = render(LabelSort::Magnitude);
+        assert!(by_magnitude.contains("HIP 1") && !by_magnitude.contains("M 13"));
+        for sort in [LabelSort::Catalog, LabelSort::Type] {
+            let doc = render(sort);
+            assert!(doc.contains("M 13") && !doc.contains("HIP 1"), "{sort:?}");
+        }
+    }
+
+    #[test]
+    fn messier_objects_known_by_ngc_number_keep_their_catalog_rank() {
+        // As loaded with --catalog-priority NGC,M,IC, beside a brighter NGC
+        // object in the same cell of the label grid
+        let mut m13 = make_object("globular-cluster", 5.0, 5.0, 7.5, 2.0);
+        m13.identifier = "6205".to_string();
+        m13.alt_designation = Some("M 13".to_string());
+        let ngc = make_object("galaxy", 5.2, 5.1, 7.0, 2.0);
+        let objects = vec![m13, ngc];
+
+        let sorted = render_with(&objects, |cfg| {
+            cfg.labels_per_cell = Some(1);
+            cfg.sort_labels_by = LabelSort::Catalog;
+        });
+        assert!(sorted.contains("NGC 6205") && !sorted.contains("NGC 1<"));
+
+        let only_m = render_with(&objects, |cfg| {
+            cfg.label_only_catalogs = vec!["m".to_string()]
+        });
+        assert!(only_m.contains("NGC 6205") && !only_m.contains("NGC 1<"));
+    }
+
+    #[test]
+    fn stars_merged_into_a_brighter_one_are_not_labeled() {
+        // Two equally bright stars at one spot, of which the first listed is
+        // kept. It has nothing to label it with.
+        let mut primary = make_star(5.0, 5.0, 0.5);
+        primary.catalog.clear();
+        primary.identifier.clear();
+        let mut companion = make_star(5.0, 5.0, 0.5);
+        companion.name = "Companion".to_string();
+        let stars = [primary, companion];
+        let render = |merge: bool| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| cfg.merge_coincident_stars = merge);
+            LabelsLayer::new().render(&context).to_string()
+        };
+
+        assert!(render(false).contains("Companion"));
+        assert!(!render(true).contains("Companion"));
+    }
+}
@@ -13,28 +13,56 @@ pub fn group_with_class(class: &str) -> Group {
     g
 }
 
-pub fn text(class: &str, x: f64, y: f64, anchor: &str, content: &str) -> TextEl {
+/// A layer's top-level group, with an id so a specific layer can be
+/// targeted when several charts share a page
+pub fn group_with_id_class(id: &str, class: &str) -> Group {
+    group_with_class(class).set("id", id)
+}
+
+pub fn text(
+    context: &ChartContext<'_>,
+    class: &str,
+    x: f64,
+    y: f64,
+    anchor: &str,
+    content: &str,
+) -> TextEl {
     TextEl::new(content)
         .set("class", class)
-        .set("x", x)
-        .set("y", y)
+        .set("x", context.fmt_coord(x))
+        .set("y", context.fmt_coord(y))
         .set("text-anchor", anchor)
 }
 
+pub mod arcs;
 pub mod constellations;
+pub mod corners;
+pub mod crosshairs;
+pub mod density;
 pub mod ecliptic;
 pub mod frame;
 pub mod grid;
 pub mod labels;
 pub mod objects;
+pub mod obstacles;
+pub mod proper_motion;
+pub mod regions;
 pub mod stars;
+pub mod twilight;
 pub mod zenith;
 
+pub use arcs::ArcsLayer;
 pub use constellations::ConstellationsLayer;
+pub use corners::CornerReadoutLayer;
+pub use crosshairs::CrosshairLayer;
+pub use density::DensityLayer;
 pub use ecliptic::EclipticLayer;
 pub use frame::FrameLayer;
 pub use grid::GridLayer;
 pub use labels::LabelsLayer;
 pub use objects::ObjectsLayer;
+pub use proper_motion::ProperMotionLayer;
+pub use regions::RegionsLayer;
 pub use stars::StarsLayer;
+pub use twilight::TwilightLayer;
 pub use zenith::ZenithLayer;
@@ -1,9 +1,23 @@
 use svg::node::element::Group as G;
 use svg::node::element::{Circle, Ellipse, Group, Line, Rectangle};
 
+use crate::config::ChartConfig;
 use crate::context::ChartContext;
-use crate::geometry::{project, to_pixels};
-use crate::layers::{group_with_class, Layer};
+use crate::data::objects::sort_faintest_first;
+use crate::layers::{group_with_id_class, LabelsLayer, Layer};
+use crate::types::{CelestialObject, Point, Size};
+
+// Smallest symbol size in pixels (before object_scale), so objects with
+// missing size data and a faint magnitude stay visible
+const MIN_SYMBOL_SIZE: f64 = 6.0;
+
+// Smallest symbol size for a galaxy with a catalog size, so small ones are
+// still drawn small
+const SIZED_GALAXY_MIN_SIZE: f64 = 2.0;
+
+// Galaxies this flattened or more, minor over major axis, get an axis line
+// with `galaxy_axis_lines`
+const ELONGATED_AXIS_RATIO: f64 = 0.4;
 
 fn r_mag(mag: f64, r_min: f64, r_max: f64, mag_bright: f64, mag_faint: f64) -> f64 {
     let m = mag.clamp(mag_bright, mag_faint);
@@ -28,6 +42,82 @@ fn radius(mag: f64, arcmin: Option<f64>, w_mag: f64, w_size: f64, floor: f64) ->
     (w_mag * by_mag + w_size * by_size).max(floor)
 }
 
+// Symbol size in pixels for an object, scaled by `object_scale`
+pub(crate) fn symbol_size(cfg: &ChartConfig, o: &CelestialObject) -> f64 {
+    let (w_mag, w_size) = (cfg.object_weight_mag, cfg.object_weight_size);
+    let sizeless = o.size.major <= 0.0;
+    let size = match o.kind.as_str() {
+        "galaxy" if !sizeless => radius(
+            o.magnitude,
+            Some(o.size.major),
+            w_mag,
+            w_size,
+            SIZED_GALAXY_MIN_SIZE,
+        ),
+        "open-cluster" | "globular-cluster" | "bright-nebula" | "galaxy" | "planetary-nebula"
+        | "milky-way" => radius(
+            o.magnitude,
+            Some(o.size.major),
+            w_mag,
+            w_size,
+            MIN_SYMBOL_SIZE,
+        ),
+        _ if sizeless => r_mag(o.magnitude, 4.0, 18.0, -1.0, 10.0).max(MIN_SYMBOL_SIZE),
+        _ => r_mag(o.magnitude, 4.0, 18.0, -1.0, 10.0),
+    };
+    size * cfg.object_scale
+}
+
+// How far a symbol of the given size reaches from its center
+pub(crate) fn symbol_extent(kind: &str, size: f64) -> f64 {
+    // Galaxy ellipses reach furthest from the center
+    let reach = if kind == "galaxy" { 0.7 } else { 0.5 };
+    reach * size
+}
+
+// Minor over major axis for an ellipse symbol. Catalogs without a minor axis
+// get a generic 2:1 rather than a flat line.
+fn ellipse_ratio(size: Size) -> f64 {
+    if size.minor > 0.0 {
+        size.aspect_ratio()
+    } else {
+        0.5
+    }
+}
+
+// Where an object is drawn, or None if it isn't: it has to be within the
+// limits and sky range, labeled with `only_labeled`, and have its symbol fit
+// the plot
+fn plot_position(
+    context: &ChartContext<'_>,
+    labels: &LabelsLayer,
+    o: &CelestialObject,
+) -> Option<Point> {
+    if !context.object_within_limits(o)
+        || !context.in_sky_range(o.coords)
+        || (context.cfg.only_labeled && !labels.is_labeled(&context.cfg, o))
+    {
+        return None;
+    }
+    let p = context.project_to_pixels(o.coords)?;
+    let extent = symbol_extent(&o.kind, symbol_size(&context.cfg, o));
+    context.fits_plot(p, extent).then_some(p)
+}
+
+/// The deep-sky objects the chart draws and where they land, in catalog
+/// order. Shared with the autocrop bounds, so they see the same set.
+pub(crate) fn plotted_objects<'a>(context: &ChartContext<'a>) -> Vec<(&'a CelestialObject, Point)> {
+    let labels = LabelsLayer::new();
+    context
+        .data
+        .objects
+        .iter()
+        .filter_map(|o| Some((o, plot_position(context, &labels, o)?)))
+        .collect()
+}
+
+/// Draws the deep-sky objects faintest first, so the brightest end up on
+/// top, whatever order the objects are listed in
 pub struct ObjectsLayer;
 impl ObjectsLayer {
     pub fn new() -> Self {
@@ -37,138 +127,213 @@ impl ObjectsLayer {
 
 impl Layer for ObjectsLayer {
     fn render(&self, context: &ChartContext<'_>) -> Group {
-        let mut g = group_with_class("objects");
-        let scale = context.cfg.object_scale;
+        let mut g = group_with_id_class("layer-objects", "objects");
+        let labels = LabelsLayer::new();
+
+        let mut objects: Vec<&CelestialObject> = context.data.objects.iter().collect();
+        sort_faintest_first(&mut objects);
 
-        for o in context.data.objects {
-            if o.magnitude > context.cfg.limit_object_mag {
+        for o in objects {
+            let Some(p) = plot_position(context, &labels, o) else {
                 continue;
-            }
+            };
+            let id = &o.identifier;
+            let kind = o.kind.as_str();
+            let faint = context
+                .cfg
+                .faint_object_mag
+                .is_some_and(|limit| o.magnitude > limit);
+            let messier = context.cfg.emphasize_messier && o.designation_in("M").is_some();
+            let class = |base: &str| {
+                let mut class = base.to_string();
+                if faint {
+                    class += " faint";
+                }
+                if messier {
+                    class += " messier";
+                }
+                class
+            };
 
-            if let Some(tp) = project(
-                o.coords,
-                context.cfg.center,
-                context.cfg.projection,
-                context.cfg.position_angle_deg,
-            ) {
-                let p = to_pixels(tp, context.layout.center_px, context.layout.scale);
-                let id = &o.identifier;
-                let kind = o.kind.as_str();
-
-                match kind {
-                    "open-cluster" => {
-                        let size = radius(o.magnitude, Some(o.size.major), 1.0, 0.3, 6.0) * scale;
-                        let r = size * 0.5;
-                        g = g.add(
-                            Circle::new()
-                                .set("id", id.as_str())
-                                .set("class", "open-cluster object")
-                                .set("cx", p.x)
-                                .set("cy", p.y)
-                                .set("r", r),
-                        );
-                    }
-                    "globular-cluster" => {
-                        let size = radius(o.magnitude, Some(o.size.major), 1.0, 0.3, 6.0) * scale;
-                        let r = size * 0.5;
-                        let mut gg = G::new()
+            let size = symbol_size(&context.cfg, o);
+
+            match kind {
+                "open-cluster" => {
+                    let r = size * 0.5;
+                    g = g.add(
+                        Circle::new()
                             .set("id", id.as_str())
-                            .set("class", "globular-cluster object");
-                        gg = gg.add(Circle::new().set("cx", p.x).set("cy", p.y).set("r", r));
-                        gg = gg.add(
-                            Line::new()
-                                .set("x1", p.x - r)
-                                .set("y1", p.y)
-                                .set("x2", p.x + r)
-                                .set("y2", p.y),
-                        );
-                        gg = gg.add(
-                            Line::new()
-                                .set("x1", p.x)
-                                .set("y1", p.y - r)
-                                .set("x2", p.x)
-                                .set("y2", p.y + r),
-                        );
-                        g = g.add(gg);
-                    }
-                    "bright-nebula" => {
-                        let size = radius(o.magnitude, Some(o.size.major), 1.0, 0.3, 6.0) * scale;
-                        let half = size * 0.5;
-                        g = g.add(
-                            Rectangle::new()
-                                .set("id", id.as_str())
-                                .set("class", "bright-nebula object")
-                                .set("x", p.x - half)
-                                .set("y", p.y - half)
-                                .set("width", 2.0 * half)
-                                .set("height", 2.0 * half),
-                        );
-                    }
-                    "galaxy" => {
-                        // let size = r_mag(o.magnitude, 4.0, 18.0, -1.0, 10.0) * scale;
-                        let size = radius(o.magnitude, Some(o.size.major), 1.0, 0.3, 2.0) * scale;
-                        let rx = size * 0.7;
-                        let ry = size * 0.35;
-                        let gg = G::new()
+                            .set("class", class("open-cluster object"))
+                            .set("cx", context.fmt_coord(p.x))
+                            .set("cy", context.fmt_coord(p.y))
+                            .set("r", context.fmt_coord(r)),
+                    );
+                }
+                "globular-cluster" => {
+                    let r = size * 0.5;
+                    let mut gg = G::new()
+                        .set("id", id.as_str())
+                        .set("class", class("globular-cluster object"));
+                    gg = gg.add(
+                        Circle::new()
+                            .set("cx", context.fmt_coord(p.x))
+                            .set("cy", context.fmt_coord(p.y))
+                            .set("r", context.fmt_coord(r)),
+                    );
+                    gg = gg.add(
+                        Line::new()
+                            .set("x1", context.fmt_coord(p.x - r))
+                            .set("y1", context.fmt_coord(p.y))
+                            .set("x2", context.fmt_coord(p.x + r))
+                            .set("y2", context.fmt_coord(p.y)),
+                    );
+                    gg = gg.add(
+                        Line::new()
+                            .set("x1", context.fmt_coord(p.x))
+                            .set("y1", context.fmt_coord(p.y - r))
+                            .set("x2", context.fmt_coord(p.x))
+                            .set("y2", context.fmt_coord(p.y + r)),
+                    );
+                    g = g.add(gg);
+                }
+                "bright-nebula" => {
+                    let half = size * 0.5;
+                    g = g.add(
+                        Rectangle::new()
                             .set("id", id.as_str())
-                            .set("class", "galaxy object")
+                            .set("class", class("bright-nebula object"))
+                            .set("x", context.fmt_coord(p.x - half))
+                            .set("y", context.fmt_coord(p.y - half))
+                            .set("width", context.fmt_coord(2.0 * half))
+                            .set("height", context.fmt_coord(2.0 * half)),
+                    );
+                }
+                "galaxy" => {
+                    let gg = G::new()
+                        .set("id", id.as_str())
+                        .set("class", class("galaxy object"));
+                    // Without either axis there's no shape or orientation
+                    // to draw, so fall back to a circle
+                    let gg = if o.size.major <= 0.0 && o.size.minor <= 0.0 {
+                        gg.add(
+                            Circle::new()
+                                .set("cx", context.fmt_coord(p.x))
+                                .set("cy", context.fmt_coord(p.y))
+                                .set("r", context.fmt_coord(size * 0.5)),
+                        )
+                    } else {
+                        let ratio = ellipse_ratio(o.size);
+                        let rx = size * 0.7;
+                        let gg = gg
                             .set(
                                 "transform",
-                                format!("rotate({:.2},{:.2},{:.2})", o.angle, p.x, p.y),
+                                format!(
+                                    "rotate({:.2},{},{})",
+                                    o.angle,
+                                    context.fmt_coord(p.x),
+                                    context.fmt_coord(p.y)
+                                ),
                             )
                             .add(
                                 Ellipse::new()
-                                    .set("cx", p.x)
-                                    .set("cy", p.y)
-                                    .set("rx", rx)
-                                    .set("ry", ry),
+                                    .set("cx", context.fmt_coord(p.x))
+                                    .set("cy", context.fmt_coord(p.y))
+                                    .set("rx", context.fmt_coord(rx))
+                                    .set("ry", context.fmt_coord(rx * ratio)),
                             );
-                        g = g.add(gg);
-                    }
-                    "planetary-nebula" => {
-                        let size = radius(o.magnitude, Some(o.size.major), 1.0, 0.3, 6.0) * scale;
-                        let r = size / 4.0;
-                        let cross = size / 2.0;
-                        let mut gg = G::new()
+                        // Only a measured minor axis says the galaxy is
+                        // edge-on; the generic 2:1 doesn't
+                        if context.cfg.galaxy_axis_lines
+                            && o.size.minor > 0.0
+                            && ratio <= ELONGATED_AXIS_RATIO
+                        {
+                            // Along x, so the group's rotation turns it
+                            // with the ellipse, and a little past its ends
+                            let half = rx * 1.2;
+                            gg.add(
+                                Line::new()
+                                    .set("class", "galaxy-axis")
+                                    .set("x1", context.fmt_coord(p.x - half))
+                                    .set("y1", context.fmt_coord(p.y))
+                                    .set("x2", context.fmt_coord(p.x + half))
+                                    .set("y2", context.fmt_coord(p.y)),
+                            )
+                        } else {
+                            gg
+                        }
+                    };
+                    g = g.add(gg);
+                }
+                "planetary-nebula" => {
+                    let r = size / 4.0;
+                    let cross = size / 2.0;
+                    let mut gg = G::new()
+                        .set("id", id.as_str())
+                        .set("class", class("planetary-nebula object"));
+                    gg = gg.add(
+                        Circle::new()
+                            .set("cx", context.fmt_coord(p.x))
+                            .set("cy", context.fmt_coord(p.y))
+                            .set("r", context.fmt_coord(r)),
+                    );
+                    gg = gg.add(
+                        Line::new()
+                            .set("x1", context.fmt_coord(p.x - cross))
+                            .set("y1", context.fmt_coord(p.y))
+                            .set("x2", context.fmt_coord(p.x + cross))
+                            .set("y2", context.fmt_coord(p.y)),
+                    );
+                    gg = gg.add(
+                        Line::new()
+                            .set("x1", context.fmt_coord(p.x))
+                            .set("y1", context.fmt_coord(p.y - cross))
+                            .set("x2", context.fmt_coord(p.x))
+                            .set("y2", context.fmt_coord(p.y + cross)),
+                    );
+                    g = g.add(gg);
+                }
+                "milky-way" => {
+                    // A shaded patch of sky rather than a point symbol
+                    let rx = size * 0.5;
+                    g = g.add(
+                        Ellipse::new()
                             .set("id", id.as_str())
-                            .set("class", "planetary-nebula object");
-                        gg = gg.add(Circle::new().set("cx", p.x).set("cy", p.y).set("r", r));
-                        gg = gg.add(
-                            Line::new()
-                                .set("x1", p.x - cross)
-                                .set("y1", p.y)
-                                .set("x2", p.x + cross)
-                                .set("y2", p.y),
-                        );
-                        gg = gg.add(
-                            Line::new()
-                                .set("x1", p.x)
-                                .set("y1", p.y - cross)
-                                .set("x2", p.x)
-                                .set("y2", p.y + cross),
-                        );
-                        g = g.add(gg);
-                    }
-                    _ => {
-                        let size = r_mag(o.magnitude, 4.0, 18.0, -1.0, 10.0) * scale;
-                        let half = size * 0.5;
-                        let mut gg = G::new().set("id", id.as_str()).set("class", "object");
-                        gg = gg.add(
-                            Line::new()
-                                .set("x1", p.x - half)
-                                .set("y1", p.y)
-                                .set("x2", p.x + half)
-                                .set("y2", p.y),
-                        );
-                        gg = gg.add(
-                            Line::new()
-                                .set("x1", p.x)
-                                .set("y1", p.y - half)
-                                .set("x2", p.x)
-                                .set("y2", p.y + half),
-                        );
-                        g = g.add(gg);
-                    }
+                            .set("class", class("milky-way object"))
+                            .set(
+                                "transform",
+                                format!(
+                                    "rotate({:.2},{},{})",
+                                    o.angle,
+                                    context.fmt_coord(p.x),
+                                    context.fmt_coord(p.y)
+                                ),
+                            )
+                            .set("cx", context.fmt_coord(p.x))
+                            .set("cy", context.fmt_coord(p.y))
+                            .set("rx", context.fmt_coord(rx))
+                            .set("ry", context.fmt_coord(rx * ellipse_ratio(o.size))),
+                    );
+                }
+                _ => {
+                    let half = size * 0.5;
+                    let mut gg = G::new()
+                        .set("id", id.as_str())
+                        .set("class", class("object"));
+                    gg = gg.add(
+                        Line::new()
+                            .set("x1", context.fmt_coord(p.x - half))
+                            .set("y1", context.fmt_coord(p.y))
+                            .set("x2", context.fmt_coord(p.x + half))
+                            .set("y2", context.fmt_coord(p.y)),
+                    );
+                    gg = gg.add(
+                        Line::new()
+                            .set("x1", context.fmt_coord(p.x))
+                            .set("y1", context.fmt_coord(p.y - half))
+                            .set("x2", context.fmt_coord(p.x))
+                            .set("y2", context.fmt_coord(p.y + half)),
+                    );
+                    g = g.add(gg);
                 }
             }
         }
@@ -176,3 +341,262 @@ impl Layer for ObjectsLayer {
         g
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{approx, attr_values, make_object, make_objects, objects_context};
+
+    #[test]
+    fn objects_past_the_faint_limit_carry_the_faint_class() {
+        let objects = vec![
+            make_object("galaxy", 0.0, 0.0, 9.5, 5.0),
+            make_object("galaxy", 1.0, 0.0, 6.0, 5.0),
+        ];
+        let context = objects_context(&objects, |cfg| cfg.faint_object_mag = Some(8.0));
+        let doc = ObjectsLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("class=\"galaxy object faint\"").count(), 1);
+        assert_eq!(doc.matches("class=\"galaxy object\"").count(), 1);
+
+        // Without a threshold nothing is marked faint
+        let doc = ObjectsLayer::new()
+            .render(&objects_context(&objects, |_| {}))
+            .to_string();
+        assert!(!doc.contains("faint"));
+    }
+
+    #[test]
+    fn only_elongated_galaxies_get_an_axis_line() {
+        let mut edge_on = make_object("galaxy", 0.0, 0.0, 8.0, 10.0);
+        edge_on.size.minor = 1.5;
+        edge_on.angle = 30.0;
+        let mut round = make_object("galaxy", 1.0, 0.0, 8.0, 10.0);
+        round.identifier = "2".to_string();
+        round.size.minor = 8.0;
+        let objects = vec![edge_on, round];
+        let render = |axis_lines| {
+            let context = objects_context(&objects, |cfg| cfg.galaxy_axis_lines = axis_lines);
+            ObjectsLayer::new().render(&context).to_string()
+        };
+
+        let doc = render(true);
+        assert_eq!(doc.matches("class=\"galaxy-axis\"").count(), 1);
+        // Inside the edge-on galaxy's rotated group
+        let edge_on_group = &doc[doc.find("id=\"1\"").unwrap()..];
+        let edge_on_group = &edge_on_group[..edge_on_group.find("</g>").unwrap()];
+        assert!(edge_on_group.contains("rotate(30.00") && edge_on_group.contains("galaxy-axis"));
+        assert!(!render(false).contains("galaxy-axis"));
+    }
+
+    #[test]
+    fn emphasized_messier_objects_carry_the_messier_class() {
+        let mut m13 = make_object("globular-cluster", 0.0, 0.0, 5.8, 20.0);
+        m13.catalog = "M".to_string();
+        m13.identifier = "13".to_string();
+        let ngc = make_object("globular-cluster", 1.0, 0.0, 7.0, 10.0);
+        let objects = vec![m13, ngc];
+        let render = |emphasize_messier: bool| {
+            let context =
+                objects_context(&objects, |cfg| cfg.emphasize_messier = emphasize_messier);
+            ObjectsLayer::new().render(&context).to_string()
+        };
+
+        let doc = render(true);
+        assert_eq!(doc.matches("messier").count(), 1);
+        assert!(doc.contains("class=\"globular-cluster object messier\""));
+        assert!(doc.contains("class=\"globular-cluster object\""));
+        assert!(!render(false).contains("messier"));
+    }
+
+    #[test]
+    fn messier_objects_known_by_ngc_number_are_still_emphasized() {
+        // As loaded with --catalog-priority NGC,M,IC
+        let mut m13 = make_object("globular-cluster", 0.0, 0.0, 5.8, 20.0);
+        m13.identifier = "6205".to_string();
+        m13.alt_designation = Some("M 13".to_string());
+        let objects = [m13];
+        let context = objects_context(&objects, |cfg| cfg.emphasize_messier = true);
+        let doc = ObjectsLayer::new().render(&context).to_string();
+        assert!(doc.contains("class=\"globular-cluster object messier\""));
+    }
+
+    #[test]
+    fn objects_smaller_than_the_minimum_are_culled() {
+        let mut tiny = make_object("planetary-nebula", 0.0, 0.0, 9.0, 0.25);
+        tiny.identifier = "tiny".to_string();
+        let mut large = make_object("galaxy", 2.0, 0.0, 9.0, 2.0);
+        large.identifier = "large".to_string();
+        let mut sizeless = make_object("galaxy", 4.0, 0.0, 9.0, 0.0);
+        sizeless.identifier = "sizeless".to_string();
+        let objects = vec![tiny, large, sizeless];
+        let render = |min_object_arcsec: Option<f64>| {
+            let context =
+                objects_context(&objects, |cfg| cfg.min_object_arcsec = min_object_arcsec);
+            ObjectsLayer::new().render(&context).to_string()
+        };
+
+        assert!(render(None).contains("id=\"tiny\""));
+        // 15" is under the 30" limit; 2' and unknown sizes stay
+        let doc = render(Some(30.0));
+        assert!(!doc.contains("id=\"tiny\""));
+        assert!(doc.contains("id=\"large\"") && doc.contains("id=\"sizeless\""));
+    }
+
+    #[test]
+    fn surface_brightness_limit_culls_diffuse_objects() {
+        // Like M 33: bright in total, but spread over an hour of arc
+        let mut diffuse = make_object("galaxy", 0.0, 0.0, 5.7, 70.0);
+        diffuse.size.minor = 41.0;
+        diffuse.identifier = "598".to_string();
+        // Like M 32: fainter in total, but compact
+        let mut compact = make_object("galaxy", 2.0, 0.0, 8.1, 8.7);
+        compact.size.minor = 6.5;
+        compact.identifier = "221".to_string();
+        // Without a size there's no surface brightness to limit
+        let sizeless = make_object("galaxy", 4.0, 0.0, 9.0, 0.0);
+        let objects = vec![diffuse, compact, sizeless];
+        let render = |limit: Option<f64>| {
+            let context = objects_context(&objects, |cfg| cfg.limit_surface_brightness = limit);
+            ObjectsLayer::new().render(&context).to_string()
+        };
+
+        assert_eq!(render(None).matches("class=\"galaxy object\"").count(), 3);
+        let doc = render(Some(13.5));
+        assert_eq!(doc.matches("class=\"galaxy object\"").count(), 2);
+        let rx = attr_values(&doc, "rx");
+        // The compact galaxy's ellipse is the one left
+        assert_eq!(rx.len(), 1);
+    }
+
+    #[test]
+    fn brightest_objects_draw_last_whatever_the_listed_order() {
+        // NGC 100 is bright, 101 middling and 102 faint
+        let mut listed = make_objects([(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]);
+        for (o, magnitude) in listed.iter_mut().zip([4.0, 7.0, 10.0]) {
+            o.magnitude = magnitude;
+        }
+        let render = |objects: &[CelestialObject]| {
+            ObjectsLayer::new()
+                .render(&objects_context(objects, |_| {}))
+                .to_string()
+        };
+
+        let doc = render(&listed);
+        let at = |id: &str| doc.find(&format!("id=\"{id}\"")).unwrap();
+        assert!(at("102") < at("101") && at("101") < at("100"));
+        let mut reversed = listed.clone();
+        reversed.reverse();
+        assert_eq!(render(&reversed), doc);
+    }
+
+    fn render_one(kind: &str, magnitude: f64) -> String {
+        let objects = vec![make_object(kind, 0.0, 0.0, magnitude, 0.0)];
+        ObjectsLayer::new()
+            .render(&objects_context(&objects, |_| {}))
+            .to_string()
+    }
+
+    #[test]
+    fn zero_size_galaxy_falls_back_to_a_visible_circle() {
+        let doc = render_one("galaxy", 10.0);
+        assert!(!doc.contains("<ellipse"));
+        assert!(!doc.contains("transform"));
+        let r = attr_values(&doc, "r");
+        assert_eq!(r.len(), 1);
+        assert!(r[0] >= MIN_SYMBOL_SIZE * 0.5);
+    }
+
+    #[test]
+    fn galaxy_ellipses_follow_the_axis_ratio() {
+        let mut edge_on = make_object("galaxy", 0.0, 0.0, 9.0, 16.0);
+        edge_on.size.minor = 1.9;
+        let mut face_on = make_object("galaxy", 1.0, 0.0, 9.0, 16.0);
+        face_on.size.minor = 15.0;
+        let mut unknown = make_object("galaxy", 2.0, 0.0, 9.0, 16.0);
+        unknown.size.minor = 0.0;
+        let objects = vec![edge_on, face_on, unknown];
+        let doc = ObjectsLayer::new()
+            .render(&objects_context(&objects, |_| {}))
+            .to_string();
+        let rx = attr_values(&doc, "rx");
+        let ry = attr_values(&doc, "ry");
+        assert_eq!(rx.len(), 3);
+        let ratios: Vec<f64> = rx.iter().zip(&ry).map(|(x, y)| y / x).collect();
+        assert!(ratios[0] < 0.15, "{ratios:?}");
+        assert!(ratios[1] > 0.9, "{ratios:?}");
+        assert!(approx(ratios[2], 0.5, 0.01), "{ratios:?}");
+    }
+
+    #[test]
+    fn milky_way_patches_are_shaded_not_crossed() {
+        let mut cloud = make_object("milky-way", 0.0, 0.0, 4.5, 90.0);
+        cloud.size.minor = 60.0;
+        let objects = vec![cloud];
+        let doc = ObjectsLayer::new()
+            .render(&objects_context(&objects, |_| {}))
+            .to_string();
+        assert!(doc.contains("class=\"milky-way object\""));
+        assert_eq!(doc.matches("<ellipse").count(), 1);
+        assert!(!doc.contains("<line"));
+    }
+
+    #[test]
+    fn milky_way_patches_without_a_minor_axis_stay_visible() {
+        let mut cloud = make_object("milky-way", 0.0, 0.0, 4.5, 90.0);
+        cloud.size.minor = 0.0;
+        let objects = vec![cloud];
+        let doc = ObjectsLayer::new()
+            .render(&objects_context(&objects, |_| {}))
+            .to_string();
+        let (rx, ry) = (attr_values(&doc, "rx"), attr_values(&doc, "ry"));
+        assert!(approx(ry[0], rx[0] * 0.5, 0.01), "{rx:?} {ry:?}");
+    }
+
+    #[test]
+    fn small_galaxies_with_a_size_stay_small() {
+        // A faint 0.5' galaxy, drawn a little smaller than a sizeless one
+        let mut small = make_object("galaxy", 0.0, 0.0, 12.0, 0.5);
+        small.size.minor = 0.5;
+        let cfg = ChartConfig::default();
+        let size = symbol_size(&cfg, &small);
+        assert!(size < MIN_SYMBOL_SIZE, "{size}");
+        assert!(size >= SIZED_GALAXY_MIN_SIZE, "{size}");
+
+        let mut sizeless = small.clone();
+        sizeless.size.major = 0.0;
+        assert_eq!(symbol_size(&cfg, &sizeless), MIN_SYMBOL_SIZE);
+    }
+
+    #[test]
+    fn zero_size_cluster_stays_visible() {
+        let doc = render_one("open-cluster", 10.0);
+        let r = attr_values(&doc, "r");
+        assert_eq!(r.len(), 1);
+        assert!(r[0] >= MIN_SYMBOL_SIZE * 0.5);
+    }
+
+    #[test]
+    fn size_weight_grows_large_objects_only() {
+        // A 60' object grows with the size weight...
+        let large_low = radius(8.0, Some(60.0), 1.0, 0.3, 6.0);
+        let large_high = radius(8.0, Some(60.0), 1.0, 1.0, 6.0);
+        assert!(large_high > large_low);
+
+        // ...while a point-like object stays governed by magnitude
+        let point_low = radius(8.0, Some(0.0), 1.0, 0.3, 6.0);
+        let point_high = radius(8.0, Some(0.0), 1.0, 1.0, 6.0);
+        assert_eq!(point_low, point_high);
+        assert_eq!(point_low, r_mag(8.0, 3.0, 20.0, -1.0, 10.0).max(6.0));
+    }
+
+    #[test]
+    fn configured_weights_reach_the_rendered_symbol() {
+        let objects = vec![make_object("open-cluster", 0.0, 0.0, 8.0, 60.0)];
+        let render = |w_size: f64| {
+            let context = objects_context(&objects, |cfg| cfg.object_weight_size = w_size);
+            let doc = ObjectsLayer::new().render(&context).to_string();
+            attr_values(&doc, "r")[0]
+        };
+        assert!(render(1.0) > render(0.3));
+    }
+}
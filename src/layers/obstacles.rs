@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+/// An axis-aligned pixel rectangle: left, top, width and height
+pub type Rect = (f64, f64, f64, f64);
+
+// Rectangles spanning more cells than this are kept in a plain list instead
+// of being bucketed into every cell they cover
+const MAX_CELLS_PER_RECT: i64 = 64;
+
+/// Whether two rectangles overlap. Rectangles that only touch don't.
+pub fn rects_overlap(a: Rect, b: Rect) -> bool {
+    let (ax, ay, aw, ah) = a;
+    let (bx, by, bw, bh) = b;
+    !(ax + aw <= bx || ax >= bx + bw || ay + ah <= by || ay >= by + bh)
+}
+
+/// Rectangles already drawn on the chart, such as symbols and labels, for
+/// placing more without overlap. Each rectangle is bucketed into the cells of
+/// a uniform grid that it covers, so a collision check only compares
+/// against its neighbors rather than everything placed so far. With the
+/// thousands of star symbols on a wide chart this turns label placement from
+/// quadratic into roughly linear time.
+pub struct ObstacleMap {
+    cell_size: f64,
+    rects: Vec<Rect>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    // Indices of rectangles too big to bucket
+    oversized: Vec<usize>,
+}
+
+impl ObstacleMap {
+    /// An empty map bucketing into square cells `cell_size` pixels across,
+    /// best about the size of the rectangles it holds
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size,
+            rects: Vec::new(),
+            cells: HashMap::new(),
+            oversized: Vec::new(),
+        }
+    }
+
+    // The inclusive range of cell indices a span covers
+    fn cell_range(&self, start: f64, len: f64) -> (i64, i64) {
+        let first = (start / self.cell_size).floor() as i64;
+        let last = ((start + len) / self.cell_size).floor() as i64;
+        (first, last)
+    }
+
+    pub fn insert(&mut self, rect: Rect) {
+        let index = self.rects.len();
+        self.rects.push(rect);
+        let (x0, x1) = self.cell_range(rect.0, rect.2);
+        let (y0, y1) = self.cell_range(rect.1, rect.3);
+        if (x1 - x0 + 1).saturating_mul(y1 - y0 + 1) > MAX_CELLS_PER_RECT {
+            self.oversized.push(index);
+            return;
+        }
+        for cx in x0..=x1 {
+            for cy in y0..=y1 {
+                self.cells.entry((cx, cy)).or_default().push(index);
+            }
+        }
+    }
+
+    /// Whether a rectangle overlaps any inserted so far
+    pub fn collides(&self, rect: Rect) -> bool {
+        let hits = |i: &usize| rects_overlap(rect, self.rects[*i]);
+        if self.oversized.iter().any(hits) {
+            return true;
+        }
+        let (x0, x1) = self.cell_range(rect.0, rect.2);
+        let (y0, y1) = self.cell_range(rect.1, rect.3);
+        if (x1 - x0 + 1).saturating_mul(y1 - y0 + 1) > MAX_CELLS_PER_RECT {
+            return self.rects.iter().any(|&r| rects_overlap(rect, r));
+        }
+        (x0..=x1).any(|cx| {
+            (y0..=y1).any(|cy| {
+                self.cells
+                    .get(&(cx, cy))
+                    .is_some_and(|bucket| bucket.iter().any(hits))
+            })
+        })
+    }
+}
+
+impl Extend<Rect> for ObstacleMap {
+    fn extend<I: IntoIterator<Item = Rect>>(&mut self, rects: I) {
+        for rect in rects {
+            self.insert(rect);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlap_excludes_touching_edges() {
+        let a = (0.0, 0.0, 10.0, 10.0);
+        assert!(rects_overlap(a, (5.0, 5.0, 10.0, 10.0)));
+        assert!(rects_overlap(a, (2.0, 2.0, 1.0, 1.0)));
+        assert!(!rects_overlap(a, (10.0, 0.0, 5.0, 5.0)));
+        assert!(!rects_overlap(a, (0.0, -5.0, 5.0, 5.0)));
+    }
+
+    #[test]
+    fn collisions_are_found_across_cells() {
+        let mut map = ObstacleMap::new(16.0);
+        assert!(!map.collides((0.0, 0.0, 10.0, 10.0)));
+
+        // Straddles four cells
+        map.insert((10.0, 10.0, 12.0, 12.0));
+        assert!(map.collides((20.0, 20.0, 5.0, 5.0)));
+        assert!(map.collides((0.0, 0.0, 11.0, 11.0)));
+        // Same cell, no overlap
+        assert!(!map.collides((0.0, 0.0, 10.0, 10.0)));
+        // A neighboring cell
+        assert!(!map.collides((23.0, 0.0, 8.0, 8.0)));
+        // Negative coordinates bucket too
+        map.insert((-40.0, -40.0, 5.0, 5.0));
+        assert!(map.collides((-38.0, -38.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn oversized_rectangles_still_collide() {
+        let mut map = ObstacleMap::new(1.0);
+        map.extend([(0.0, 0.0, 500.0, 500.0)]);
+        assert!(map.collides((250.0, 250.0, 1.0, 1.0)));
+        assert!(map.collides((-100.0, -100.0, 200.0, 200.0)));
+        assert!(!map.collides((600.0, 0.0, 1.0, 1.0)));
+    }
+}
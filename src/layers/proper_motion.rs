@@ -0,0 +1,152 @@
+use svg::node::element::path::Data;
+use svg::node::element::{Group, Path};
+
+use crate::context::ChartContext;
+use crate::layers::stars::drawn_stars;
+use crate::layers::{group_with_id_class, Layer};
+use crate::types::Point;
+
+// Length of each side of an arrowhead, in pixels, and its angle off the shaft
+const HEAD_LEN: f64 = 4.0;
+const HEAD_ANGLE_DEG: f64 = 25.0;
+
+pub struct ProperMotionLayer;
+impl ProperMotionLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Layer for ProperMotionLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-proper-motion", "proper-motion");
+        let Some(years) = context.cfg.proper_motion_years else {
+            return g;
+        };
+        // The same stars the stars layer draws
+        for (s, from) in drawn_stars(context) {
+            let Some(pm) = s.proper_motion else {
+                continue;
+            };
+            let Some(to) = context.project_to_pixels(pm.position_after(s.coords, years)) else {
+                continue;
+            };
+            let len = from.distance(to);
+            // Too short to show a direction
+            if len < HEAD_LEN {
+                continue;
+            }
+
+            // The head's two sides, swung either way off the reversed shaft
+            let back = (from - to) * (HEAD_LEN / len);
+            let (sin, cos) = HEAD_ANGLE_DEG.to_radians().sin_cos();
+            let side = |sin: f64| {
+                to + Point {
+                    x: back.x * cos - back.y * sin,
+                    y: back.x * sin + back.y * cos,
+                }
+            };
+            let (left, right) = (side(sin), side(-sin));
+            let xy = |p: Point| (context.fmt_coord(p.x), context.fmt_coord(p.y));
+            let d = Data::new()
+                .move_to(xy(from))
+                .line_to(xy(to))
+                .move_to(xy(left))
+                .line_to(xy(to))
+                .line_to(xy(right));
+            g = g.add(
+                Path::new()
+                    .set("class", "pm-arrow")
+                    .set("id", format!("pm-{}", s.identifier))
+                    .set("d", d),
+            );
+        }
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Datasets;
+    use crate::test_utils::{make_context_with, make_star};
+    use crate::types::ProperMotion;
+
+    #[test]
+    fn arrows_point_along_the_proper_motion() {
+        // Moving north-east, 10° in each direction over the years drawn
+        let mut moving = make_star(0.0, 0.0, 3.0);
+        moving.proper_motion = Some(ProperMotion {
+            ra_mas_yr: 1000.0,
+            dec_mas_yr: 1000.0,
+        });
+        let still = make_star(5.0, 5.0, 3.0);
+        let stars = [moving, still];
+        let data = Datasets {
+            stars: &stars,
+            objects: &[],
+            constellations: &[],
+        };
+        let context = make_context_with(data, |cfg| cfg.proper_motion_years = Some(36_000.0));
+        let doc = ProperMotionLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("class=\"pm-arrow\"").count(), 1);
+
+        let d = doc
+            .split(" d=\"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        let numbers: Vec<f64> = d
+            .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+            .filter_map(|n| n.parse().ok())
+            .collect();
+        let (from, to) = (
+            Point {
+                x: numbers[0],
+                y: numbers[1],
+            },
+            Point {
+                x: numbers[2],
+                y: numbers[3],
+            },
+        );
+        let center = context.layout.plot_center();
+        assert!(from.distance(center) < 1e-6);
+        // North is up and east is left
+        assert!(to.x < from.x && to.y < from.y, "{from:?} -> {to:?}");
+        assert!((from.x - to.x - (from.y - to.y)).abs() < 5.0);
+
+        // No arrows unless asked for
+        let context = make_context_with(data, |_| {});
+        let doc = ProperMotionLayer::new().render(&context).to_string();
+        assert!(!doc.contains("pm-arrow"));
+    }
+
+    #[test]
+    fn stars_merged_away_have_no_arrow() {
+        let bright = make_star(0.0, 0.0, 2.0);
+        let mut faint = make_star(0.0, 0.0, 5.0);
+        faint.identifier = "2".to_string();
+        faint.proper_motion = Some(ProperMotion {
+            ra_mas_yr: 1000.0,
+            dec_mas_yr: 1000.0,
+        });
+        let stars = [bright, faint];
+        let render = |merge: bool| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| {
+                cfg.proper_motion_years = Some(36_000.0);
+                cfg.merge_coincident_stars = merge;
+            });
+            ProperMotionLayer::new().render(&context).to_string()
+        };
+        assert!(render(false).contains("id=\"pm-2\""));
+        assert!(!render(true).contains("pm-arrow"));
+    }
+}
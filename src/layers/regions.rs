@@ -0,0 +1,154 @@
+use svg::node::element::path::Data;
+use svg::node::element::{Group, Path};
+
+use crate::config::Region;
+use crate::context::ChartContext;
+use crate::geometry::{great_circle_points, split_segments};
+use crate::layers::{group_with_id_class, Layer};
+use crate::types::{EQPoint, Point};
+
+// Every edge is drawn with at least this many segments, however short
+const MIN_EDGE_SEGMENTS: f64 = 8.0;
+
+pub struct RegionsLayer;
+impl RegionsLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+// The region's outline sampled along its great-circle edges, back to the
+// first vertex
+fn outline(region: &Region, step_deg: f64) -> Vec<EQPoint> {
+    let v = &region.vertices;
+    let mut eqs: Vec<EQPoint> = Vec::new();
+    for (i, &a) in v.iter().enumerate() {
+        let b = v[(i + 1) % v.len()];
+        let step = step_deg.min(a.separation_deg(b) / MIN_EDGE_SEGMENTS);
+        // Each edge starts where the last one ended
+        let skip = usize::from(!eqs.is_empty());
+        eqs.extend(great_circle_points(a, b, step).into_iter().skip(skip));
+    }
+    eqs
+}
+
+fn polyline(context: &ChartContext<'_>, pts: &[Point]) -> Data {
+    let mut d = Data::new().move_to((context.fmt_coord(pts[0].x), context.fmt_coord(pts[0].y)));
+    for p in &pts[1..] {
+        d = d.line_to((context.fmt_coord(p.x), context.fmt_coord(p.y)));
+    }
+    d
+}
+
+impl Layer for RegionsLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-regions", "regions");
+        let step = context.cfg.sampling_step_deg.unwrap_or(1) as f64;
+
+        for region in &context.cfg.regions {
+            let eqs = outline(region, step);
+            // Samples outside the RA/Dec clip ranges or off the projection
+            // break the outline, as do jumps across the chart
+            let runs: Vec<Vec<Point>> = eqs
+                .split(|&eq| !context.in_sky_range(eq))
+                .map(|run| {
+                    run.iter()
+                        .filter_map(|&eq| context.project_to_pixels(eq))
+                        .collect::<Vec<_>>()
+                })
+                .flat_map(|pts| split_segments(&pts, context.layout.split_threshold))
+                .filter(|s| s.len() >= 2)
+                .collect();
+
+            // Whole, the outline is closed and filled. In pieces there's no
+            // telling which side is inside, so only the edges are drawn.
+            if let [whole] = &runs[..]
+                && whole.len() == eqs.len()
+            {
+                g = g.add(
+                    Path::new()
+                        .set("class", "region")
+                        .set("d", polyline(context, whole).close()),
+                );
+            } else {
+                for seg in &runs {
+                    g = g.add(
+                        Path::new()
+                            .set("class", "region partial")
+                            .set("fill", "none")
+                            .set("d", polyline(context, seg)),
+                    );
+                }
+            }
+        }
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_context;
+
+    fn triangle(size_deg: f64) -> Region {
+        let eq = |ra_deg, dec_deg| EQPoint { ra_deg, dec_deg };
+        Region {
+            vertices: vec![
+                eq(0.0, 0.0),
+                eq(size_deg, 0.0),
+                eq(size_deg / 2.0, size_deg),
+            ],
+        }
+    }
+
+    #[test]
+    fn triangle_is_a_closed_path_sampled_along_its_edges() {
+        let context = make_context(|cfg| cfg.regions = vec![triangle(10.0)]);
+        let doc = RegionsLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("class=\"region\"").count(), 1);
+        let d = doc
+            .split(" d=\"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        assert!(d.trim_end().ends_with('z'), "{d}");
+        // A 1° step puts ten or so segments on each edge, not just three
+        assert!(d.matches('L').count() >= 30, "{d}");
+        // The first edge's samples follow its great circle
+        let eqs = outline(&triangle(10.0), 1.0);
+        assert_eq!(eqs.first(), eqs.last());
+        assert!(eqs
+            .windows(2)
+            .all(|w| w[0].separation_deg(w[1]) <= 1.0 + 1e-9));
+    }
+
+    #[test]
+    fn a_region_through_the_far_side_is_drawn_in_pieces() {
+        // Half of it is behind the gnomonic chart's horizon
+        let context = make_context(|cfg| {
+            cfg.regions = vec![Region {
+                vertices: vec![
+                    EQPoint {
+                        ra_deg: 0.0,
+                        dec_deg: 0.0,
+                    },
+                    EQPoint {
+                        ra_deg: 170.0,
+                        dec_deg: 10.0,
+                    },
+                    EQPoint {
+                        ra_deg: 170.0,
+                        dec_deg: -10.0,
+                    },
+                ],
+            }];
+            cfg.fov_deg = 120.0;
+        });
+        let doc = RegionsLayer::new().render(&context).to_string();
+        assert!(!doc.contains("class=\"region\""));
+        assert!(doc.matches("class=\"region partial\"").count() >= 1);
+        assert!(!doc.contains('z'));
+    }
+}
@@ -1,8 +1,135 @@
-use svg::node::element::{Circle, Group};
+use std::collections::HashMap;
+use svg::node::element::{Circle, Group, Line, Rectangle};
+use svg::Node;
 
+use crate::config::FaintStarStyle;
 use crate::context::ChartContext;
-use crate::geometry::{project, to_pixels};
-use crate::layers::{group_with_class, Layer};
+use crate::layers::{group_with_id_class, LabelsLayer, Layer};
+use crate::types::{CelestialObject, EQPoint, Point};
+
+// Stars brighter than this get a halo when glow is enabled
+const GLOW_MAG: f64 = 1.5;
+
+// With the point style, stars with a smaller radius than this, in pixels,
+// are drawn as one-pixel squares. Circles this small antialias to a faint
+// smudge, or vanish entirely in some renderers.
+const POINT_RADIUS: f64 = 1.0;
+
+// Symbol radius in pixels for a star of the given magnitude
+pub(crate) fn star_radius(magnitude: f64, scale: f64) -> f64 {
+    (4.0 - 0.6 * magnitude).max(0.5) * scale
+}
+
+// Of the stars landing in the same 1px bucket, only the brightest, or the
+// first listed among equals. Survivors keep their order.
+fn brightest_per_pixel(stars: Vec<(&CelestialObject, Point)>) -> Vec<(&CelestialObject, Point)> {
+    let bucket = |p: Point| (p.x.floor() as i64, p.y.floor() as i64);
+    let mut brightest: HashMap<(i64, i64), usize> = HashMap::new();
+    for (i, (s, p)) in stars.iter().enumerate() {
+        brightest
+            .entry(bucket(*p))
+            .and_modify(|j| {
+                if s.magnitude < stars[*j].0.magnitude {
+                    *j = i;
+                }
+            })
+            .or_insert(i);
+    }
+    stars
+        .iter()
+        .enumerate()
+        .filter(|&(i, (_, p))| brightest[&bucket(*p)] == i)
+        .map(|(_, &star)| star)
+        .collect()
+}
+
+/// The stars the chart draws and where they land: those within the
+/// magnitude limit and sky range whose symbols fit the plot, only the
+/// labeled ones with `only_labeled`, and with `merge_coincident_stars` just
+/// the brightest in each pixel. Shared with the proper-motion arrows, the
+/// labels and the JSON output, so they all see the same set.
+pub(crate) fn drawn_stars<'a>(context: &ChartContext<'a>) -> Vec<(&'a CelestialObject, Point)> {
+    let labels = LabelsLayer::new();
+    let mut visible = Vec::new();
+    for s in context.data.stars {
+        if s.magnitude > context.cfg.limit_star_mag
+            || !context.in_sky_range(s.coords)
+            || (context.cfg.only_labeled && !labels.is_labeled(&context.cfg, s))
+        {
+            continue;
+        }
+        if let Some(p) = context.project_to_pixels(s.coords) {
+            let r = star_radius(s.magnitude, context.cfg.object_scale);
+            if context.fits_plot(p, r) {
+                visible.push((s, p));
+            }
+        }
+    }
+    if context.cfg.merge_coincident_stars {
+        visible = brightest_per_pixel(visible);
+    }
+    visible
+}
+
+// How far on the chart, in pixels, a point `arcsec` north of a star lies
+// from it, or south near the pole
+fn projected_separation(
+    context: &ChartContext<'_>,
+    s: &CelestialObject,
+    p: Point,
+    arcsec: f64,
+) -> Option<f64> {
+    let deg = arcsec / 3600.0;
+    let dec_deg = if s.coords.dec_deg + deg <= 90.0 {
+        s.coords.dec_deg + deg
+    } else {
+        s.coords.dec_deg - deg
+    };
+    let q = context.project_to_pixels(EQPoint {
+        ra_deg: s.coords.ra_deg,
+        dec_deg,
+    })?;
+    Some(p.distance(q))
+}
+
+// The double-star symbol: two dots joined by a bar, as far apart as the
+// components, or one dot with a bar through it where the separation is
+// unknown or too small for two dots. Catalogs don't give which way the
+// companion lies, so the bar runs across the page.
+fn double_star(context: &ChartContext<'_>, s: &CelestialObject, p: Point, r: f64) -> Group {
+    let g = Group::new()
+        .set("id", s.identifier.as_str())
+        .set("class", "double-star");
+    let dot = |x: f64, r: f64| {
+        Circle::new()
+            .set("class", "star")
+            .set("cx", context.fmt_coord(x))
+            .set("cy", context.fmt_coord(p.y))
+            .set("r", context.fmt_coord(r))
+    };
+    let bar = |x1: f64, x2: f64| {
+        Line::new()
+            .set("x1", context.fmt_coord(x1))
+            .set("y1", context.fmt_coord(p.y))
+            .set("x2", context.fmt_coord(x2))
+            .set("y2", context.fmt_coord(p.y))
+    };
+    let component_r = r * 0.7;
+    let apart = s
+        .multiplicity
+        .and_then(|m| m.separation_arcsec)
+        .and_then(|arcsec| projected_separation(context, s, p, arcsec))
+        .filter(|d| *d >= 2.0 * component_r + 1.0);
+    match apart {
+        Some(d) => {
+            let (left, right) = (p.x - d / 2.0, p.x + d / 2.0);
+            g.add(bar(left, right))
+                .add(dot(left, component_r))
+                .add(dot(right, component_r))
+        }
+        None => g.add(bar(p.x - 1.8 * r, p.x + 1.8 * r)).add(dot(p.x, r)),
+    }
+}
 
 pub struct StarsLayer;
 impl StarsLayer {
@@ -13,31 +140,218 @@ impl StarsLayer {
 
 impl Layer for StarsLayer {
     fn render(&self, context: &ChartContext<'_>) -> Group {
-        let mut g = group_with_class("stars");
+        let mut g = group_with_id_class("layer-stars", "stars");
         let scale = context.cfg.object_scale;
 
-        for s in context.data.stars {
-            if s.magnitude > context.cfg.limit_star_mag {
-                continue;
-            }
-            if let Some(tp) = project(
-                s.coords,
-                context.cfg.center,
-                context.cfg.projection,
-                context.cfg.position_angle_deg,
-            ) {
-                let p = to_pixels(tp, context.layout.center_px, context.layout.scale);
-                let r = (4.0 - 0.6 * s.magnitude).max(0.5) * scale;
-
-                let c = Circle::new()
-                    .set("id", s.identifier.as_str())
-                    .set("class", "star")
-                    .set("cx", p.x)
-                    .set("cy", p.y)
-                    .set("r", r);
+        for (s, p) in drawn_stars(context) {
+            let r = star_radius(s.magnitude, scale);
+            let c: Box<dyn Node> = if context.cfg.double_stars && s.multiplicity.is_some() {
+                Box::new(double_star(context, s, p, r))
+            } else if context.cfg.faint_star_style == FaintStarStyle::Point && r < POINT_RADIUS {
+                Box::new(
+                    Rectangle::new()
+                        .set("id", s.identifier.as_str())
+                        .set("class", "star")
+                        .set("x", context.fmt_coord(p.x - 0.5))
+                        .set("y", context.fmt_coord(p.y - 0.5))
+                        .set("width", 1)
+                        .set("height", 1),
+                )
+            } else {
+                Box::new(
+                    Circle::new()
+                        .set("id", s.identifier.as_str())
+                        .set("class", "star")
+                        .set("cx", context.fmt_coord(p.x))
+                        .set("cy", context.fmt_coord(p.y))
+                        .set("r", context.fmt_coord(r)),
+                )
+            };
+            if context.cfg.star_glow && s.magnitude < GLOW_MAG {
+                let halo = Circle::new()
+                    .set("class", "star-glow")
+                    .set("cx", context.fmt_coord(p.x))
+                    .set("cy", context.fmt_coord(p.y))
+                    .set("r", context.fmt_coord(r * 2.5));
+                g = g.add(Group::new().add(halo).add(c));
+            } else {
                 g = g.add(c);
             }
         }
         g
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClipShape;
+    use crate::context::Datasets;
+    use crate::test_utils::{approx, attr_values, make_context, make_context_with, make_star};
+    use crate::types::Multiplicity;
+
+    #[test]
+    fn group_carries_a_layer_id() {
+        let doc = StarsLayer::new().render(&make_context(|_| {})).to_string();
+        assert!(doc.starts_with("<g"));
+        assert!(doc.contains("id=\"layer-stars\""));
+        assert!(doc.contains("class=\"stars\""));
+    }
+
+    #[test]
+    fn glow_adds_a_halo_to_bright_stars_only() {
+        let circles = |magnitude: f64| {
+            let stars = vec![make_star(0.0, 0.0, magnitude)];
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| cfg.star_glow = true);
+            let doc = StarsLayer::new().render(&context).to_string();
+            (
+                doc.matches("<circle").count(),
+                doc.matches("star-glow").count(),
+            )
+        };
+        assert_eq!(circles(-1.0), (2, 1));
+        assert_eq!(circles(5.0), (1, 0));
+    }
+
+    #[test]
+    fn coincident_stars_merge_into_the_brightest() {
+        let mut bright = make_star(10.0, 10.0, 4.0);
+        bright.identifier = "2".to_string();
+        // A fraction of an arcsecond apart, well under a pixel
+        let stars = vec![make_star(10.0, 10.0001, 6.0), bright];
+        let render = |merge: bool| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| cfg.merge_coincident_stars = merge);
+            StarsLayer::new().render(&context).to_string()
+        };
+        assert_eq!(render(false).matches("<circle").count(), 2);
+        let doc = render(true);
+        assert_eq!(doc.matches("<circle").count(), 1);
+        assert!(doc.contains("id=\"2\""));
+    }
+
+    #[test]
+    fn point_style_draws_faint_stars_as_single_pixels() {
+        // Radii of 3.4px and 0.5px at the default scale
+        let stars = vec![make_star(0.0, 0.0, 1.0), make_star(1.0, 1.0, 6.0)];
+        let render = |style: FaintStarStyle| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| cfg.faint_star_style = style);
+            StarsLayer::new().render(&context).to_string()
+        };
+        let doc = render(FaintStarStyle::Circle);
+        assert_eq!(doc.matches("<circle").count(), 2);
+        assert!(!doc.contains("<rect"));
+
+        let doc = render(FaintStarStyle::Point);
+        assert_eq!(doc.matches("<circle").count(), 1);
+        assert_eq!(doc.matches("<rect").count(), 1);
+        assert!(doc.contains("width=\"1\""));
+    }
+
+    #[test]
+    fn only_labeled_omits_unnamed_faint_stars() {
+        let mut named = make_star(0.0, 0.0, 0.5);
+        named.name = "Bright".to_string();
+        let stars = vec![named, make_star(1.0, 1.0, 5.0)];
+        let render = |only_labeled: bool| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| cfg.only_labeled = only_labeled);
+            StarsLayer::new()
+                .render(&context)
+                .to_string()
+                .matches("<circle")
+                .count()
+        };
+        assert_eq!(render(false), 2);
+        assert_eq!(render(true), 1);
+    }
+
+    #[test]
+    fn circular_clip_drops_stars_outside_the_inscribed_circle() {
+        // Default 800x800 with 40px margins: a 360px circle. Toward the plot
+        // corner, this star lands about 370px out, inside the square but
+        // just outside the circle.
+        let stars = vec![make_star(0.0, 0.0, 3.0), make_star(22.0, 22.0, 3.0)];
+        let render = |clip: ClipShape| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| cfg.clip = clip);
+            let p = context.project_to_pixels(stars[1].coords).unwrap();
+            let l = &context.layout;
+            assert!(p.x > l.plot_x && p.y > l.plot_y);
+            StarsLayer::new()
+                .render(&context)
+                .to_string()
+                .matches("<circle")
+                .count()
+        };
+        assert_eq!(render(ClipShape::Rect), 2);
+        assert_eq!(render(ClipShape::Circle), 1);
+    }
+
+    #[test]
+    fn doubles_are_drawn_as_far_apart_as_their_components() {
+        let mut known = make_star(0.0, 0.0, 3.0);
+        known.multiplicity = Some(Multiplicity {
+            components: Some(2),
+            separation_arcsec: Some(60.0),
+        });
+        let mut unknown = make_star(0.1, 0.1, 3.0);
+        unknown.identifier = "2".to_string();
+        unknown.multiplicity = Some(Multiplicity {
+            components: Some(2),
+            separation_arcsec: None,
+        });
+        let stars = vec![known, unknown];
+        let render = |double_stars| {
+            let data = Datasets {
+                stars: &stars,
+                objects: &[],
+                constellations: &[],
+            };
+            let context = make_context_with(data, |cfg| {
+                cfg.fov_deg = 1.0;
+                cfg.coord_decimals = 6;
+                cfg.double_stars = double_stars;
+            });
+            let doc = StarsLayer::new().render(&context).to_string();
+            (doc, context.cfg.fov_to_scale(context.cfg.fov_deg))
+        };
+
+        let (plain, _) = render(false);
+        assert_eq!(plain.matches("<circle").count(), 2);
+        assert!(!plain.contains("double-star"));
+
+        let (doc, arcsec_per_px) = render(true);
+        assert_eq!(doc.matches("class=\"double-star\"").count(), 2);
+        assert_eq!(doc.matches("<line").count(), 2);
+        let known = &doc[doc.find("id=\"1\"").unwrap()..doc.find("id=\"2\"").unwrap()];
+        let cx = attr_values(known, "cx");
+        assert_eq!(cx.len(), 2);
+        assert!(approx((cx[1] - cx[0]).abs(), 60.0 / arcsec_per_px, 1e-3));
+        // Without a separation, one dot with the bar through it
+        let unknown = &doc[doc.find("id=\"2\"").unwrap()..];
+        assert_eq!(unknown.matches("<circle").count(), 1);
+    }
+}
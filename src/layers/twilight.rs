@@ -0,0 +1,196 @@
+use svg::node::element::path::Data;
+use svg::node::element::{Group, Path};
+
+use crate::context::ChartContext;
+use crate::layers::{group_with_id_class, Layer};
+use crate::observer::Observer;
+use crate::types::Point;
+
+// Astronomical twilight ends when the sun is 18° below the horizon
+const TWILIGHT_ALT_DEG: f64 = -18.0;
+
+// Civil, nautical and astronomical twilight, each 6° deeper than the last,
+// as (class, upper altitude, lower altitude)
+const TWILIGHT_ZONES: [(&str, f64, f64); 3] = [
+    ("civil", 0.0, -6.0),
+    ("nautical", -6.0, -12.0),
+    ("astronomical", -12.0, TWILIGHT_ALT_DEG),
+];
+
+pub struct TwilightLayer;
+impl TwilightLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+// The circle of constant altitude, in pixel space, or None if the projection
+// doesn't reach all of it
+fn altitude_ring(
+    context: &ChartContext<'_>,
+    observer: Observer,
+    alt_deg: f64,
+) -> Option<Vec<Point>> {
+    let step = context.cfg.sampling_step_deg.unwrap_or(2) as usize;
+    (0..360)
+        .step_by(step)
+        .map(|az| context.project_unculled(observer.horizontal_to_eq(alt_deg, az as f64)))
+        .collect()
+}
+
+fn ring_data(context: &ChartContext<'_>, d: Data, ring: &[Point]) -> Data {
+    let mut d = d.move_to((context.fmt_coord(ring[0].x), context.fmt_coord(ring[0].y)));
+    for p in &ring[1..] {
+        d = d.line_to((context.fmt_coord(p.x), context.fmt_coord(p.y)));
+    }
+    d.close()
+}
+
+// The sky between two altitudes, filled between its edges. It's only drawn
+// when both circles project whole; only the stereographic projection reaches
+// more than 90° from the center, all the way around, so the command line
+// takes no other for the twilight shading.
+fn altitude_band(
+    context: &ChartContext<'_>,
+    observer: Observer,
+    upper_deg: f64,
+    lower_deg: f64,
+) -> Option<Path> {
+    let upper = altitude_ring(context, observer, upper_deg)?;
+    let lower = altitude_ring(context, observer, lower_deg)?;
+    let d = ring_data(context, Data::new(), &upper);
+    let d = ring_data(context, d, &lower);
+    Some(Path::new().set("fill-rule", "evenodd").set("d", d))
+}
+
+impl Layer for TwilightLayer {
+    fn render(&self, context: &ChartContext<'_>) -> Group {
+        let mut g = group_with_id_class("layer-twilight", "twilight");
+        let Some(observer) = context.cfg.observer else {
+            return g;
+        };
+        if context.cfg.twilight_zones {
+            for (name, upper, lower) in TWILIGHT_ZONES {
+                if let Some(band) = altitude_band(context, observer, upper, lower) {
+                    g = g.add(band.set("class", format!("twilight-zone {name}")));
+                }
+            }
+        } else if context.cfg.twilight_band
+            && let Some(band) = altitude_band(context, observer, 0.0, TWILIGHT_ALT_DEG)
+        {
+            g = g.add(band.set("class", "twilight-band"));
+        }
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ChartConfig;
+    use crate::geometry::radial_distance;
+    use crate::observer::parse_utc;
+    use crate::test_utils::{approx, make_context};
+    use crate::types::Projection;
+
+    fn observer() -> Observer {
+        Observer {
+            lat_deg: 40.0,
+            lon_deg: -75.0,
+            jd: parse_utc("2024-01-15T04:00:00Z").unwrap(),
+        }
+    }
+
+    fn zenith_context(
+        projection: Projection,
+        patch: impl FnOnce(&mut ChartConfig),
+    ) -> ChartContext<'static> {
+        let observer = observer();
+        make_context(|cfg| {
+            cfg.projection = projection;
+            cfg.center = observer.zenith();
+            // Out to the horizon: stereographic charts are sized by tan(fov/2)
+            cfg.fov_deg = 90.0;
+            cfg.observer = Some(observer);
+            patch(cfg);
+        })
+    }
+
+    fn render(projection: Projection, twilight_band: bool) -> String {
+        let context = zenith_context(projection, |cfg| cfg.twilight_band = twilight_band);
+        TwilightLayer::new().render(&context).to_string()
+    }
+
+    #[test]
+    fn band_is_drawn_only_when_asked_and_reachable() {
+        let doc = render(Projection::Stereographic, true);
+        assert_eq!(doc.matches("class=\"twilight-band\"").count(), 1);
+        // Two closed rings
+        assert_eq!(doc.matches('z').count(), 2);
+
+        assert!(!render(Projection::Stereographic, false).contains("twilight-band"));
+        // Centered on the zenith, the altaz projection stops at the horizon
+        assert!(!render(Projection::AltAz, true).contains("twilight-band"));
+    }
+
+    // The rings of the path drawn with `class`, as points
+    fn drawn_rings(doc: &str, class: &str) -> Vec<Vec<Point>> {
+        let needle = format!("class=\"{class}\" d=\"");
+        let start = doc.find(&needle).unwrap() + needle.len();
+        let d = &doc[start..start + doc[start..].find('"').unwrap()];
+        d.split('M')
+            .filter(|ring| !ring.trim().is_empty())
+            .map(|ring| {
+                ring.trim()
+                    .trim_end_matches('z')
+                    .split('L')
+                    .map(|p| {
+                        let (x, y) = p.trim().split_once(',').unwrap();
+                        Point {
+                            x: x.parse().unwrap(),
+                            y: y.parse().unwrap(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn zones_need_the_rings_to_project_whole() {
+        // Centered on the zenith, the altaz projection stops at the horizon
+        let context = zenith_context(Projection::AltAz, |cfg| cfg.twilight_zones = true);
+        let doc = TwilightLayer::new().render(&context).to_string();
+        assert!(!doc.contains("twilight-zone"), "{doc}");
+    }
+
+    #[test]
+    fn zones_step_down_from_the_horizon() {
+        let context = zenith_context(Projection::Stereographic, |cfg| cfg.twilight_zones = true);
+        let doc = TwilightLayer::new().render(&context).to_string();
+        assert!(!doc.contains("twilight-band"));
+
+        // Centered on the zenith, each zone is drawn between the circles as
+        // far out as its altitudes lie from the zenith
+        let center = context.layout.plot_center();
+        let radius = |alt_deg: f64| {
+            let zenith = (90.0 - alt_deg).to_radians();
+            context.layout.scale * radial_distance(Projection::Stereographic, zenith)
+        };
+        for (name, upper, lower) in [
+            ("civil", 0.0, -6.0),
+            ("nautical", -6.0, -12.0),
+            ("astronomical", -12.0, -18.0),
+        ] {
+            let rings = drawn_rings(&doc, &format!("twilight-zone {name}"));
+            assert_eq!(rings.len(), 2, "{name}");
+            for (ring, alt_deg) in rings.iter().zip([upper, lower]) {
+                let r = radius(alt_deg);
+                assert!(
+                    ring.iter().all(|p| approx(p.distance(center), r, 0.01)),
+                    "{name} edge at {alt_deg}°"
+                );
+            }
+        }
+    }
+}
@@ -1,8 +1,9 @@
-use svg::node::element::{Group, Line};
+use svg::node::element::{Circle, Group, Line};
 
+use crate::config::CenterMarker;
 use crate::context::ChartContext;
-use crate::geometry::{project, to_pixels};
-use crate::layers::{group_with_class, Layer};
+use crate::layers::{group_with_id_class, Layer};
+use crate::types::Point;
 
 pub struct ZenithLayer;
 impl ZenithLayer {
@@ -11,34 +12,92 @@ impl ZenithLayer {
     }
 }
 
+/// Horizontal and vertical lines of a cross `size` pixels across, centered
+/// on `p`
+pub(crate) fn cross(context: &ChartContext<'_>, p: Point, size: f64) -> [Line; 2] {
+    let h = Line::new()
+        .set("class", "crosshair")
+        .set("x1", context.fmt_coord(p.x - size / 2.0))
+        .set("y1", context.fmt_coord(p.y))
+        .set("x2", context.fmt_coord(p.x + size / 2.0))
+        .set("y2", context.fmt_coord(p.y));
+    let v = Line::new()
+        .set("class", "crosshair")
+        .set("x1", context.fmt_coord(p.x))
+        .set("y1", context.fmt_coord(p.y - size / 2.0))
+        .set("x2", context.fmt_coord(p.x))
+        .set("y2", context.fmt_coord(p.y + size / 2.0));
+    [h, v]
+}
+
 impl Layer for ZenithLayer {
     fn render(&self, context: &ChartContext<'_>) -> Group {
-        let mut g = group_with_class("zenith");
-
-        if let Some(tp) = project(
-            context.cfg.center,
-            context.cfg.center,
-            context.cfg.projection,
-            context.cfg.position_angle_deg,
-        ) {
-            let p = to_pixels(tp, context.layout.center_px, context.layout.scale);
-            let size = 10.0;
-
-            let h = Line::new()
-                .set("x1", p.x - size / 2.0)
-                .set("y1", p.y)
-                .set("x2", p.x + size / 2.0)
-                .set("y2", p.y)
-                .set("stroke-width", 2);
-            let v = Line::new()
-                .set("x1", p.x)
-                .set("y1", p.y - size / 2.0)
-                .set("x2", p.x)
-                .set("y2", p.y + size / 2.0)
-                .set("stroke-width", 2);
-
-            g = g.add(h).add(v);
+        let mut g = group_with_id_class("layer-zenith", "zenith");
+        let marker = context.cfg.center_marker;
+        if marker == CenterMarker::None {
+            return g;
+        }
+
+        if let Some(p) = context.project_to_pixels(context.cfg.center) {
+            let size = context.cfg.center_marker_size;
+
+            match marker {
+                CenterMarker::Cross => {
+                    let [h, v] = cross(context, p, size);
+                    g = g.add(h).add(v);
+                }
+                CenterMarker::Dot => {
+                    g = g.add(
+                        Circle::new()
+                            .set("class", "dot")
+                            .set("cx", context.fmt_coord(p.x))
+                            .set("cy", context.fmt_coord(p.y))
+                            .set("r", context.fmt_coord(size / 4.0)),
+                    );
+                }
+                CenterMarker::Circle => {
+                    g = g.add(
+                        Circle::new()
+                            .set("class", "ring")
+                            .set("cx", context.fmt_coord(p.x))
+                            .set("cy", context.fmt_coord(p.y))
+                            .set("r", context.fmt_coord(size / 2.0)),
+                    );
+                }
+                CenterMarker::None => {}
+            }
         }
         g
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{attr_values, make_context};
+
+    #[test]
+    fn none_suppresses_the_marker() {
+        let context = make_context(|cfg| cfg.center_marker = CenterMarker::None);
+        let doc = ZenithLayer::new().render(&context).to_string();
+        assert!(!doc.contains("<line") && !doc.contains("<circle"));
+    }
+
+    #[test]
+    fn cross_is_two_classed_lines_of_the_configured_size() {
+        let context = make_context(|cfg| cfg.center_marker_size = 20.0);
+        let doc = ZenithLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("<line class=\"crosshair\"").count(), 2);
+        let x1 = attr_values(&doc, "x1")[0];
+        let x2 = attr_values(&doc, "x2")[0];
+        assert_eq!(x2 - x1, 20.0);
+    }
+
+    #[test]
+    fn dot_emits_a_circle() {
+        let context = make_context(|cfg| cfg.center_marker = CenterMarker::Dot);
+        let doc = ZenithLayer::new().render(&context).to_string();
+        assert_eq!(doc.matches("<circle").count(), 1);
+        assert!(!doc.contains("<line"));
+    }
+}
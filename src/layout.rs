@@ -1,7 +1,10 @@
-use crate::config::ChartConfig;
-use crate::types::Point;
+use crate::config::{ChartConfig, Fit};
+use crate::geometry::radial_slope;
+use crate::types::{Point, Projection};
+use serde::Serialize;
+use std::f64::consts::FRAC_PI_2;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct ChartLayout {
     pub plot_x: f64,
     pub plot_y: f64,
@@ -18,26 +21,350 @@ impl From<&ChartConfig> for ChartLayout {
         let plot_y = cfg.margin.top as f64;
         let plot_w = (cfg.width - cfg.margin.left - cfg.margin.right) as f64;
         let plot_h = (cfg.height - cfg.margin.top - cfg.margin.bottom) as f64;
+        // The projection's tangent point, shifted off the middle of the plot
+        // by a fraction of its size
+        let (dx, dy) = cfg.center_offset;
         let center_px = Point {
-            x: plot_x + plot_w / 2.0,
-            y: plot_y + plot_h / 2.0,
+            x: plot_x + plot_w * (0.5 + dx),
+            y: plot_y + plot_h * (0.5 + dy),
         };
 
-        let half_fov_rad = (cfg.fov_deg / 2.0).to_radians();
-        let rho_max = half_fov_rad.tan();
-        let radius_px = plot_w.min(plot_h) / 2.0;
-        let scale = radius_px / rho_max;
-
         let split_threshold = plot_w.min(plot_h) * 0.8;
 
-        Self {
+        let mut layout = Self {
             plot_x,
             plot_y,
             plot_w,
             plot_h,
             center_px,
-            scale,
+            scale: 0.0,
             split_threshold,
+        };
+        layout.scale = layout.projection_scale(cfg, cfg.fov_deg);
+        layout
+    }
+}
+
+const ARCSEC_PER_RADIAN: f64 = 180.0 * 3600.0 / std::f64::consts::PI;
+
+impl ChartConfig {
+    // How far out on the projection plane the edge of a chart `fov_deg`
+    // across lies
+    fn rho_max(&self, fov_deg: f64) -> f64 {
+        let half_fov_rad = (fov_deg / 2.0).to_radians();
+        match self.projection {
+            // Cylindrical projections are linear in RA
+            Projection::Mercator => half_fov_rad,
+            // Equidistant: the horizon (90° from the center) is at r = 1, so
+            // a field of view of 180° reaches it. Sized by tan(fov/2), as
+            // gnomonic is, the horizon would never fit.
+            Projection::AltAz => half_fov_rad / FRAC_PI_2,
+            // Orthographic: the limb, 90° out, is as far as it reaches
+            Projection::Spherical => half_fov_rad.min(FRAC_PI_2).sin(),
+            _ => half_fov_rad.tan(),
+        }
+    }
+
+    // Inverse of `rho_max`: the field of view whose edge lies `rho` out
+    fn fov_at_rho(&self, rho: f64) -> f64 {
+        let half_fov_rad = match self.projection {
+            Projection::Mercator => rho,
+            Projection::AltAz => rho * FRAC_PI_2,
+            Projection::Spherical => rho.min(1.0).asin(),
+            _ => rho.atan(),
+        };
+        2.0 * half_fov_rad.to_degrees()
+    }
+
+    /// The plate scale at the chart center, in arcseconds per pixel, of a
+    /// chart `fov_deg` across with this projection, size and fit. Away from
+    /// the center gnomonic stretches the sky the most and stereographic
+    /// less, so the scale gets finer towards the edge, while spherical
+    /// compresses it, so the scale there is coarser.
+    pub fn fov_to_scale(&self, fov_deg: f64) -> f64 {
+        let scale = ChartLayout::from(self).projection_scale(self, fov_deg);
+        ARCSEC_PER_RADIAN / (scale * radial_slope(self.projection, 0.0))
+    }
+
+    /// The field of view, in degrees, whose plate scale at the chart center
+    /// is `arcsec_per_px`; the inverse of `fov_to_scale`
+    pub fn scale_to_fov(&self, arcsec_per_px: f64) -> f64 {
+        let scale = ARCSEC_PER_RADIAN / (arcsec_per_px * radial_slope(self.projection, 0.0));
+        let radius = ChartLayout::from(self).fit_radius(self.fit);
+        self.fov_at_rho(radius / scale)
+    }
+}
+
+impl ChartLayout {
+    // Half the plot dimension the field of view spans, in pixels
+    fn fit_radius(&self, fit: Fit) -> f64 {
+        let span = match fit {
+            Fit::Width => self.plot_w,
+            Fit::Height => self.plot_h,
+            Fit::Contain => self.plot_w.min(self.plot_h),
+            Fit::Cover => self.plot_w.max(self.plot_h),
+        };
+        span / 2.0
+    }
+
+    // Pixels per unit of the projection plane for a chart `fov_deg` across
+    fn projection_scale(&self, cfg: &ChartConfig, fov_deg: f64) -> f64 {
+        self.fit_radius(cfg.fit) / cfg.rho_max(fov_deg)
+    }
+
+    /// The middle of the plot area, which `center_px` is offset from
+    pub fn plot_center(&self) -> Point {
+        Point {
+            x: self.plot_x + self.plot_w / 2.0,
+            y: self.plot_y + self.plot_h / 2.0,
+        }
+    }
+
+    /// Radius of the circle inscribed in the plot area, in pixels
+    pub fn inscribed_radius(&self) -> f64 {
+        self.plot_w.min(self.plot_h) / 2.0
+    }
+
+    /// Whether a pixel falls inside the plot rectangle
+    pub fn contains(&self, p: Point) -> bool {
+        (self.plot_x..=self.plot_x + self.plot_w).contains(&p.x)
+            && (self.plot_y..=self.plot_y + self.plot_h).contains(&p.y)
+    }
+}
+
+/// A pixel-space bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Bounds {
+    /// The box around a circle of radius `r`
+    pub fn around(p: Point, r: f64) -> Self {
+        Self {
+            min_x: p.x - r,
+            min_y: p.y - r,
+            max_x: p.x + r,
+            max_y: p.y + r,
+        }
+    }
+
+    /// The box around some points, or None for no points
+    pub fn from_points(points: &[Point]) -> Option<Self> {
+        let (&first, rest) = points.split_first()?;
+        Some(rest.iter().fold(Self::around(first, 0.0), |b, &p| {
+            b.union(Self::around(p, 0.0))
+        }))
+    }
+
+    /// The smallest box covering both
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    /// The shortest distance between the two boxes, 0 where they overlap
+    pub fn distance_to(&self, other: &Self) -> f64 {
+        let dx = (other.min_x - self.max_x).max(self.min_x - other.max_x);
+        let dy = (other.min_y - self.max_y).max(self.min_y - other.max_y);
+        dx.max(0.0).hypot(dy.max(0.0))
+    }
+
+    pub fn center(&self) -> Point {
+        Point {
+            x: (self.min_x + self.max_x) * 0.5,
+            y: (self.min_y + self.max_y) * 0.5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bounds;
+    use crate::config::{ChartConfig, Fit, Margin};
+    use crate::test_utils::{approx, make_context};
+    use crate::types::{EQPoint, Point, Projection};
+
+    #[test]
+    fn bounds_from_points_span_the_extremes() {
+        assert_eq!(Bounds::from_points(&[]), None);
+        let p = |x, y| Point { x, y };
+        assert_eq!(
+            Bounds::from_points(&[p(3.0, -1.0)]),
+            Some(Bounds::around(p(3.0, -1.0), 0.0))
+        );
+        assert_eq!(
+            Bounds::from_points(&[p(3.0, -1.0), p(-2.0, 4.0), p(0.5, 0.5)]),
+            Some(Bounds {
+                min_x: -2.0,
+                min_y: -1.0,
+                max_x: 3.0,
+                max_y: 4.0,
+            })
+        );
+    }
+
+    #[test]
+    fn width_fit_spans_the_fov_across_a_wide_chart() {
+        let edge = EQPoint {
+            ra_deg: 20.0,
+            dec_deg: 0.0,
+        };
+        let context = |fit| {
+            make_context(|cfg| {
+                cfg.width = 1200;
+                cfg.height = 400;
+                cfg.margin = Margin::uniform(0);
+                cfg.fov_deg = 40.0;
+                cfg.fit = fit;
+            })
+        };
+
+        // Half the FOV east of center lands on the left edge
+        let width = context(Fit::Width);
+        let p = width.project_to_pixels(edge).unwrap();
+        assert!(approx(p.x, 0.0, 1e-9));
+        assert!(approx(
+            width.layout.scale,
+            3.0 * context(Fit::Contain).layout.scale,
+            1e-9
+        ));
+
+        // Cover picks the wider dimension, and height the shorter one here
+        assert!(approx(
+            context(Fit::Cover).layout.scale,
+            width.layout.scale,
+            1e-9
+        ));
+        let contain = context(Fit::Contain).layout.scale;
+        assert!(approx(context(Fit::Height).layout.scale, contain, 1e-9));
+    }
+
+    #[test]
+    fn width_fit_spans_the_fov_edge_to_edge_on_a_tall_chart() {
+        let context = make_context(|cfg| {
+            cfg.width = 600;
+            cfg.height = 800;
+            cfg.fov_deg = 30.0;
+            cfg.fit = Fit::Width;
+        });
+        let l = context.layout;
+        let at = |ra_deg| {
+            context
+                .project_to_pixels(EQPoint {
+                    ra_deg,
+                    dec_deg: 0.0,
+                })
+                .unwrap()
+        };
+        assert!(approx(at(15.0).x, l.plot_x, 1e-9));
+        assert!(approx(at(345.0).x, l.plot_x + l.plot_w, 1e-9));
+    }
+
+    #[test]
+    fn center_offset_moves_the_projection_center_only() {
+        let context = make_context(|cfg| cfg.center_offset = (0.1, 0.25));
+        let l = context.layout;
+        // 720px square plot
+        let p = context.project_to_pixels(context.cfg.center).unwrap();
+        assert!(approx(p.x - l.plot_center().x, 72.0, 1e-9));
+        assert!(approx(p.y - l.plot_center().y, 180.0, 1e-9));
+        assert!(approx(l.plot_center().x, 400.0, 1e-9));
+        assert!(approx(l.scale, make_context(|_| {}).layout.scale, 1e-9));
+    }
+
+    #[test]
+    fn altaz_fov_of_180_puts_the_horizon_on_the_plot_edge() {
+        let context = make_context(|cfg| {
+            cfg.projection = Projection::AltAz;
+            cfg.fov_deg = 180.0;
+        });
+        let l = context.layout;
+        // The horizon projects to r = 1, which should be half the plot
+        assert!(approx(l.scale, l.plot_w.min(l.plot_h) / 2.0, 1e-9));
+    }
+
+    #[test]
+    fn spherical_scale_follows_the_sine_of_half_the_fov() {
+        let scale = |fov_deg| {
+            make_context(|cfg| {
+                cfg.projection = Projection::Spherical;
+                cfg.fov_deg = fov_deg;
+            })
+            .layout
+            .scale
+        };
+        // 720px square plot, so a 360px radius
+        assert!(approx(scale(120.0), 360.0 / 60f64.to_radians().sin(), 1e-9));
+        assert!(approx(scale(180.0), 360.0, 1e-9));
+        // Wider than the visible hemisphere, the limb still fills the plot
+        assert!(approx(scale(240.0), 360.0, 1e-9));
+    }
+
+    #[test]
+    fn fov_and_plate_scale_round_trip_in_every_projection() {
+        for projection in [
+            Projection::Gnomonic,
+            Projection::Stereographic,
+            Projection::Spherical,
+            Projection::AltAz,
+            Projection::Mercator,
+        ] {
+            let cfg = ChartConfig {
+                projection,
+                fit: Fit::Width,
+                width: 1200,
+                ..ChartConfig::default()
+            };
+            for fov_deg in [0.5, 20.0, 60.0, 150.0] {
+                let scale = cfg.fov_to_scale(fov_deg);
+                let back = cfg.scale_to_fov(scale);
+                assert!(
+                    approx(back, fov_deg, 1e-9),
+                    "{projection} {fov_deg}: {back}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn plate_scale_matches_the_drawn_chart_at_its_center() {
+        for projection in [
+            Projection::Gnomonic,
+            Projection::Stereographic,
+            Projection::AltAz,
+        ] {
+            let context = make_context(|cfg| cfg.projection = projection);
+            let center = context.project_to_pixels(context.cfg.center).unwrap();
+            // One arcminute north of the center
+            let mut north = context.cfg.center;
+            north.dec_deg += 1.0 / 60.0;
+            let p = context.project_to_pixels(north).unwrap();
+            let measured = 60.0 / (p.y - center.y).abs();
+            let scale = context.cfg.fov_to_scale(context.cfg.fov_deg);
+            assert!(
+                approx(measured, scale, 1e-4),
+                "{projection}: {measured} vs {scale}"
+            );
         }
+        // A 60° gnomonic chart 720px across: 360px is tan(30°)
+        let cfg = ChartConfig::default();
+        let expected = 30f64.to_radians().tan() / 360.0 * 180.0 * 3600.0 / std::f64::consts::PI;
+        assert!(approx(cfg.fov_to_scale(60.0), expected, 1e-9));
     }
 }
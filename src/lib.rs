@@ -0,0 +1,16 @@
+pub mod chart;
+pub mod config;
+pub mod context;
+mod crossmatch;
+pub mod data;
+pub mod error;
+pub mod geometry;
+mod json;
+mod layers;
+pub mod layout;
+pub mod observer;
+mod png;
+pub mod types;
+
+#[cfg(test)]
+mod test_utils;
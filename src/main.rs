@@ -1,64 +1,205 @@
-mod chart;
-mod config;
-mod context;
-mod data;
-mod geometry;
-mod layers;
-mod layout;
-mod types;
-
-use crate::chart::Chart;
-use crate::config::{ChartConfig, Margin};
-use crate::context::Datasets;
-use crate::data::{load_constellations, load_objects, load_stars};
-use crate::types::{
-    hours_to_degrees, parse_dms, parse_hms, sexagesimal_dms_to_degrees, sexagesimal_hms_to_hours,
-    EQPoint, Projection,
+use charter::chart::{Chart, CssSource};
+use charter::config::{
+    Arc, CenterMarker, ChartConfig, ClipShape, Crosshair, DecTickBand, FaintStarStyle, Fit,
+    FrameStyle, Inset, LabelSort, Margin, PageLayout, Region, LABEL_CHAR_WIDTH_PER_PX,
+    LABEL_LINE_HEIGHT_PER_PX,
+};
+use charter::context::Datasets;
+use charter::data::{
+    default_catalog_priority, find_constellation, find_object, frame_constellation,
+    load_constellations, load_objects, load_stars, merge_objects, snap_to_stars, Figures,
+    ObjectOrder, StarFormat,
+};
+use charter::error::ChartError;
+use charter::geometry::{scale_factors, within_projection};
+use charter::observer::{now_jd, parse_utc, Observer};
+use charter::types::{
+    format_dms, format_hms, parse_dec_deg, parse_ra_deg_in, AngleUnit, CelestialObject, EQPoint,
+    Projection, RaDirection,
 };
 
 use anyhow::{anyhow, Context, Result};
 use clap::Parser;
+use std::fs;
+use std::path::Path;
 
 #[derive(Parser, Debug)]
 #[command(name = "charter")]
 #[command(about = "Simple and attractive star charts", version)]
 struct Args {
-    /// Center RA either as hour:minute:second (e.g. "5:35:17.3") or decimal degrees ("83.821")
-    #[arg(long = "ra", alias = "center-ra")]
-    ra: String,
+    /// Center RA either as hour:minute:second (e.g. "5:35:17.3" or "5h35m17.3s") or decimal degrees ("83.821")
+    #[arg(long = "ra", alias = "center-ra", required_unless_present_any = ["lat", "list_projections", "catalog_info", "distortion_report", "center_on_constellation", "layout"])]
+    ra: Option<String>,
+
+    /// Center Dec as degree:minute:second (e.g. "-5:23:28" or "-5°23′28″") or decimal degrees ("-5.391")
+    #[arg(long = "dec", alias = "center-dec", required_unless_present_any = ["lat", "list_projections", "catalog_info", "distortion_report", "center_on_constellation", "layout"])]
+    dec: Option<String>,
+
+    /// How a bare RA number is read, either `degrees` or `hours`, in --ra, --ra-range, --inset, --arc, --crosshair and --region. Sexagesimal and suffixed RAs keep their own units
+    #[arg(long, alias = "center-ra-unit", default_value = "degrees")]
+    ra_unit: String,
+
+    /// How a bare Dec number is read. Only `degrees` is accepted, so scripts can state it explicitly
+    #[arg(long, alias = "center-dec-unit", default_value = "degrees")]
+    dec_unit: String,
+
+    /// Observer latitude in degrees, north positive; with --lon enables observer mode
+    #[arg(long, requires = "lon", allow_hyphen_values = true)]
+    lat: Option<f64>,
+
+    /// Observer longitude in degrees, east positive
+    #[arg(long, requires = "lat", allow_hyphen_values = true)]
+    lon: Option<f64>,
+
+    /// Observation time in UTC, e.g. "2024-01-15T03:00:00Z" (default: now)
+    #[arg(long, requires = "lat")]
+    time: Option<String>,
+
+    /// In observer mode, draw this many frames of a timelapse, each --frame-step-minutes later, to numbered files like sky-000.svg
+    #[arg(long, requires = "lat", value_parser = clap::value_parser!(u32).range(1..))]
+    frames: Option<u32>,
+
+    /// Minutes between timelapse frames
+    #[arg(
+        long,
+        default_value_t = 10.0,
+        requires = "frames",
+        allow_hyphen_values = true
+    )]
+    frame_step_minutes: f64,
+
+    /// In observer mode, rotate the chart so the zenith is up
+    #[arg(long, requires = "lat")]
+    rotate_to_horizon: bool,
 
-    /// Center Dec as degree:minute:second (e.g. "-5:23:28") or decimal degrees ("-5.391")
-    #[arg(long = "dec", alias = "center-dec")]
-    dec: String,
+    /// In observer mode, don't draw anything below the horizon
+    #[arg(long, requires = "lat")]
+    show_horizon_only: bool,
+
+    /// In observer mode, don't draw anything below this altitude in degrees (e.g. -6 for civil twilight)
+    #[arg(long, requires = "lat", allow_hyphen_values = true)]
+    min_altitude: Option<f64>,
+
+    /// In observer mode, shade the twilight band from 18° below the horizon up to it. Takes a stereographic chart
+    #[arg(long, requires = "lat")]
+    twilight_band: bool,
+
+    /// In observer mode, shade the civil (0° to -6°), nautical (-6° to -12°) and astronomical (-12° to -18°) twilight zones, each darker than the last. Takes a stereographic chart
+    #[arg(long, requires = "lat", conflicts_with = "twilight_band")]
+    twilight: bool,
+
+    /// Center on a constellation, by abbreviation (e.g. "ORI") or name, with a field of view that frames its figure
+    #[arg(long, conflicts_with_all = ["ra", "dec", "fov"])]
+    center_on_constellation: Option<String>,
 
     /// Field of view (in degrees)
     #[arg(long, default_value_t = 40.0)]
     fov: f64,
 
-    /// Type of projectionto draw, either gnomonic, stereographic, spherical, or altaz
+    /// Don't draw anything farther than this many degrees from the center, to bound a wide stereographic chart
+    #[arg(long)]
+    max_zenith: Option<f64>,
+
+    /// Page layout, either single or dual-hemisphere, the north and south skies side by side
+    #[arg(long, default_value = "single")]
+    layout: String,
+
+    /// Type of projectionto draw, either gnomonic, stereographic, spherical, altaz, or mercator
     #[arg(long, default_value = "gnomonic")]
     projection: String,
 
+    /// Print the available projection names, one per line, and exit
+    #[arg(long)]
+    list_projections: bool,
+
+    /// Print the position, magnitude, kind and size of a catalog object, such as "M31" or "NGC 7000", and exit
+    #[arg(long, conflicts_with = "out")]
+    catalog_info: Option<String>,
+
+    /// Print how much the projection stretches the sky at the edge of the field of view, compared with its center, and exit
+    #[arg(long, conflicts_with = "out")]
+    distortion_report: bool,
+
     /// Dimmest magnitude stars to draw
     #[arg(long, default_value_t = 6.5)]
     limit_star_mag: f64,
 
-    /// Dimmest magnitude objects to draw
-    #[arg(long, default_value_t = 10.0)]
-    limit_object_mag: f64,
+    /// Dimmest magnitude objects to draw, or "auto" to pick one from the field of view
+    #[arg(long, default_value = "10")]
+    limit_object_mag: String,
 
     /// Scale multiplier for object symbol size relative to its physical size and/or magnitude
     #[arg(long, default_value_t = 1.25)]
     object_scale: f64,
 
-    /// Output SVG path
-    #[arg(short = 'o', long = "out")]
-    out: String,
+    /// Objects fainter than this magnitude are drawn with a lighter, dashed style
+    #[arg(long)]
+    faint_object_mag: Option<f64>,
+
+    /// Leave out objects dimmer than this surface brightness, in magnitudes per square arcminute
+    #[arg(long)]
+    limit_surface_brightness: Option<f64>,
+
+    /// Leave out objects smaller than this across, in arcseconds
+    #[arg(long, value_name = "ARCSEC")]
+    min_object_arcsec: Option<f64>,
+
+    /// Draw Messier objects with a heavier outline so they stand out among the NGC and IC objects
+    #[arg(long)]
+    emphasize_messier: bool,
+
+    /// Draw a line along the major axis of markedly elongated galaxies, to show which way edge-on galaxies lie
+    #[arg(long)]
+    galaxy_axis_lines: bool,
+
+    /// Weight of the magnitude term in object symbol sizes
+    #[arg(long, default_value_t = 1.0)]
+    object_size_weight_mag: f64,
+
+    /// Weight of the physical-size term in object symbol sizes
+    #[arg(long, default_value_t = 0.3)]
+    object_size_weight_size: f64,
+
+    /// Output path; a .json extension writes the projected geometry as JSON, and .png a raster image, instead of SVG
+    #[arg(
+        short = 'o',
+        long = "out",
+        required_unless_present_any = ["list_projections", "catalog_info", "distortion_report", "output_dir"]
+    )]
+    out: Option<String>,
+
+    /// Write charts into this directory, named by --name-template, instead of to --out
+    #[arg(long, conflicts_with = "out")]
+    output_dir: Option<String>,
+
+    /// File name for charts in --output-dir, filled in from each chart's {ra}, {dec}, {fov}, {projection} and timelapse {frame}; the extension picks the format as with --out
+    #[arg(long, default_value = "{ra}_{dec}_{fov}.svg", requires = "output_dir")]
+    name_template: String,
+
+    /// Size multiplier for PNG output, for high-DPI images; the chart's layout is unchanged
+    #[arg(long = "dpi", visible_alias = "scale-factor", default_value = "1", value_parser = parse_scale_factor)]
+    dpi: f64,
+
+    /// Antialias PNG output by rendering this many times larger and averaging down to size
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=8))]
+    supersample: u32,
 
     /// Optional CSS override file path; if omitted embedded CSS is used
     #[arg(long)]
     css: Option<String>,
 
+    /// Also write the stylesheet the chart is drawn with to this path
+    #[arg(long)]
+    dump_css: Option<String>,
+
+    /// Also write a CSV of the deep-sky objects drawn, with each one's Messier, NGC and IC numbers, to this path
+    #[arg(long)]
+    crossmatch: Option<String>,
+
+    /// Report how much of the loaded catalogs falls on each chart, to catch a mis-centered chart
+    #[arg(short, long)]
+    verbose: bool,
+
     /// Output image width in pixels
     #[arg(long, default_value_t = 600)]
     width: u32,
@@ -67,79 +208,887 @@ struct Args {
     #[arg(long, default_value_t = 800)]
     height: u32,
 
-    /// RA gridlines step in degrees (e.g., 15)
-    #[arg(long, default_value_t = 15)]
-    step_ra_deg: u32,
+    /// Which way RA increases, either left (as seen on the sky) or right
+    #[arg(long, default_value = "left")]
+    ra_direction: String,
+
+    /// Which plot dimension the field of view spans: width, height, contain (the smaller; also "diameter"), or cover (the larger)
+    #[arg(long, visible_alias = "fov-axis", default_value = "contain")]
+    fit: String,
+
+    /// Shift the chart center off the middle of the plot, given as "dx,dy" in fractions of the plot's width and height, positive right and down
+    #[arg(long, allow_hyphen_values = true, value_parser = parse_center_offset)]
+    center_offset: Option<(f64, f64)>,
+
+    /// Shape of the plot area, either rect or circle
+    #[arg(long, default_value = "rect")]
+    clip: String,
+
+    /// Frame around the plot area, either outer (ticks and labels outside the border), inner (inside it), or none
+    #[arg(long, default_value = "outer")]
+    frame_style: String,
+
+    /// Debugging aid: draw everything inside the plot area without clipping it, to see what falls outside
+    #[arg(long)]
+    no_clip: bool,
+
+    /// RA gridlines step in degrees, fractions allowed (e.g., 15)
+    #[arg(long, alias = "grid-step-ra", default_value_t = 15.0, value_parser = parse_grid_step)]
+    step_ra_deg: f64,
+
+    /// Dec gridlines step in degrees, fractions allowed (e.g., 10 or 0.5)
+    #[arg(long, alias = "grid-step-dec", default_value_t = 10.0, value_parser = parse_grid_step)]
+    step_dec_deg: f64,
+
+    /// Split each RA and Dec gridline step into this many parts with lighter minor gridlines
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    minor_grid_divisions: u32,
+
+    /// Frame Dec tick spacing within a distance of the equator, given as "max_dec:step" or "max_dec:step:label_step" in degrees (e.g. "20:1:5"); may be repeated
+    #[arg(long, value_parser = parse_dec_tick_band)]
+    dec_tick_band: Vec<DecTickBand>,
+
+    /// Decimal places kept in SVG coordinates
+    #[arg(long, default_value_t = 2)]
+    coord_decimals: u32,
+
+    /// Sampling step in degrees for grid, frame, and ecliptic curves (default: based on FOV)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    sampling_step: Option<u32>,
+
+    /// Simplify grid, ecliptic and constellation lines, dropping points within this many pixels of the line without them, for smaller files (default: 0, keep every point)
+    #[arg(long, default_value_t = 0.0, value_parser = parse_simplify_tolerance)]
+    simplify_tolerance: f64,
+
+    /// Draw only the stars and objects that get labels
+    #[arg(long)]
+    only_labeled: bool,
+
+    /// Draw only these constellations, as comma-separated abbreviations (e.g. "ORI,TAU")
+    #[arg(long, value_delimiter = ',')]
+    constellations_only: Vec<String>,
+
+    /// Mark the point each constellation's name is centered on, for tuning their placement
+    #[arg(long)]
+    show_constellation_centroids: bool,
+
+    /// Font size of star and object labels in pixels, overriding the stylesheet's. Label sizes are estimated from it for placement
+    #[arg(long)]
+    label_font_size: Option<f64>,
+
+    /// Estimated label width per character in pixels (default 7, or 0.6 times --label-font-size)
+    #[arg(long)]
+    label_char_width: Option<f64>,
+
+    /// Estimated label height in pixels (default 12, or 1.2 times --label-font-size)
+    #[arg(long)]
+    label_line_height: Option<f64>,
+
+    /// Clearance kept between labels and symbols, in pixels
+    #[arg(long, default_value_t = 1.0)]
+    label_symbol_pad: f64,
+
+    /// Outline star and object labels in the background color, so they stay readable over lines
+    #[arg(long)]
+    label_halo: bool,
+
+    /// Follow a Messier object's label with its NGC or IC number, as in "M 42 (NGC 1976)"
+    #[arg(long)]
+    label_alt_catalog: bool,
+
+    /// Label only deep-sky objects in these catalogs, as comma-separated names (e.g. "M,NGC"); the rest are still drawn
+    #[arg(long, value_delimiter = ',')]
+    label_only_catalogs: Vec<String>,
+
+    /// Outline the symbol and label boxes that label placement avoids, and the boxes of the labels placed, for tuning placement
+    #[arg(long)]
+    label_collision_debug: bool,
+
+    /// Tilt star and object labels to follow the grid, square to the local direction of north
+    #[arg(long)]
+    align_labels_to_grid: bool,
+
+    /// Most star and object labels drawn in any one cell of a grid over the plot, to spread labels out
+    #[arg(long)]
+    labels_per_cell: Option<usize>,
+
+    /// Cells across each side of the grid that --labels-per-cell applies to
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    label_cells: u32,
+
+    /// Which labels are placed first, and so kept when they don't all fit: magnitude (brightest first), catalog (Messier, NGC, IC, then stars), or type (deep-sky objects, then stars)
+    #[arg(long, default_value = "magnitude")]
+    sort_labels_by: String,
+
+    /// Draw a soft halo behind the brightest stars
+    #[arg(long)]
+    star_glow: bool,
+
+    /// Shade the background by the number of stars fainter than --limit-star-mag in each small patch of sky, on a log scale, to show the Milky Way
+    #[arg(long)]
+    density_shading: bool,
+
+    /// How to draw stars under two pixels across, either circle or point (a one-pixel square that stays visible)
+    #[arg(long, default_value = "circle")]
+    faint_star_style: String,
+
+    /// Draw stars beneath object symbols rather than on top of them
+    #[arg(long)]
+    stars_under_objects: bool,
+
+    /// Draw the grid over the stars and objects, as a coordinate overlay, rather than under them
+    #[arg(long)]
+    grid_on_top: bool,
+
+    /// Where several stars land on the same pixel, draw only the brightest
+    #[arg(long)]
+    merge_coincident_stars: bool,
+
+    /// Draw double and multiple stars as two dots joined by a bar, as far apart as the components where the catalog gives their separation, or as one dot with a bar through it where it doesn't
+    #[arg(long)]
+    double_stars: bool,
+
+    /// Draw an arrow from each star to where its proper motion will carry it in this many years (e.g. 10000)
+    #[arg(long, value_name = "YEARS")]
+    proper_motion_arrows: Option<f64>,
+
+    /// Marker drawn at the chart center, either none, cross, dot, or circle
+    #[arg(long, default_value = "cross")]
+    center_marker: String,
+
+    /// Size of the center marker in pixels
+    #[arg(long, default_value_t = 10.0)]
+    center_marker_size: f64,
 
-    /// Dec gridlines step in degrees (e.g., 10)
-    #[arg(long, default_value_t = 10)]
-    step_dec_deg: u32,
+    /// Draw a zoomed-in inset in the bottom right corner, given as "ra,dec,fov"
+    #[arg(long, allow_hyphen_values = true)]
+    inset: Option<String>,
 
-    /// Optional path override for stars (HYG format expected)
+    /// Draw a great-circle arc labeled with its length, given as "ra1,dec1,ra2,dec2"; may be repeated
+    #[arg(long, allow_hyphen_values = true)]
+    arc: Vec<String>,
+
+    /// Mark a position with a cross and an optional label, given as "ra,dec" or "ra,dec,label"; may be repeated
+    #[arg(long, allow_hyphen_values = true)]
+    crosshair: Vec<String>,
+
+    /// Shade a polygon, such as a survey footprint, given as "ra1,dec1;ra2,dec2;..." or a file with one "ra,dec" per line; may be repeated
+    #[arg(long, allow_hyphen_values = true)]
+    region: Vec<String>,
+
+    /// Print the RA/Dec of each plot corner just outside the frame
     #[arg(long)]
+    corner_readouts: bool,
+
+    /// Crop the image to the symbols, grid and constellation lines, labels and inset drawn, leaving out the frame
+    #[arg(long)]
+    autocrop: bool,
+
+    /// Record the chart's settings (center, field of view, projection, limits and so on) as JSON in the SVG's metadata
+    #[arg(long)]
+    output_metadata: bool,
+
+    /// Refuse to write a chart with more than this many SVG elements, as a runaway wide-field chart can have, or JSON output listing more than this many stars, objects and lines
+    #[arg(long, default_value_t = 500_000)]
+    max_output_size: usize,
+
+    /// Only draw RA within "lo,hi" (HMS or degrees); wraps through 0h when lo > hi, and equal ends such as "0,360" take in the whole circle
+    #[arg(long, allow_hyphen_values = true)]
+    ra_range: Option<String>,
+
+    /// Only draw Dec within "lo,hi" (DMS or degrees)
+    #[arg(long, allow_hyphen_values = true)]
+    dec_range: Option<String>,
+
+    /// Optional path override for stars (in the --star-format format)
+    #[arg(long, alias = "stars-path")]
     hyg_path: Option<String>,
 
+    /// Star catalog format of the stars path, either hyg or bsc (Yale Bright Star Catalog)
+    #[arg(long, default_value = "hyg")]
+    star_format: String,
+
     /// Optional path override for deep-sky objects (OpenNGC format expected)
     #[arg(long)]
     ngc_path: Option<String>,
 
+    /// Supplementary deep-sky objects to add to the catalog, in the same format; they replace catalog entries with the same name or position
+    #[arg(long)]
+    extra_objects: Option<String>,
+
+    /// Keep deep-sky objects in catalog order rather than sorting them faintest first, which changes the order of JSON output but not how the chart is drawn
+    #[arg(long)]
+    no_sort_objects: bool,
+
+    /// Which catalogs to name deep-sky objects by, most preferred first, from M, NGC and IC; an object in several is labeled with the first
+    #[arg(long, default_value = "M,NGC,IC")]
+    catalog_priority: String,
+
     /// Optional path override for constellations vectors CSV
     #[arg(long)]
     constellations_path: Option<String>,
+
+    /// Snap constellation figure vertices onto the nearest drawn star within half a degree
+    #[arg(long)]
+    snap_figures: bool,
+
+    /// Constellation figure set, either modern (IAU/Sky & Telescope) or rey (H.A. Rey, only a sample of Cassiopeia and Leo so far, with modern figures for the rest)
+    #[arg(long, default_value = "modern", conflicts_with = "constellations_path")]
+    figures: String,
 }
 
-fn parse_ra_deg(s: &str) -> Result<f64> {
-    if s.contains(':') {
-        let (h, m, sec) = parse_hms(s).ok_or_else(|| anyhow!("bad RA HMS: {s}"))?;
-        let hours = sexagesimal_hms_to_hours(h, m, sec);
-        Ok(hours_to_degrees(hours).rem_euclid(360.0))
-    } else {
-        let deg: f64 = s.parse().context("RA must be HMS or degrees")?;
-        Ok(deg.rem_euclid(360.0))
+fn parse_range(s: &str, parse: impl Fn(&str) -> charter::error::Result<f64>) -> Result<(f64, f64)> {
+    let (lo, hi) = s
+        .split_once(',')
+        .ok_or_else(|| anyhow!("range must be \"lo,hi\": {s}"))?;
+    Ok((parse(lo.trim())?, parse(hi.trim())?))
+}
+
+// RAs wrap into [0, 360), so "0,360" reads as equal ends. A strip with no
+// width is no use, so equal ends take in the whole circle instead.
+fn parse_ra_range(s: &str, ra_unit: AngleUnit) -> Result<(f64, f64)> {
+    let (lo, hi) = parse_range(s, |ra| parse_ra_deg_in(ra, ra_unit))?;
+    Ok(if lo == hi { (lo, lo + 360.0) } else { (lo, hi) })
+}
+
+fn parse_dec_range(s: &str) -> Result<(f64, f64)> {
+    let (lo, hi) = parse_range(s, parse_dec_deg)?;
+    if lo > hi {
+        return Err(anyhow!("Dec range must be ascending: {s}"));
     }
+    Ok((lo, hi))
 }
 
-fn parse_dec_deg(s: &str) -> Result<f64> {
-    if s.contains(':') {
-        let (d, m, sec) = parse_dms(s).ok_or_else(|| anyhow!("bad Dec DMS: {s}"))?;
-        Ok(sexagesimal_dms_to_degrees(d, m, sec))
-    } else {
-        let deg: f64 = s.parse().context("Dec must be DMS or degrees")?;
-        Ok(deg)
+fn projection_names() -> Vec<&'static str> {
+    Projection::all().iter().map(|(_, p)| p.as_str()).collect()
+}
+
+// The number of single-character insertions, deletions and substitutions
+// that turn one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != cb));
+            diagonal = above;
+        }
     }
+    row[b.len()]
+}
+
+// The closest of the names to a misspelling, if any is close enough to be
+// what was meant
+fn suggest<'a>(s: &str, names: &[&'a str]) -> Option<&'a str> {
+    names
+        .iter()
+        .map(|&name| (edit_distance(s, name), name))
+        .filter(|&(d, name)| d <= (name.len() / 3).max(2))
+        .min_by_key(|&(d, _)| d)
+        .map(|(_, name)| name)
 }
 
 fn parse_projection(s: &str) -> Result<Projection> {
-    Projection::from_str(&s.to_lowercase()).ok_or_else(|| {
-        anyhow!("invalid projection '{s}'. Use: gnomonic | stereographic | spherical | altaz")
+    let lower = s.to_lowercase();
+    lower.parse::<Projection>().map_err(|_| {
+        let names = projection_names();
+        let hint = suggest(&lower, &names)
+            .map(|name| format!(" Did you mean '{name}'?"))
+            .unwrap_or_default();
+        anyhow!("invalid projection '{s}'.{hint} Use: {}", names.join(" | "))
+    })
+}
+
+fn parse_star_format(s: &str) -> Result<StarFormat> {
+    s.to_lowercase()
+        .parse::<StarFormat>()
+        .map_err(|_| anyhow!("invalid star format '{s}'. Use: hyg | bsc"))
+}
+
+fn parse_grid_step(s: &str) -> Result<f64> {
+    let step: f64 = s.parse().map_err(|_| anyhow!("bad grid step: {s}"))?;
+    if !(step > 0.0 && step <= 90.0) {
+        return Err(anyhow!(
+            "grid step must be greater than 0 and at most 90: {s}"
+        ));
+    }
+    Ok(step)
+}
+
+fn parse_simplify_tolerance(s: &str) -> Result<f64> {
+    let tolerance: f64 = s
+        .parse()
+        .map_err(|_| anyhow!("bad simplify tolerance: {s}"))?;
+    if !(tolerance >= 0.0 && tolerance.is_finite()) {
+        return Err(anyhow!("simplify tolerance must be 0 or more pixels: {s}"));
+    }
+    Ok(tolerance)
+}
+
+fn parse_dec_tick_band(s: &str) -> Result<DecTickBand> {
+    let bad = || anyhow!("bad Dec tick band '{s}'. Use: max_dec:step or max_dec:step:label_step");
+    let parts: Vec<&str> = s.split(':').map(str::trim).collect();
+    let (max, step, label_step) = match parts[..] {
+        [max, step] => (max, step, None),
+        [max, step, label_step] => (max, step, Some(label_step)),
+        _ => return Err(bad()),
+    };
+    let max_abs_dec_deg: f64 = max.parse().map_err(|_| bad())?;
+    if !(max_abs_dec_deg > 0.0 && max_abs_dec_deg <= 90.0) {
+        return Err(anyhow!(
+            "Dec tick band must reach greater than 0 and at most 90: {s}"
+        ));
+    }
+    Ok(DecTickBand {
+        max_abs_dec_deg,
+        step_deg: parse_grid_step(step)?,
+        label_step_deg: label_step.map(parse_grid_step).transpose()?,
+    })
+}
+
+fn parse_scale_factor(s: &str) -> Result<f64> {
+    let factor: f64 = s.parse().map_err(|_| anyhow!("bad scale factor: {s}"))?;
+    if !(factor > 0.0 && factor <= 16.0) {
+        return Err(anyhow!(
+            "scale factor must be greater than 0 and at most 16: {s}"
+        ));
+    }
+    Ok(factor)
+}
+
+// A magnitude limit, or None for "auto"
+fn parse_mag_limit(s: &str) -> Result<Option<f64>> {
+    if s.eq_ignore_ascii_case("auto") {
+        return Ok(None);
+    }
+    s.parse()
+        .map(Some)
+        .map_err(|_| anyhow!("invalid magnitude limit '{s}'. Use a number or auto"))
+}
+
+fn parse_center_offset(s: &str) -> Result<(f64, f64)> {
+    let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+    let [dx, dy] = parts[..] else {
+        return Err(anyhow!("bad center offset '{s}'. Use: dx,dy"));
+    };
+    let fraction = |v: &str| {
+        v.parse::<f64>()
+            .ok()
+            .filter(|f| (-0.5..=0.5).contains(f))
+            .ok_or_else(|| anyhow!("bad center offset '{v}'. Use a fraction from -0.5 to 0.5"))
+    };
+    Ok((fraction(dx)?, fraction(dy)?))
+}
+
+fn parse_arc(s: &str, ra_unit: AngleUnit) -> Result<Arc> {
+    let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+    let [ra1, dec1, ra2, dec2] = parts[..] else {
+        return Err(anyhow!("bad arc '{s}'. Use: ra1,dec1,ra2,dec2"));
+    };
+    Ok(Arc {
+        from: EQPoint::from_strings_in(ra1, dec1, ra_unit)?,
+        to: EQPoint::from_strings_in(ra2, dec2, ra_unit)?,
+    })
+}
+
+fn parse_crosshair(s: &str, ra_unit: AngleUnit) -> Result<Crosshair> {
+    // The label may itself contain commas
+    let parts: Vec<&str> = s.splitn(3, ',').map(str::trim).collect();
+    let (ra, dec, label) = match parts[..] {
+        [ra, dec] => (ra, dec, None),
+        [ra, dec, label] => (ra, dec, Some(label.to_string())),
+        _ => return Err(anyhow!("bad crosshair '{s}'. Use: ra,dec or ra,dec,label")),
+    };
+    Ok(Crosshair {
+        position: EQPoint::from_strings_in(ra, dec, ra_unit)?,
+        label,
+    })
+}
+
+// Vertices separated by semicolons, or a file with one vertex per line
+fn parse_region(s: &str, ra_unit: AngleUnit) -> Result<Region> {
+    let text = if !s.contains(';') && Path::new(s).is_file() {
+        fs::read_to_string(s).with_context(|| format!("reading region {s}"))?
+    } else {
+        s.replace(';', "\n")
+    };
+    let vertices = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|vertex| {
+            let (ra, dec) = vertex
+                .split_once(',')
+                .ok_or_else(|| anyhow!("bad region vertex '{vertex}'. Use: ra,dec"))?;
+            Ok(EQPoint::from_strings_in(ra.trim(), dec.trim(), ra_unit)?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if vertices.len() < 3 {
+        return Err(anyhow!(
+            "bad region '{s}'. Use at least three vertices: ra1,dec1;ra2,dec2;ra3,dec3"
+        ));
+    }
+    Ok(Region { vertices })
+}
+
+fn parse_inset(s: &str, ra_unit: AngleUnit) -> Result<Inset> {
+    let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+    let [ra, dec, fov] = parts[..] else {
+        return Err(anyhow!("bad inset '{s}'. Use: ra,dec,fov"));
+    };
+    let fov_deg: f64 = fov.parse().map_err(|_| anyhow!("bad inset FOV: {fov}"))?;
+    if !(fov_deg > 0.0 && fov_deg.is_finite()) {
+        return Err(anyhow!(
+            "bad inset FOV: {fov}. Use a positive number of degrees"
+        ));
+    }
+    Ok(Inset {
+        center: EQPoint::from_strings_in(ra, dec, ra_unit)?,
+        fov_deg,
     })
 }
 
+fn parse_ra_direction(s: &str) -> Result<RaDirection> {
+    s.to_lowercase()
+        .parse::<RaDirection>()
+        .map_err(|_| anyhow!("invalid RA direction '{s}'. Use: left | right"))
+}
+
+fn parse_ra_unit(s: &str) -> Result<AngleUnit> {
+    s.to_lowercase()
+        .parse::<AngleUnit>()
+        .map_err(|_| anyhow!("invalid RA unit '{s}'. Use: degrees | hours"))
+}
+
+fn parse_dec_unit(s: &str) -> Result<AngleUnit> {
+    match s.to_lowercase().parse::<AngleUnit>() {
+        Ok(AngleUnit::Degrees) => Ok(AngleUnit::Degrees),
+        _ => Err(anyhow!("invalid Dec unit '{s}'. Use: degrees")),
+    }
+}
+
+fn parse_label_sort(s: &str) -> Result<LabelSort> {
+    s.to_lowercase()
+        .parse::<LabelSort>()
+        .map_err(|_| anyhow!("invalid label sort '{s}'. Use: magnitude | catalog | type"))
+}
+
+fn parse_fit(s: &str) -> Result<Fit> {
+    s.to_lowercase()
+        .parse::<Fit>()
+        .map_err(|_| anyhow!("invalid fit '{s}'. Use: width | height | contain | diameter | cover"))
+}
+
+fn parse_page_layout(s: &str) -> Result<PageLayout> {
+    s.to_lowercase()
+        .parse::<PageLayout>()
+        .map_err(|_| anyhow!("invalid layout '{s}'. Use: single | dual-hemisphere"))
+}
+
+// A timelapse frame's number, zero-padded to at least three digits so the
+// files sort in order
+fn frame_number(frame: u32, frames: u32) -> String {
+    let width = (frames.max(1) - 1).to_string().len().max(3);
+    format!("{frame:0width$}")
+}
+
+// The numbered file for one timelapse frame, such as sky-007.svg for sky.svg
+fn frame_path(out: &str, frame: u32, frames: u32) -> String {
+    let number = frame_number(frame, frames);
+    let path = Path::new(out);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{stem}-{number}.{ext}"),
+        None => format!("{stem}-{number}"),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+// Where --crossmatch writes for one chart: into --output-dir with the charts
+// when it's given, and numbered like them for each timelapse frame
+fn crossmatch_path(
+    crossmatch: &str,
+    output_dir: Option<&str>,
+    frame: Option<(u32, u32)>,
+) -> String {
+    let name = match frame {
+        Some((frame, frames)) => frame_path(crossmatch, frame, frames),
+        None => crossmatch.to_string(),
+    };
+    match output_dir {
+        Some(dir) => Path::new(dir).join(name).to_string_lossy().into_owned(),
+        None => name,
+    }
+}
+
+// A chart's file name from --name-template, with each {placeholder} filled
+// in from its settings. Coordinates are spelled with letters rather than
+// colons or degree signs so the name is safe on any filesystem, as in
+// "05h35m17s_-05d23m28s_40.svg".
+fn expand_name_template(
+    template: &str,
+    cfg: &ChartConfig,
+    frame: u32,
+    frames: u32,
+) -> Result<String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        name += &rest[..open];
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed '{{' in name template '{template}'"))?;
+        let key = &rest[open + 1..open + close];
+        name += &match key {
+            "ra" => format_hms(cfg.center.ra_deg),
+            "dec" => format_dms(cfg.center.dec_deg)
+                .replace('°', "d")
+                .replace('′', "m")
+                .replace('″', "s"),
+            "fov" => cfg.fov_deg.to_string(),
+            "projection" => cfg.projection.to_string(),
+            "frame" => frame_number(frame, frames),
+            _ => {
+                return Err(anyhow!(
+                    "unknown placeholder '{{{key}}}' in name template. Use: {{ra}} | {{dec}} | {{fov}} | {{projection}} | {{frame}}"
+                ))
+            }
+        };
+        rest = &rest[open + close + 1..];
+    }
+    name += rest;
+    Ok(name)
+}
+
+fn parse_faint_star_style(s: &str) -> Result<FaintStarStyle> {
+    s.to_lowercase()
+        .parse::<FaintStarStyle>()
+        .map_err(|_| anyhow!("invalid faint star style '{s}'. Use: circle | point"))
+}
+
+fn parse_clip(s: &str) -> Result<ClipShape> {
+    s.to_lowercase()
+        .parse::<ClipShape>()
+        .map_err(|_| anyhow!("invalid clip shape '{s}'. Use: rect | circle"))
+}
+
+fn parse_frame_style(s: &str) -> Result<FrameStyle> {
+    s.to_lowercase()
+        .parse::<FrameStyle>()
+        .map_err(|_| anyhow!("invalid frame style '{s}'. Use: outer | inner | none"))
+}
+
+fn parse_catalog_priority(s: &str) -> Result<Vec<String>> {
+    let catalogs = default_catalog_priority();
+    let mut priority: Vec<String> = Vec::new();
+    for part in s.split(',') {
+        let catalog = part.trim().to_uppercase();
+        if !catalogs.contains(&catalog) {
+            return Err(anyhow!(
+                "invalid catalog '{}' in --catalog-priority. Use: {}",
+                part.trim(),
+                catalogs.join(" | ")
+            ));
+        }
+        if priority.contains(&catalog) {
+            return Err(anyhow!(
+                "catalog '{catalog}' is listed twice in --catalog-priority"
+            ));
+        }
+        priority.push(catalog);
+    }
+    Ok(priority)
+}
+
+fn parse_figures(s: &str) -> Result<Figures> {
+    s.to_lowercase()
+        .parse::<Figures>()
+        .map_err(|_| anyhow!("invalid figures '{s}'. Use: modern | rey"))
+}
+
+fn parse_center_marker(s: &str) -> Result<CenterMarker> {
+    s.to_lowercase()
+        .parse::<CenterMarker>()
+        .map_err(|_| anyhow!("invalid center marker '{s}'. Use: none | cross | dot | circle"))
+}
+
+// A catalog object's position, magnitude, kind and size, for --catalog-info
+fn catalog_info(o: &CelestialObject) -> String {
+    let EQPoint { ra_deg, dec_deg } = o.coords;
+    let mut info = format!("{} {} ({})\n", o.catalog, o.identifier, o.kind);
+    if !o.name.is_empty() {
+        info += &format!("Name: {}\n", o.name);
+    }
+    info += &format!("RA:   {} ({ra_deg:.4}°)\n", format_hms(ra_deg));
+    info += &format!("Dec:  {} ({dec_deg:.4}°)\n", format_dms(dec_deg));
+    info += &format!("Mag:  {:.2}\n", o.magnitude);
+    info += &format!("Size: {:.1}′ × {:.1}′\n", o.size.major, o.size.minor);
+    info
+}
+
+// Twilight lies below the horizon, more than 90° from the zenith, which only
+// the stereographic projection reaches. The others would draw nothing.
+fn check_twilight_projection(flag: &str, projection: Projection) -> Result<()> {
+    if projection == Projection::Stereographic {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{flag} takes a {} chart; the {projection} projection stops 90° from the center",
+        Projection::Stereographic
+    ))
+}
+
+// The projection's distortion at the edge of a field of view, relative to
+// its center, for --distortion-report
+fn distortion_report(projection: Projection, fov_deg: f64) -> Result<String> {
+    let edge_deg = fov_deg / 2.0;
+    if !within_projection(projection, edge_deg.to_radians()) {
+        return Err(anyhow!(
+            "a {fov_deg}° field of view reaches past the edge of the {projection} projection"
+        ));
+    }
+    let mut report = format!("Projection: {projection}, {fov_deg}° field of view\n");
+    let Some((radial, tangential)) = scale_factors(projection, edge_deg.to_radians()) else {
+        report += "Scale grows with declination away from the equator rather than with distance from the center\n";
+        return Ok(report);
+    };
+    report += &format!("At the edge, {edge_deg}° from the center:\n");
+    report += &format!("Radial scale:     {radial:.3}×\n");
+    report += &format!("Tangential scale: {tangential:.3}×\n");
+    report += &format!("Area:             {:.3}×\n", radial * tangential);
+    // Spherical squeezes its limb to nothing along the radius
+    let shape = radial.max(tangential) / radial.min(tangential);
+    if shape < 1e9 {
+        report += &format!("Shape:            {shape:.3}× (1 is conformal)\n");
+    } else {
+        report += "Shape:            ∞× (1 is conformal)\n";
+    }
+    Ok(report)
+}
+
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    let ra_deg = parse_ra_deg(&args.ra)?;
-    let dec_deg = parse_dec_deg(&args.dec)?;
-    let center = EQPoint { ra_deg, dec_deg };
+    if args.list_projections {
+        for name in projection_names() {
+            println!("{name}");
+        }
+        return Ok(());
+    }
     let projection = parse_projection(&args.projection)?;
+    if args.distortion_report {
+        print!("{}", distortion_report(projection, args.fov)?);
+        return Ok(());
+    }
+    if args.twilight_band {
+        check_twilight_projection("--twilight-band", projection)?;
+    }
+    if args.twilight {
+        check_twilight_projection("--twilight", projection)?;
+    }
+    let page_layout = parse_page_layout(&args.layout)?;
+    let ra_unit = parse_ra_unit(&args.ra_unit)?;
+    // Dec has no other unit to choose, so its unit is only checked
+    parse_dec_unit(&args.dec_unit)?;
+    let observer = match (args.lat, args.lon) {
+        (Some(lat_deg), Some(lon_deg)) => {
+            let jd = match &args.time {
+                Some(t) => parse_utc(t).ok_or_else(|| anyhow!("bad UTC time: {t}"))?,
+                None => now_jd(),
+            };
+            Some(Observer {
+                lat_deg,
+                lon_deg,
+                jd,
+            })
+        }
+        _ => None,
+    };
+
+    let order = if args.no_sort_objects {
+        ObjectOrder::Catalog
+    } else {
+        ObjectOrder::FaintestFirst
+    };
+    let catalog_priority = parse_catalog_priority(&args.catalog_priority)?;
+    let mut objects = load_objects(args.ngc_path.as_deref(), order, &catalog_priority)?;
+    if let Some(path) = &args.extra_objects {
+        let extra = load_objects(Some(path), order, &catalog_priority)
+            .with_context(|| format!("reading {path}"))?;
+        objects = merge_objects(objects, extra, order);
+    }
+    if let Some(query) = &args.catalog_info {
+        let o = find_object(&objects, query)?;
+        print!("{}", catalog_info(o));
+        return Ok(());
+    }
+    if args.out.is_none() && args.output_dir.is_none() {
+        return Err(anyhow!("--out or --output-dir is required"));
+    }
+    // With --output-dir, each chart's name comes from --name-template, whose
+    // extension picks the format
+    let out = args
+        .out
+        .clone()
+        .unwrap_or_else(|| args.name_template.clone());
+    let star_format = parse_star_format(&args.star_format)?;
+    let stars = load_stars(args.hyg_path.as_deref(), star_format)?;
+    let figures = parse_figures(&args.figures)?;
+    let mut constellations = load_constellations(args.constellations_path.as_deref(), figures)?;
+    if args.snap_figures {
+        snap_to_stars(&mut constellations, &stars, args.limit_star_mag, 0.5);
+    }
+
+    // An altaz chart for an observer is centered on their zenith unless
+    // another center is given
+    let (center, fov_deg) = match (&args.ra, &args.dec, observer) {
+        (Some(ra), Some(dec), _) => (EQPoint::from_strings_in(ra, dec, ra_unit)?, args.fov),
+        _ if let Some(name) = &args.center_on_constellation => {
+            let c = find_constellation(&constellations, name)?;
+            frame_constellation(c)
+        }
+        (None, None, Some(o)) if projection == Projection::AltAz => (o.zenith(), args.fov),
+        // Each hemisphere sets its own center
+        (None, None, _) if page_layout == PageLayout::DualHemisphere => {
+            let pole = EQPoint {
+                ra_deg: 0.0,
+                dec_deg: 90.0,
+            };
+            (pole, 180.0)
+        }
+        _ => {
+            return Err(anyhow!(
+                "--ra and --dec are required unless drawing an {} chart with --lat/--lon",
+                Projection::AltAz
+            ))
+        }
+    };
+    let position_angle_deg = match observer {
+        Some(o) if args.rotate_to_horizon => o.parallactic_angle_deg(center),
+        _ => 0.0,
+    };
 
-    let stars = load_stars(args.hyg_path.as_deref())?;
-    let objects = load_objects(args.ngc_path.as_deref())?;
-    let constellations = load_constellations(args.constellations_path.as_deref())?;
+    let ra_range = args
+        .ra_range
+        .as_deref()
+        .map(|s| parse_ra_range(s, ra_unit))
+        .transpose()?;
+    let dec_range = args.dec_range.as_deref().map(parse_dec_range).transpose()?;
 
     let cfg = ChartConfig {
         center,
-        position_angle_deg: 0.0,
+        position_angle_deg,
+        page_layout,
         projection,
-        fov_deg: args.fov,
+        fov_deg,
+        max_zenith_deg: args.max_zenith,
         width: args.width,
         height: args.height,
         margin: Margin::uniform(40),
+        ra_direction: parse_ra_direction(&args.ra_direction)?,
+        fit: parse_fit(&args.fit)?,
+        center_offset: args.center_offset.unwrap_or((0.0, 0.0)),
+        clip: parse_clip(&args.clip)?,
+        frame_style: parse_frame_style(&args.frame_style)?,
+        no_clip: args.no_clip,
         step_ra_deg: args.step_ra_deg,
         step_dec_deg: args.step_dec_deg,
+        minor_grid_divisions: args.minor_grid_divisions,
+        dec_tick_bands: args.dec_tick_band,
+        coord_decimals: args.coord_decimals,
+        sampling_step_deg: args.sampling_step,
+        simplify_tolerance: args.simplify_tolerance,
         limit_star_mag: args.limit_star_mag,
-        limit_object_mag: args.limit_object_mag,
+        limit_object_mag: parse_mag_limit(&args.limit_object_mag)?,
         object_scale: args.object_scale,
+        faint_object_mag: args.faint_object_mag,
+        limit_surface_brightness: args.limit_surface_brightness,
+        min_object_arcsec: args.min_object_arcsec,
+        emphasize_messier: args.emphasize_messier,
+        galaxy_axis_lines: args.galaxy_axis_lines,
+        object_weight_mag: args.object_size_weight_mag,
+        object_weight_size: args.object_size_weight_size,
+        observer,
+        horizon_only: args.show_horizon_only,
+        min_altitude_deg: args.min_altitude,
+        twilight_band: args.twilight_band,
+        twilight_zones: args.twilight,
+        only_labeled: args.only_labeled,
+        constellations_only: args
+            .constellations_only
+            .iter()
+            .map(|abbr| abbr.trim().to_string())
+            .filter(|abbr| !abbr.is_empty())
+            .collect(),
+        show_constellation_centroids: args.show_constellation_centroids,
+        label_font_size: args.label_font_size,
+        label_char_width: args
+            .label_char_width
+            .or(args.label_font_size.map(|px| LABEL_CHAR_WIDTH_PER_PX * px))
+            .unwrap_or(7.0),
+        label_line_height: args
+            .label_line_height
+            .or(args.label_font_size.map(|px| LABEL_LINE_HEIGHT_PER_PX * px))
+            .unwrap_or(12.0),
+        label_symbol_pad: args.label_symbol_pad,
+        label_halo: args.label_halo,
+        label_alt_catalog: args.label_alt_catalog,
+        label_only_catalogs: args
+            .label_only_catalogs
+            .iter()
+            .map(|catalog| catalog.trim().to_string())
+            .filter(|catalog| !catalog.is_empty())
+            .collect(),
+        catalog_priority,
+        label_collision_debug: args.label_collision_debug,
+        align_labels_to_grid: args.align_labels_to_grid,
+        labels_per_cell: args.labels_per_cell,
+        label_cells: args.label_cells,
+        sort_labels_by: parse_label_sort(&args.sort_labels_by)?,
+        star_glow: args.star_glow,
+        density_shading: args.density_shading,
+        faint_star_style: parse_faint_star_style(&args.faint_star_style)?,
+        stars_under_objects: args.stars_under_objects,
+        grid_on_top: args.grid_on_top,
+        merge_coincident_stars: args.merge_coincident_stars,
+        double_stars: args.double_stars,
+        proper_motion_years: args.proper_motion_arrows,
+        center_marker: parse_center_marker(&args.center_marker)?,
+        center_marker_size: args.center_marker_size,
+        inset: args
+            .inset
+            .as_deref()
+            .map(|s| parse_inset(s, ra_unit))
+            .transpose()?,
+        arcs: args
+            .arc
+            .iter()
+            .map(|s| parse_arc(s, ra_unit))
+            .collect::<Result<_>>()?,
+        crosshairs: args
+            .crosshair
+            .iter()
+            .map(|s| parse_crosshair(s, ra_unit))
+            .collect::<Result<_>>()?,
+        regions: args
+            .region
+            .iter()
+            .map(|s| parse_region(s, ra_unit))
+            .collect::<Result<_>>()?,
+        corner_readouts: args.corner_readouts,
+        autocrop: args.autocrop,
+        output_metadata: args.output_metadata,
+        max_output_size: args.max_output_size,
+        ra_range,
+        dec_range,
     };
 
     let data = Datasets {
@@ -148,76 +1097,141 @@ fn main() -> anyhow::Result<()> {
         constellations: &constellations,
     };
 
-    let chart = Chart::new(data, cfg, args.css);
-    chart
-        .to_file(&args.out)
-        .with_context(|| format!("writing {}", args.out))?;
+    if out.ends_with(".json") && page_layout == PageLayout::DualHemisphere {
+        return Err(anyhow!(
+            "JSON output isn't available for the dual-hemisphere layout"
+        ));
+    }
+
+    // A timelapse advances the observer's clock each frame, keeping an altaz
+    // chart on the zenith and a rotated one upright
+    let tracks_zenith = args.ra.is_none()
+        && args.center_on_constellation.is_none()
+        && projection == Projection::AltAz;
+    if let Some(dir) = &args.output_dir {
+        fs::create_dir_all(dir).with_context(|| format!("creating {dir}"))?;
+    }
+    let frames = args.frames.unwrap_or(1);
+    for frame in 0..frames {
+        let mut cfg = cfg.clone();
+        if let Some(o) = observer {
+            let o = o.after_minutes(frame as f64 * args.frame_step_minutes);
+            cfg.observer = Some(o);
+            if tracks_zenith {
+                cfg.center = o.zenith();
+            }
+            if args.rotate_to_horizon {
+                cfg.position_angle_deg = o.parallactic_angle_deg(cfg.center);
+            }
+        }
+        let chart = Chart::new(data, cfg, args.css.clone());
+        if frame == 0 {
+            let (css, css_source) = chart.load_css();
+            if let CssSource::Fallback { path, error } = &css_source {
+                eprintln!("warning: couldn't read {path} ({error}); using the embedded stylesheet");
+            }
+            if let Some(path) = &args.dump_css {
+                fs::write(path, css).with_context(|| format!("writing {path}"))?;
+            }
+        }
+
+        let path = if let Some(dir) = &args.output_dir {
+            let name =
+                expand_name_template(&args.name_template, &chart.context.cfg, frame, frames)?;
+            // Frames that would otherwise share a name are numbered
+            let name = if args.frames.is_some() && !args.name_template.contains("{frame}") {
+                frame_path(&name, frame, frames)
+            } else {
+                name
+            };
+            Path::new(dir).join(name).to_string_lossy().into_owned()
+        } else if args.frames.is_some() {
+            frame_path(&out, frame, frames)
+        } else {
+            out.clone()
+        };
+        if args.verbose {
+            let (stars, objects) = chart.context.visible_fraction();
+            eprintln!(
+                "{path}: {:.1}% of stars and {:.1}% of objects are on the chart",
+                stars * 100.0,
+                objects * 100.0
+            );
+        }
+        let written = if path.ends_with(".json") {
+            fs::write(&path, chart.to_json()?).map_err(ChartError::from)
+        } else if path.ends_with(".png") {
+            fs::write(&path, chart.to_png(args.dpi, args.supersample)?).map_err(ChartError::from)
+        } else {
+            chart.to_file(&path)
+        };
+        written.with_context(|| format!("writing {path}"))?;
+
+        if let Some(crossmatch) = &args.crossmatch {
+            let crossmatch = crossmatch_path(
+                crossmatch,
+                args.output_dir.as_deref(),
+                args.frames.map(|_| (frame, frames)),
+            );
+            fs::write(&crossmatch, chart.to_crossmatch_csv()?)
+                .with_context(|| format!("writing {crossmatch}"))?;
+        }
+    }
 
     Ok(())
 }
 
-#[cfg(test)]
-mod test_utils;
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::approx;
-
-    #[test]
-    fn ra_hms_parses_and_normalizes() {
-        // 5:35:17.3 -> 5.588138... hours -> 83.822083... deg
-        let ra = parse_ra_deg("5:35:17.3").unwrap();
-        assert!(approx(ra, 83.82208333333332, 1e-9));
+    use charter::types::parse_ra_deg;
 
-        // 24:00:00 -> 360 deg -> normalized to 0
-        let ra = parse_ra_deg("24:00:00").unwrap();
-        assert!(approx(ra, 0.0, 1e-12));
-
-        // Negative degrees should wrap into [0, 360)
-        let ra = parse_ra_deg("-30").unwrap();
-        assert!(approx(ra, 330.0, 1e-12));
-    }
-
-    #[test]
-    fn ra_degrees_parses_direct() {
-        let ra = parse_ra_deg("83.82208333333332").unwrap();
-        assert!(approx(ra, 83.82208333333332, 1e-12));
-
-        // >360 wraps
-        let ra = parse_ra_deg("720").unwrap();
-        assert!(approx(ra, 0.0, 1e-12));
+    // Check that the error between a and b is close enough
+    fn approx(a: f64, b: f64, eps: f64) -> bool {
+        (a - b).abs() <= eps
     }
 
     #[test]
-    fn ra_bad_input_errors() {
-        // Completely non-numeric degrees is an error
-        assert!(parse_ra_deg("not-a-number").is_err());
-        // Wrong HMS arity (needs exactly 3 fields)
-        assert!(parse_ra_deg("1:2").is_err());
-    }
-
-    #[test]
-    fn dec_dms_parses_with_sign() {
-        // -5:23:28 -> -5.391111... deg
-        let dec = parse_dec_deg("-5:23:28").unwrap();
-        assert!(approx(dec, -5.391111111111111, 1e-9));
+    fn ranges_parse_pairs_in_either_notation() {
+        let (lo, hi) = parse_range("23:20:00,0:40:00", parse_ra_deg).unwrap();
+        assert!(approx(lo, 350.0, 1e-9));
+        assert!(approx(hi, 10.0, 1e-9));
 
-        // +10:00:00 -> 10 deg
-        let dec = parse_dec_deg("+10:00:00").unwrap();
-        assert!(approx(dec, 10.0, 1e-12));
+        let (lo, hi) = parse_dec_range("-10, 5:30:00").unwrap();
+        assert!(approx(lo, -10.0, 1e-12));
+        assert!(approx(hi, 5.5, 1e-12));
     }
 
     #[test]
-    fn dec_degrees_parses_direct() {
-        let dec = parse_dec_deg("-5.3911111111").unwrap();
-        assert!(approx(dec, -5.3911111111, 1e-12));
+    fn ra_range_can_take_in_the_whole_circle() {
+        for s in ["0,360", "0:00:00,24:00:00"] {
+            let (lo, hi) = parse_ra_range(s, AngleUnit::Degrees).unwrap();
+            let p = |ra_deg| EQPoint {
+                ra_deg,
+                dec_deg: 0.0,
+            };
+            assert!(
+                [0.0, 90.0, 180.0, 359.9]
+                    .iter()
+                    .all(|&ra| p(ra).in_ra_range(lo, hi)),
+                "{s}"
+            );
+        }
+        assert_eq!(
+            parse_ra_range("0,24", AngleUnit::Hours).unwrap(),
+            (0.0, 360.0)
+        );
+        assert_eq!(
+            parse_ra_range("350,10", AngleUnit::Degrees).unwrap(),
+            (350.0, 10.0)
+        );
     }
 
     #[test]
-    fn dec_bad_input_errors() {
-        assert!(parse_dec_deg("bad").is_err());
-        assert!(parse_dec_deg("1:2").is_err()); // not DMS (needs 3 fields)
+    fn ranges_bad_input_errors() {
+        assert!(parse_range("10", parse_ra_deg).is_err());
+        assert!(parse_range("a,b", parse_ra_deg).is_err());
+        assert!(parse_dec_range("10,-10").is_err());
     }
 
     #[test]
@@ -238,10 +1252,322 @@ mod tests {
             parse_projection("AltAz").unwrap(),
             Projection::AltAz
         ));
+        assert!(matches!(
+            parse_projection("Mercator").unwrap(),
+            Projection::Mercator
+        ));
+    }
+
+    #[test]
+    fn inset_parses_sexagesimal_and_decimal() {
+        let inset = parse_inset("5:35:17, -5:23:28, 2", AngleUnit::Degrees).unwrap();
+        assert!(approx(inset.center.ra_deg, 83.820833, 1e-5));
+        assert!(approx(inset.center.dec_deg, -5.391111, 1e-5));
+        assert_eq!(inset.fov_deg, 2.0);
+        assert!(parse_inset("83.8,-5.4", AngleUnit::Degrees).is_err());
+        assert!(parse_inset("83.8,-5.4,wide", AngleUnit::Degrees).is_err());
+        assert!(parse_inset("83.8,-5.4,0", AngleUnit::Degrees).is_err());
+        assert!(parse_inset("83.8,-5.4,-2", AngleUnit::Degrees).is_err());
+    }
+
+    #[test]
+    fn arc_parses_two_points() {
+        let arc = parse_arc("5:35:17,-5:23:28, 88.79, 7.41", AngleUnit::Degrees).unwrap();
+        assert!(approx(arc.from.ra_deg, 83.820833, 1e-5));
+        assert!(approx(arc.to.dec_deg, 7.41, 1e-9));
+        assert!(parse_arc("83.8,-5.4,88.8", AngleUnit::Degrees).is_err());
+    }
+
+    #[test]
+    fn dec_tick_band_label_step_is_optional() {
+        let band = parse_dec_tick_band("20:1").unwrap();
+        assert_eq!(band.max_abs_dec_deg, 20.0);
+        assert_eq!(band.step_deg, 1.0);
+        assert_eq!(band.label_step_deg, None);
+        let band = parse_dec_tick_band("20: 0.5 :5").unwrap();
+        assert_eq!(band.step_deg, 0.5);
+        assert_eq!(band.label_step_deg, Some(5.0));
+        assert!(parse_dec_tick_band("20").is_err());
+        assert!(parse_dec_tick_band("100:1").is_err());
+        assert!(parse_dec_tick_band("20:0").is_err());
+    }
+
+    #[test]
+    fn crosshair_label_is_optional() {
+        let c = parse_crosshair("5:35:17,-5:23:28", AngleUnit::Degrees).unwrap();
+        assert!(approx(c.position.dec_deg, -5.391111, 1e-5));
+        assert_eq!(c.label, None);
+        let c = parse_crosshair("83.8, -5.4, ISS, 21:04", AngleUnit::Degrees).unwrap();
+        assert_eq!(c.label.as_deref(), Some("ISS, 21:04"));
+        assert!(parse_crosshair("83.8", AngleUnit::Degrees).is_err());
+    }
+
+    // The command line for a plain chart, with some more arguments
+    fn args(extra: &[&str]) -> Result<Args, clap::Error> {
+        let base = ["charter", "--ra", "0", "--dec", "0", "--out", "sky.svg"];
+        Args::try_parse_from(base.iter().chain(extra))
+    }
+
+    #[test]
+    fn figures_and_a_constellations_path_conflict() {
+        assert!(args(&["--constellations-path", "lines.csv"]).is_ok());
+        assert!(args(&["--constellations-path", "lines.csv", "--figures", "rey"]).is_err());
+    }
+
+    #[test]
+    fn horizon_only_needs_an_observer() {
+        assert!(args(&["--show-horizon-only"]).is_err());
+        assert!(args(&["--show-horizon-only", "--lat", "40", "--lon", "-75"]).is_ok());
+    }
+
+    #[test]
+    fn dec_unit_flag_only_takes_degrees() {
+        assert_eq!(parse_dec_unit("Degrees").unwrap(), AngleUnit::Degrees);
+        assert!(parse_dec_unit("hours").is_err());
+    }
+
+    #[test]
+    fn ra_unit_flag_reaches_every_coordinate_option() {
+        assert_eq!(parse_ra_unit("Hours").unwrap(), AngleUnit::Hours);
+        assert!(parse_ra_unit("radians").is_err());
+
+        let hours = AngleUnit::Hours;
+        let arc = parse_arc("5.58,0,6,10", hours).unwrap();
+        assert!(approx(arc.from.ra_deg, 83.7, 1e-9));
+        assert!(approx(arc.to.ra_deg, 90.0, 1e-9));
+        let c = parse_crosshair("5.58,0,ISS", hours).unwrap();
+        assert!(approx(c.position.ra_deg, 83.7, 1e-9));
+        let region = parse_region("1,0;2,0;3,10", hours).unwrap();
+        assert!(approx(region.vertices[2].ra_deg, 45.0, 1e-9));
+        let inset = parse_inset("5.58,0,2", hours).unwrap();
+        assert!(approx(inset.center.ra_deg, 83.7, 1e-9));
+    }
+
+    #[test]
+    fn fit_accepts_diameter_for_contain() {
+        assert_eq!(parse_fit("Diameter").unwrap(), Fit::Contain);
+        assert_eq!(parse_fit("width").unwrap(), Fit::Width);
+        assert!(parse_fit("horizontal").is_err());
+    }
+
+    #[test]
+    fn label_sort_parses_case_insensitively() {
+        assert_eq!(parse_label_sort("Catalog").unwrap(), LabelSort::Catalog);
+        assert!(parse_label_sort("brightness").is_err());
+    }
+
+    #[test]
+    fn frame_paths_number_before_the_extension() {
+        assert_eq!(frame_path("sky.svg", 7, 12), "sky-007.svg");
+        assert_eq!(frame_path("out/night.v2.png", 0, 2), "out/night.v2-000.png");
+        assert_eq!(frame_path("sky", 1234, 2000), "sky-1234");
+    }
+
+    #[test]
+    fn crossmatch_files_go_beside_the_charts() {
+        assert_eq!(crossmatch_path("objects.csv", None, None), "objects.csv");
+        assert_eq!(
+            crossmatch_path("objects.csv", Some("charts"), None),
+            "charts/objects.csv"
+        );
+        assert_eq!(
+            crossmatch_path("objects.csv", Some("charts"), Some((3, 10))),
+            "charts/objects-003.csv"
+        );
+    }
+
+    #[test]
+    fn name_templates_expand_to_filesystem_safe_names() {
+        let cfg = ChartConfig {
+            center: EQPoint::from_strings("05:35:17", "-5:23:28").unwrap(),
+            fov_deg: 2.5,
+            ..ChartConfig::default()
+        };
+        assert_eq!(
+            expand_name_template("{ra}_{dec}_{fov}.svg", &cfg, 0, 1).unwrap(),
+            "05h35m17s_-05d23m28s_2.5.svg"
+        );
+        assert_eq!(
+            expand_name_template("orion-{projection}-{frame}.png", &cfg, 7, 12).unwrap(),
+            "orion-gnomonic-007.png"
+        );
+        assert_eq!(
+            expand_name_template("chart.svg", &cfg, 0, 1).unwrap(),
+            "chart.svg"
+        );
+        assert!(expand_name_template("{ra}_{size}.svg", &cfg, 0, 1).is_err());
+        assert!(expand_name_template("{ra.svg", &cfg, 0, 1).is_err());
+    }
+
+    #[test]
+    fn faint_star_style_parses_case_insensitive() {
+        assert_eq!(
+            parse_faint_star_style("Point").unwrap(),
+            FaintStarStyle::Point
+        );
+        assert_eq!(
+            parse_faint_star_style("circle").unwrap(),
+            FaintStarStyle::Circle
+        );
+        assert!(parse_faint_star_style("dot").is_err());
+    }
+
+    #[test]
+    fn region_parses_inline_or_from_a_file() {
+        let region = parse_region(
+            "10:00:00,1:00:00; 150.5,-2 ;10:20:00,3:00:00",
+            AngleUnit::Degrees,
+        )
+        .unwrap();
+        assert_eq!(region.vertices.len(), 3);
+        assert_eq!(region.vertices[1].ra_deg, 150.5);
+        assert_eq!(region.vertices[1].dec_deg, -2.0);
+
+        let path = std::env::temp_dir().join(format!("charter-region-{}.txt", std::process::id()));
+        fs::write(&path, "10,1\n11,1\n\n11,2\n10,2\n").unwrap();
+        let region = parse_region(path.to_str().unwrap(), AngleUnit::Degrees).unwrap();
+        assert_eq!(region.vertices.len(), 4);
+        fs::remove_file(&path).unwrap();
+
+        assert!(parse_region("10,1;11,1", AngleUnit::Degrees).is_err());
+        assert!(parse_region("10,1;11;12,2", AngleUnit::Degrees).is_err());
+    }
+
+    #[test]
+    fn frame_style_parses_case_insensitive() {
+        assert_eq!(parse_frame_style("Inner").unwrap(), FrameStyle::Inner);
+        assert_eq!(parse_frame_style("none").unwrap(), FrameStyle::None);
+        assert!(parse_frame_style("inside").is_err());
+    }
+
+    #[test]
+    fn catalog_priority_parses_known_catalogs_in_order() {
+        assert_eq!(
+            parse_catalog_priority("ngc, M").unwrap(),
+            vec!["NGC".to_string(), "M".to_string()]
+        );
+        assert!(parse_catalog_priority("NGC,Caldwell").is_err());
+        assert!(parse_catalog_priority("M,NGC,m").is_err());
+        assert!(parse_catalog_priority("").is_err());
+    }
+
+    #[test]
+    fn center_offset_parses_fractions_within_the_plot() {
+        assert_eq!(parse_center_offset("0, 0.3").unwrap(), (0.0, 0.3));
+        assert_eq!(parse_center_offset("-0.5,0").unwrap(), (-0.5, 0.0));
+        assert!(parse_center_offset("0.6,0").is_err());
+        assert!(parse_center_offset("0.1").is_err());
+    }
+
+    #[test]
+    fn simplify_tolerance_is_zero_or_more_pixels() {
+        assert_eq!(parse_simplify_tolerance("0").unwrap(), 0.0);
+        assert_eq!(parse_simplify_tolerance("0.25").unwrap(), 0.25);
+        assert!(parse_simplify_tolerance("-1").is_err());
+        assert!(parse_simplify_tolerance("inf").is_err());
+        assert!(parse_simplify_tolerance("some").is_err());
+    }
+
+    #[test]
+    fn mag_limit_accepts_numbers_and_auto() {
+        assert_eq!(parse_mag_limit("12.5").unwrap(), Some(12.5));
+        assert_eq!(parse_mag_limit("Auto").unwrap(), None);
+        assert!(parse_mag_limit("faint").is_err());
+    }
+
+    #[test]
+    fn grid_step_accepts_fractions_only_when_positive() {
+        assert_eq!(parse_grid_step("0.5").unwrap(), 0.5);
+        assert!(parse_grid_step("0").is_err());
+        assert!(parse_grid_step("-1").is_err());
+        assert!(parse_grid_step("ten").is_err());
+    }
+
+    #[test]
+    fn twilight_shading_takes_a_stereographic_chart() {
+        for flag in ["--twilight-band", "--twilight"] {
+            assert!(check_twilight_projection(flag, Projection::Stereographic).is_ok());
+            let err = check_twilight_projection(flag, Projection::AltAz).unwrap_err();
+            assert!(err.to_string().starts_with(flag), "{err}");
+        }
+    }
+
+    #[test]
+    fn distortion_report_compares_the_edge_with_the_center() {
+        let report = distortion_report(Projection::Stereographic, 60.0).unwrap();
+        assert!(report.contains("30° from the center"));
+        assert!(report.contains("Area:             1.149×"));
+        assert!(report.contains("Shape:            1.000×"));
+        let report = distortion_report(Projection::Mercator, 60.0).unwrap();
+        assert!(report.contains("declination"));
+    }
+
+    #[test]
+    fn distortion_report_rejects_fields_past_the_projections_edge() {
+        assert!(distortion_report(Projection::Gnomonic, 179.0).is_ok());
+        assert!(distortion_report(Projection::Gnomonic, 180.0).is_err());
+        let limb = distortion_report(Projection::Spherical, 180.0).unwrap();
+        assert!(limb.contains("Shape:            ∞×"));
+        assert!(distortion_report(Projection::Spherical, 200.0).is_err());
+        assert!(distortion_report(Projection::AltAz, 359.0).is_ok());
+        assert!(distortion_report(Projection::AltAz, 360.0).is_err());
+        assert!(distortion_report(Projection::Mercator, 360.0).is_err());
+    }
+
+    #[test]
+    fn catalog_info_lists_position_magnitude_kind_and_size() {
+        let objects = vec![CelestialObject {
+            kind: "galaxy".to_string(),
+            catalog: "M".to_string(),
+            identifier: "31".to_string(),
+            coords: EQPoint {
+                ra_deg: 10.684708,
+                dec_deg: 41.2689,
+            },
+            magnitude: 3.44,
+            size: charter::types::Size {
+                major: 177.83,
+                minor: 69.66,
+            },
+            angle: 35.0,
+            name: String::new(),
+            alt_designation: None,
+            proper_motion: None,
+            multiplicity: None,
+        }];
+        let info = catalog_info(find_object(&objects, "m31").unwrap());
+        assert_eq!(
+            info.lines().collect::<Vec<_>>(),
+            vec![
+                "M 31 (galaxy)",
+                "RA:   00h42m44s (10.6847°)",
+                "Dec:  +41°16′08″ (41.2689°)",
+                "Mag:  3.44",
+                "Size: 177.8′ × 69.7′",
+            ]
+        );
+    }
+
+    #[test]
+    fn center_marker_parses_case_insensitive() {
+        assert_eq!(parse_center_marker("None").unwrap(), CenterMarker::None);
+        assert_eq!(parse_center_marker("dot").unwrap(), CenterMarker::Dot);
+        assert!(parse_center_marker("star").is_err());
     }
 
     #[test]
     fn projection_invalid_errors() {
-        assert!(parse_projection("unknown").is_err());
+        let err = parse_projection("unknown").unwrap_err().to_string();
+        assert!(!err.contains("Did you mean"), "{err}");
+    }
+
+    #[test]
+    fn projection_typos_suggest_the_closest_name() {
+        let err = parse_projection("stereografic").unwrap_err().to_string();
+        assert!(err.contains("Did you mean 'stereographic'?"), "{err}");
+        let err = parse_projection("Gnomic").unwrap_err().to_string();
+        assert!(err.contains("Did you mean 'gnomonic'?"), "{err}");
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "altaz"), 5);
     }
 }
@@ -0,0 +1,334 @@
+use serde::Serialize;
+
+use crate::types::EQPoint;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Julian date of the Unix epoch, 1970-01-01T00:00:00Z
+const JD_UNIX_EPOCH: f64 = 2440587.5;
+// Julian date of the J2000.0 epoch, 2000-01-01T12:00:00Z
+const JD_J2000: f64 = 2451545.0;
+
+/// An observer on the ground at an instant in time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Observer {
+    /// Geographic latitude, north positive
+    pub lat_deg: f64,
+    /// Geographic longitude, east positive
+    pub lon_deg: f64,
+    /// Julian date (UT)
+    pub jd: f64,
+}
+
+impl Observer {
+    /// Local sidereal time, in degrees
+    pub fn lst_deg(&self) -> f64 {
+        (gmst_deg(self.jd) + self.lon_deg).rem_euclid(360.0)
+    }
+
+    /// The same observer some minutes later
+    pub fn after_minutes(&self, minutes: f64) -> Self {
+        Self {
+            jd: self.jd + minutes / (24.0 * 60.0),
+            ..*self
+        }
+    }
+
+    /// The equatorial point directly overhead
+    pub fn zenith(&self) -> EQPoint {
+        EQPoint {
+            ra_deg: self.lst_deg(),
+            dec_deg: self.lat_deg,
+        }
+    }
+
+    /// Hour angle of a point, in degrees in [-180, 180), positive to the west
+    pub fn hour_angle_deg(&self, eq: EQPoint) -> f64 {
+        (self.lst_deg() - eq.ra_deg + 180.0).rem_euclid(360.0) - 180.0
+    }
+
+    /// Altitude above the horizon, in degrees
+    pub fn altitude_deg(&self, eq: EQPoint) -> f64 {
+        let ha = self.hour_angle_deg(eq).to_radians();
+        let dec = eq.dec_deg.to_radians();
+        let lat = self.lat_deg.to_radians();
+        let sin_alt = lat.sin() * dec.sin() + lat.cos() * dec.cos() * ha.cos();
+        sin_alt.clamp(-1.0, 1.0).asin().to_degrees()
+    }
+
+    /// Azimuth, in degrees in [0, 360) measured from north through east
+    pub fn azimuth_deg(&self, eq: EQPoint) -> f64 {
+        let ha = self.hour_angle_deg(eq).to_radians();
+        let dec = eq.dec_deg.to_radians();
+        let lat = self.lat_deg.to_radians();
+        let y = -ha.sin() * dec.cos();
+        let x = dec.sin() * lat.cos() - dec.cos() * ha.cos() * lat.sin();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    /// The equatorial point at an altitude and azimuth, the inverse of
+    /// `altitude_deg` and `azimuth_deg`
+    pub fn horizontal_to_eq(&self, alt_deg: f64, az_deg: f64) -> EQPoint {
+        let alt = alt_deg.to_radians();
+        let az = az_deg.to_radians();
+        let lat = self.lat_deg.to_radians();
+        let sin_dec = lat.sin() * alt.sin() + lat.cos() * alt.cos() * az.cos();
+        let ha =
+            (-az.sin() * alt.cos()).atan2(alt.sin() * lat.cos() - alt.cos() * az.cos() * lat.sin());
+        EQPoint {
+            ra_deg: (self.lst_deg() - ha.to_degrees()).rem_euclid(360.0),
+            dec_deg: sin_dec.clamp(-1.0, 1.0).asin().to_degrees(),
+        }
+    }
+
+    /// Parallactic angle of a point for this observer, in degrees
+    pub fn parallactic_angle_deg(&self, eq: EQPoint) -> f64 {
+        parallactic_angle(self.hour_angle_deg(eq), eq.dec_deg, self.lat_deg)
+    }
+}
+
+/// Parallactic angle, in degrees, from an hour angle, declination, and
+/// latitude (all in degrees). This is the position angle of the zenith as
+/// seen from the point, measured from north through east, so it's zero on
+/// the meridian and positive west of it (Meeus, equation 14.1).
+pub fn parallactic_angle(ha_deg: f64, dec_deg: f64, lat_deg: f64) -> f64 {
+    let ha = ha_deg.to_radians();
+    let dec = dec_deg.to_radians();
+    let lat = lat_deg.to_radians();
+    ha.sin()
+        .atan2(lat.tan() * dec.cos() - dec.sin() * ha.cos())
+        .to_degrees()
+}
+
+/// Julian date for a proleptic Gregorian UTC calendar date and time
+pub fn julian_date(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: f64) -> f64 {
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 12)
+    } else {
+        (year, month)
+    };
+    let a = (y as f64 / 100.0).floor();
+    let b = 2.0 - a + (a / 4.0).floor();
+    let day_frac = (hour as f64 + minute as f64 / 60.0 + second / 3600.0) / 24.0;
+    (365.25 * (y as f64 + 4716.0)).floor()
+        + (30.6001 * (m as f64 + 1.0)).floor()
+        + day as f64
+        + day_frac
+        + b
+        - 1524.5
+}
+
+/// Parse a UTC timestamp like "2024-01-15T03:00:00Z" (seconds and the "Z"
+/// are optional) into a Julian date.
+pub fn parse_utc(s: &str) -> Option<f64> {
+    let s = s.trim().trim_end_matches('Z');
+    let (date, time) = s.split_once(['T', ' ']).unwrap_or((s, "00:00"));
+
+    let mut d = date.splitn(3, '-');
+    let year: i32 = d.next()?.parse().ok()?;
+    let month: u32 = d.next()?.parse().ok()?;
+    let day: u32 = d.next()?.parse().ok()?;
+
+    let mut t = time.split(':');
+    let hour: u32 = t.next()?.parse().ok()?;
+    let minute: u32 = t.next()?.parse().ok()?;
+    let second: f64 = t.next().map(str::parse).transpose().ok()?.unwrap_or(0.0);
+    if t.next().is_some()
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || !(0.0..60.0).contains(&second)
+    {
+        return None;
+    }
+
+    Some(julian_date(year, month, day, hour, minute, second))
+}
+
+/// The Julian date right now
+pub fn now_jd() -> f64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    JD_UNIX_EPOCH + secs / 86400.0
+}
+
+/// Greenwich mean sidereal time, in degrees (IAU 1982 expression)
+pub fn gmst_deg(jd: f64) -> f64 {
+    let d = jd - JD_J2000;
+    let t = d / 36525.0;
+    (280.46061837 + 360.98564736629 * d + 0.000387933 * t * t - t * t * t / 38710000.0)
+        .rem_euclid(360.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::project;
+    use crate::test_utils::approx;
+    use crate::types::Projection;
+
+    #[test]
+    fn julian_date_matches_known_epochs() {
+        assert!(approx(julian_date(2000, 1, 1, 12, 0, 0.0), JD_J2000, 1e-9));
+        assert!(approx(
+            julian_date(1970, 1, 1, 0, 0, 0.0),
+            JD_UNIX_EPOCH,
+            1e-9
+        ));
+        // Meeus, Astronomical Algorithms, example 7.a
+        assert!(approx(
+            julian_date(1957, 10, 4, 19, 26, 24.0),
+            2436116.31,
+            1e-6
+        ));
+    }
+
+    #[test]
+    fn parse_utc_accepts_optional_seconds_and_zone() {
+        assert!(approx(
+            parse_utc("2000-01-01T12:00:00Z").unwrap(),
+            JD_J2000,
+            1e-9
+        ));
+        assert!(approx(
+            parse_utc("2000-01-01 12:00").unwrap(),
+            JD_J2000,
+            1e-9
+        ));
+        assert!(approx(
+            parse_utc("2000-01-01").unwrap(),
+            JD_J2000 - 0.5,
+            1e-9
+        ));
+        assert!(parse_utc("2000-13-01T00:00").is_none());
+        assert!(parse_utc("yesterday").is_none());
+        assert!(parse_utc("2000-01-01T12:00:00:00").is_none());
+    }
+
+    #[test]
+    fn gmst_at_known_instants() {
+        assert!(approx(gmst_deg(JD_J2000), 280.46061837, 1e-9));
+        // Meeus example 12.a: 1987-04-10 0h UT → 13h10m46.3668s
+        let gmst = gmst_deg(julian_date(1987, 4, 10, 0, 0, 0.0));
+        assert!(approx(gmst, 197.693195, 1e-5));
+    }
+
+    #[test]
+    fn zenith_is_at_altitude_90() {
+        let o = Observer {
+            lat_deg: 40.0,
+            lon_deg: -75.0,
+            jd: parse_utc("2024-01-15T03:00:00Z").unwrap(),
+        };
+        assert!(approx(o.altitude_deg(o.zenith()), 90.0, 1e-6));
+        // The celestial pole sits at the observer's latitude, due north
+        let pole = EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 90.0,
+        };
+        assert!(approx(o.altitude_deg(pole), 40.0, 1e-9));
+    }
+
+    #[test]
+    fn azimuth_runs_north_through_east() {
+        let o = Observer {
+            lat_deg: 40.0,
+            lon_deg: 0.0,
+            jd: JD_J2000,
+        };
+        let lst = o.lst_deg();
+        // On the meridian south of the zenith → due south
+        let south = EQPoint {
+            ra_deg: lst,
+            dec_deg: 0.0,
+        };
+        assert!(approx(o.azimuth_deg(south), 180.0, 1e-9));
+        // Celestial equator six hours east of the meridian rises due east
+        let east = EQPoint {
+            ra_deg: lst + 90.0,
+            dec_deg: 0.0,
+        };
+        assert!(approx(o.azimuth_deg(east), 90.0, 1e-9));
+        assert!(approx(o.altitude_deg(east), 0.0, 1e-9));
+    }
+
+    #[test]
+    fn advancing_sidereal_time_turns_the_sky_about_the_pole() {
+        // From the pole, the sky turns about the zenith, so azimuths and
+        // the bearing on an altaz chart follow sidereal time exactly
+        let o = Observer {
+            lat_deg: 90.0,
+            lon_deg: 0.0,
+            jd: JD_J2000,
+        };
+        // 15° of sidereal time, a little under an hour of clock time
+        let later = o.after_minutes(15.0 / 360.98564736629 * 24.0 * 60.0);
+        assert!(approx(
+            (later.lst_deg() - o.lst_deg()).rem_euclid(360.0),
+            15.0,
+            1e-6
+        ));
+
+        let star = EQPoint {
+            ra_deg: 100.0,
+            dec_deg: 60.0,
+        };
+        let turn = |a: f64, b: f64| (b - a).rem_euclid(360.0);
+        assert!(approx(
+            turn(o.azimuth_deg(star), later.azimuth_deg(star)),
+            15.0,
+            1e-6
+        ));
+        let bearing = |o: &Observer| {
+            let p = project(star, o.zenith(), Projection::AltAz, 0.0).unwrap();
+            p.x.atan2(p.y).to_degrees()
+        };
+        assert!(approx(turn(bearing(&later), bearing(&o)), 15.0, 1e-6));
+    }
+
+    #[test]
+    fn horizontal_to_eq_inverts_altitude_and_azimuth() {
+        let o = Observer {
+            lat_deg: -33.0,
+            lon_deg: 151.0,
+            jd: parse_utc("2024-06-01T12:00:00Z").unwrap(),
+        };
+        for (alt, az) in [(3.0, 90.0), (45.0, 200.0), (-18.0, 315.0), (80.0, 10.0)] {
+            let eq = o.horizontal_to_eq(alt, az);
+            assert!(approx(o.altitude_deg(eq), alt, 1e-9));
+            assert!(approx(o.azimuth_deg(eq), az, 1e-9));
+        }
+    }
+
+    #[test]
+    fn parallactic_angle_is_zero_on_the_meridian_and_signed_by_side() {
+        assert!(approx(parallactic_angle(0.0, 20.0, 40.0), 0.0, 1e-12));
+        assert!(parallactic_angle(30.0, 20.0, 40.0) > 0.0);
+        assert!(parallactic_angle(-30.0, 20.0, 40.0) < 0.0);
+        // On the meridian north of the zenith, the zenith lies due south
+        assert!(approx(
+            parallactic_angle(0.0, 80.0, 40.0).abs(),
+            180.0,
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn rotating_by_the_parallactic_angle_puts_the_zenith_up() {
+        let o = Observer {
+            lat_deg: 40.0,
+            lon_deg: -75.0,
+            jd: parse_utc("2024-01-15T04:00:00Z").unwrap(),
+        };
+        let target = EQPoint {
+            ra_deg: o.lst_deg() - 40.0,
+            dec_deg: 10.0,
+        };
+        let pa = o.parallactic_angle_deg(target);
+        let up = project(o.zenith(), target, Projection::Stereographic, pa).unwrap();
+        assert!(approx(up.x, 0.0, 1e-9));
+        assert!(up.y > 0.0);
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use resvg::tiny_skia::{Color, Pixmap, Transform};
+use resvg::usvg::fontdb::{Database, Family, Query};
+use resvg::usvg::{Options, Tree};
+
+use crate::error::{ChartError, Result};
+
+// The stylesheet asks for Verdana or Arial, falling back to sans-serif. Those
+// fonts are often missing on Linux, so sans-serif maps to the first of these
+// that's installed.
+const SANS_SERIF_FAMILIES: [&str; 5] = [
+    "Arial",
+    "Helvetica",
+    "DejaVu Sans",
+    "Liberation Sans",
+    "Noto Sans",
+];
+
+// The rasterizer doesn't understand CSS custom properties, so the stylesheet's
+// `var(--name)` references are replaced with their values before rendering.
+// The first definition of each property wins, which is the light scheme in the
+// default stylesheet; the `prefers-color-scheme` overrides come after it.
+pub(crate) fn css_vars(css: &str) -> HashMap<&str, &str> {
+    let mut vars = HashMap::new();
+    for decl in css.split([';', '{', '}']) {
+        // Drop any comments ahead of the declaration
+        let decl = decl.rsplit("*/").next().unwrap_or(decl);
+        if let Some((name, value)) = decl.trim().split_once(':')
+            && name.trim_end().starts_with("--")
+        {
+            vars.entry(name.trim_end()).or_insert(value.trim());
+        }
+    }
+    vars
+}
+
+pub(crate) fn resolve_css_vars(css: &str, vars: &HashMap<&str, &str>) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("var(") {
+        let Some(len) = rest[start..].find(')') else {
+            break;
+        };
+        let name = rest[start + 4..start + len].trim();
+        out.push_str(&rest[..start]);
+        match vars.get(name) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Parse a CSS hex color like #fff or #231f20
+fn hex_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#')?;
+    let channel = |i: usize, n: usize| {
+        let v = u8::from_str_radix(hex.get(i * n..(i + 1) * n)?, 16).ok()?;
+        Some(if n == 1 { v * 17 } else { v })
+    };
+    let n = match hex.len() {
+        3 => 1,
+        6 => 2,
+        _ => return None,
+    };
+    Some(Color::from_rgba8(
+        channel(0, n)?,
+        channel(1, n)?,
+        channel(2, n)?,
+        255,
+    ))
+}
+
+fn set_sans_serif_family(fontdb: &mut Database) {
+    let installed = SANS_SERIF_FAMILIES.into_iter().find(|&name| {
+        let query = Query {
+            families: &[Family::Name(name)],
+            ..Default::default()
+        };
+        fontdb.query(&query).is_some()
+    });
+    if let Some(name) = installed {
+        fontdb.set_sans_serif_family(name);
+    }
+}
+
+// Shrink an image by a whole factor, averaging each square block of pixels
+// into one. The pixels are premultiplied, so plain averages blend edges
+// correctly.
+fn downsample(src: &Pixmap, factor: u32) -> Option<Pixmap> {
+    let (width, height) = (src.width() / factor, src.height() / factor);
+    let mut out = Pixmap::new(width, height)?;
+    let data = src.data();
+    let area = factor * factor;
+    for (i, pixel) in out.data_mut().chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let mut sums = [0u32; 4];
+        for sy in y * factor..(y + 1) * factor {
+            for sx in x * factor..(x + 1) * factor {
+                let at = ((sy * src.width() + sx) * 4) as usize;
+                for (sum, &v) in sums.iter_mut().zip(&data[at..at + 4]) {
+                    *sum += u32::from(v);
+                }
+            }
+        }
+        for (v, sum) in pixel.iter_mut().zip(sums) {
+            *v = ((sum + area / 2) / area) as u8;
+        }
+    }
+    Some(out)
+}
+
+/// Rasterize an SVG document to PNG bytes. The image is `scale_factor` times
+/// the document's size; the drawing is scaled up to match, so coordinates in
+/// the SVG are unchanged. `background` fills the image first when given.
+/// With a `supersample` above 1 the drawing is rendered that many times
+/// larger again and averaged down, smoothing thin lines and small dots.
+pub(crate) fn render_png(
+    svg: &str,
+    scale_factor: f64,
+    supersample: u32,
+    background: Option<&str>,
+) -> Result<Vec<u8>> {
+    rasterize(svg, scale_factor, supersample, background)?
+        .encode_png()
+        .map_err(|e| ChartError::Render(format!("can't encode the chart as PNG: {e}")))
+}
+
+fn rasterize(
+    svg: &str,
+    scale_factor: f64,
+    supersample: u32,
+    background: Option<&str>,
+) -> Result<Pixmap> {
+    let mut opt = Options::default();
+    let fontdb = opt.fontdb_mut();
+    fontdb.load_system_fonts();
+    set_sans_serif_family(fontdb);
+    let tree = Tree::from_str(svg, &opt)
+        .map_err(|e| ChartError::Parse(format!("can't read the chart's SVG: {e}")))?;
+
+    let size = tree.size();
+    let factor = scale_factor as f32;
+    let width = (size.width() * factor).round() as u32;
+    let height = (size.height() * factor).round() as u32;
+    let n = supersample.max(1);
+    let (big_width, big_height) = (width * n, height * n);
+    let mut pixmap = Pixmap::new(big_width, big_height).ok_or_else(|| {
+        ChartError::InvalidConfig(format!("can't make a {big_width}x{big_height} image"))
+    })?;
+    if let Some(color) = background.and_then(hex_color) {
+        pixmap.fill(color);
+    }
+    let factor = factor * n as f32;
+    resvg::render(
+        &tree,
+        Transform::from_scale(factor, factor),
+        &mut pixmap.as_mut(),
+    );
+    if n == 1 {
+        return Ok(pixmap);
+    }
+    downsample(&pixmap, n)
+        .ok_or_else(|| ChartError::InvalidConfig(format!("can't make a {width}x{height} image")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn css_vars_resolve_to_their_first_definition() {
+        let css = ":root { /* neutrals */ --ink: #231f20; }\n\
+                   @media (prefers-color-scheme: dark) { :root { --ink: #a0dcdd; } }\n\
+                   .tick { stroke: var(--ink); fill: var(--missing); }";
+        let resolved = resolve_css_vars(css, &css_vars(css));
+        assert!(resolved.contains("stroke: #231f20;"));
+        assert!(resolved.contains("fill: var(--missing);"));
+    }
+
+    #[test]
+    fn supersampling_smooths_edges() {
+        // Without antialiasing the line is all or nothing at every pixel
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+            <line x1="0" y1="3" x2="20" y2="17" stroke="black" shape-rendering="crispEdges"/>
+            </svg>"#;
+        let partial = |supersample: u32| {
+            let pixmap = rasterize(svg, 1.0, supersample, None).unwrap();
+            assert_eq!((pixmap.width(), pixmap.height()), (20, 20));
+            pixmap
+                .pixels()
+                .iter()
+                .filter(|p| p.alpha() != 0 && p.alpha() != 255)
+                .count()
+        };
+        assert_eq!(partial(1), 0);
+        assert!(partial(4) > 10);
+    }
+
+    #[test]
+    fn downsampling_averages_blocks() {
+        let mut src = Pixmap::new(4, 2).unwrap();
+        // Opaque black in the left block's top row only
+        for i in [0, 1] {
+            src.data_mut()[i * 4 + 3] = 255;
+        }
+        let out = downsample(&src, 2).unwrap();
+        assert_eq!((out.width(), out.height()), (2, 1));
+        assert_eq!(out.pixels()[0].alpha(), 128);
+        assert_eq!(out.pixels()[1].alpha(), 0);
+    }
+
+    #[test]
+    fn hex_colors_parse() {
+        assert_eq!(hex_color("#fff"), Some(Color::WHITE));
+        assert_eq!(hex_color("#000000"), Some(Color::BLACK));
+        assert_eq!(hex_color("white"), None);
+    }
+}
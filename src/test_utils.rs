@@ -1,6 +1,6 @@
 use crate::config::ChartConfig;
 use crate::context::{ChartContext, Datasets};
-use crate::types::EQPoint;
+use crate::types::{CelestialObject, EQPoint, Size};
 
 // Check that the error between a and b is close enough
 pub fn approx(a: f64, b: f64, eps: f64) -> bool {
@@ -8,16 +8,105 @@ pub fn approx(a: f64, b: f64, eps: f64) -> bool {
 }
 
 pub fn make_context(patch: impl FnOnce(&mut ChartConfig)) -> ChartContext<'static> {
-    let mut cfg = ChartConfig::default();
-    cfg.center = EQPoint {
-        ra_deg: 0.0,
-        dec_deg: 0.0,
-    };
-    patch(&mut cfg);
     let data = Datasets {
         stars: &[],
         objects: &[],
         constellations: &[],
     };
+    make_context_with(data, patch)
+}
+
+// Same as `make_context`, but drawing from the given datasets
+pub fn make_context_with<'a>(
+    data: Datasets<'a>,
+    patch: impl FnOnce(&mut ChartConfig),
+) -> ChartContext<'a> {
+    let mut cfg = ChartConfig {
+        center: EQPoint {
+            ra_deg: 0.0,
+            dec_deg: 0.0,
+        },
+        ..Default::default()
+    };
+    patch(&mut cfg);
     ChartContext::new(data, cfg)
 }
+
+// Same as `make_context`, but drawing only the given objects
+pub fn objects_context<'a>(
+    objects: &'a [CelestialObject],
+    patch: impl FnOnce(&mut ChartConfig),
+) -> ChartContext<'a> {
+    let data = Datasets {
+        stars: &[],
+        objects,
+        constellations: &[],
+    };
+    make_context_with(data, patch)
+}
+
+pub fn make_star(ra_deg: f64, dec_deg: f64, magnitude: f64) -> CelestialObject {
+    CelestialObject {
+        kind: "star".to_string(),
+        catalog: "HIP".to_string(),
+        identifier: "1".to_string(),
+        coords: EQPoint { ra_deg, dec_deg },
+        magnitude,
+        size: Size::zero(),
+        angle: 0.0,
+        name: String::new(),
+        alt_designation: None,
+        proper_motion: None,
+        multiplicity: None,
+    }
+}
+
+pub fn make_object(
+    kind: &str,
+    ra_deg: f64,
+    dec_deg: f64,
+    magnitude: f64,
+    major_arcmin: f64,
+) -> CelestialObject {
+    CelestialObject {
+        kind: kind.to_string(),
+        catalog: "NGC".to_string(),
+        identifier: "1".to_string(),
+        coords: EQPoint { ra_deg, dec_deg },
+        magnitude,
+        size: Size {
+            major: major_arcmin,
+            minor: major_arcmin,
+        },
+        angle: 0.0,
+        name: String::new(),
+        alt_designation: None,
+        proper_motion: None,
+        multiplicity: None,
+    }
+}
+
+// Equally bright 2' galaxies at these RA/Dec positions, numbered NGC 100
+// onward so their labels differ
+pub fn make_objects(positions: impl IntoIterator<Item = (f64, f64)>) -> Vec<CelestialObject> {
+    positions
+        .into_iter()
+        .enumerate()
+        .map(|(i, (ra_deg, dec_deg))| {
+            let mut o = make_object("galaxy", ra_deg, dec_deg, 7.0, 2.0);
+            o.identifier = format!("{}", 100 + i);
+            o
+        })
+        .collect()
+}
+
+// Collect every numeric value of the attribute `name` in rendered SVG text
+pub fn attr_values(svg: &str, name: &str) -> Vec<f64> {
+    let needle = format!(" {name}=\"");
+    svg.match_indices(&needle)
+        .filter_map(|(i, _)| {
+            let rest = &svg[i + needle.len()..];
+            rest[..rest.find('"')?].parse().ok()
+        })
+        .collect()
+}
@@ -1,15 +1,120 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use serde::Serialize;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+use crate::error::{ChartError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl Point {
+    /// Straight-line distance to another point
+    pub fn distance(&self, other: Point) -> f64 {
+        let d = other - *self;
+        d.x.hypot(d.y)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+    fn add(self, other: Point) -> Point {
+        Point {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+    fn sub(self, other: Point) -> Point {
+        Point {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl Mul<f64> for Point {
+    type Output = Point;
+    fn mul(self, k: f64) -> Point {
+        Point {
+            x: self.x * k,
+            y: self.y * k,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub struct EQPoint {
     pub ra_deg: f64,
     pub dec_deg: f64,
 }
 
+impl EQPoint {
+    /// Parse an RA and Dec, each either sexagesimal ("5:35:17.3",
+    /// "-5:23:28") or decimal degrees. RA wraps into [0, 360).
+    pub fn from_strings(ra: &str, dec: &str) -> Result<EQPoint> {
+        Self::from_strings_in(ra, dec, AngleUnit::Degrees)
+    }
+
+    /// Like `from_strings`, but a bare RA number is read in `ra_unit`
+    pub fn from_strings_in(ra: &str, dec: &str, ra_unit: AngleUnit) -> Result<EQPoint> {
+        Ok(EQPoint {
+            ra_deg: parse_ra_deg_in(ra, ra_unit)?,
+            dec_deg: parse_dec_deg(dec)?,
+        })
+    }
+
+    /// The same point with RA wrapped into [0, 360). Dec is left alone, so
+    /// an out-of-range one still fails `is_valid`
+    pub fn with_wrapped_ra(self) -> EQPoint {
+        let ra_deg = self.ra_deg.rem_euclid(360.0);
+        EQPoint {
+            // A tiny negative RA wraps to 360 itself after rounding
+            ra_deg: if ra_deg >= 360.0 { 0.0 } else { ra_deg },
+            dec_deg: self.dec_deg,
+        }
+    }
+
+    /// Whether the point is in canonical form, with finite coordinates
+    pub fn is_valid(&self) -> bool {
+        (0.0..360.0).contains(&self.ra_deg) && (-90.0..=90.0).contains(&self.dec_deg)
+    }
+
+    /// Angular separation from another point, in degrees
+    pub fn separation_deg(&self, other: EQPoint) -> f64 {
+        let (d1, d2) = (self.dec_deg.to_radians(), other.dec_deg.to_radians());
+        let d_ra = (other.ra_deg - self.ra_deg).to_radians();
+        // Haversine, which stays accurate for small separations
+        let h = ((d2 - d1) / 2.0).sin().powi(2) + d1.cos() * d2.cos() * (d_ra / 2.0).sin().powi(2);
+        2.0 * h.sqrt().min(1.0).asin().to_degrees()
+    }
+
+    /// Whether the declination lies within `[lo, hi]`, inclusive
+    pub fn in_dec_range(&self, lo: f64, hi: f64) -> bool {
+        self.dec_deg >= lo && self.dec_deg <= hi
+    }
+
+    /// Whether the RA lies within `[lo, hi]`, inclusive. When `lo > hi` the
+    /// range wraps through 0°, so `(350, 10)` covers 350°–360° and 0°–10°.
+    pub fn in_ra_range(&self, lo: f64, hi: f64) -> bool {
+        if hi - lo >= 360.0 {
+            return true;
+        }
+        let ra = self.ra_deg.rem_euclid(360.0);
+        let (lo, hi) = (lo.rem_euclid(360.0), hi.rem_euclid(360.0));
+        if lo <= hi {
+            ra >= lo && ra <= hi
+        } else {
+            ra >= lo || ra <= hi
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Size {
     pub major: f64,
@@ -23,9 +128,65 @@ impl Size {
             minor: 0.0,
         }
     }
+
+    /// Minor over major axis, from near 0 for an edge-on galaxy to 1 for a
+    /// round object. Without a major axis there's no shape to speak of, so
+    /// it's 1.
+    pub fn aspect_ratio(&self) -> f64 {
+        if self.major <= 0.0 {
+            return 1.0;
+        }
+        (self.minor / self.major).clamp(0.0, 1.0)
+    }
+}
+
+impl CelestialObject {
+    /// The text an object is labeled with: its proper name, or failing that
+    /// its catalog and identifier ("M 42"), or empty with none of them
+    pub fn display_label(&self) -> String {
+        if !self.name.is_empty() {
+            return self.name.clone();
+        }
+        [self.catalog.as_str(), self.identifier.as_str()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The object's identifier in `catalog`, such as "1976" in "NGC" for
+    /// M 42, from either its own designation or its alternate one. Catalog
+    /// names match ignoring case.
+    pub fn designation_in(&self, catalog: &str) -> Option<&str> {
+        if self.catalog.eq_ignore_ascii_case(catalog) {
+            return Some(&self.identifier);
+        }
+        let (alt_catalog, id) = self.alt_designation.as_deref()?.split_once(' ')?;
+        alt_catalog.eq_ignore_ascii_case(catalog).then_some(id)
+    }
+
+    /// Size across the sky in degrees, along the major axis
+    pub fn angular_size_deg(&self) -> f64 {
+        self.size.major / 60.0
+    }
+
+    /// Mean surface brightness in magnitudes per square arcminute, spreading
+    /// the magnitude over the ellipse of the object's axes. An object with no
+    /// minor axis is taken as round; with no size at all it's None.
+    pub fn surface_brightness(&self) -> Option<f64> {
+        let Size { major, minor } = self.size;
+        if major <= 0.0 {
+            return None;
+        }
+        let minor = if minor > 0.0 { minor } else { major };
+        let area = std::f64::consts::PI / 4.0 * major * minor;
+        Some(self.magnitude + 2.5 * area.log10())
+    }
 }
 
 pub struct Constellation {
+    /// IAU three-letter abbreviation, e.g. "ORI"
+    pub abbr: String,
     pub name: String,
     pub lines: Vec<Vec<EQPoint>>,
 }
@@ -40,24 +201,150 @@ pub struct CelestialObject {
     pub size: Size,
     pub angle: f64,
     pub name: String,
+    /// The object's designation in a second catalog, such as "NGC 1976" for
+    /// M 42
+    pub alt_designation: Option<String>,
+    /// Yearly motion across the sky, for stars whose catalog gives it
+    pub proper_motion: Option<ProperMotion>,
+    /// What the catalog says of a double or multiple star, for stars it
+    /// lists as one
+    pub multiplicity: Option<Multiplicity>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A double or multiple star's components, as far as its catalog gives them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Multiplicity {
+    /// How many components there are
+    pub components: Option<u32>,
+    /// How far apart the components are, in arcseconds
+    pub separation_arcsec: Option<f64>,
+}
+
+/// A star's proper motion in milliarcseconds per year. The RA component is
+/// measured on the sky, already multiplied by cos(Dec).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProperMotion {
+    pub ra_mas_yr: f64,
+    pub dec_mas_yr: f64,
+}
+
+impl ProperMotion {
+    /// Where a star at `coords` will be after `years` of this motion, taking
+    /// it as a straight line in RA and Dec. A star carried over a pole comes
+    /// down the other side, 180° away in RA.
+    pub fn position_after(&self, coords: EQPoint, years: f64) -> EQPoint {
+        let mas_to_deg = years / 3_600_000.0;
+        // RA degrees widen toward the poles
+        let cos_dec = coords.dec_deg.to_radians().cos().max(1e-9);
+        let mut ra_deg = coords.ra_deg + self.ra_mas_yr * mas_to_deg / cos_dec;
+        let mut dec_deg = coords.dec_deg + self.dec_mas_yr * mas_to_deg;
+        if dec_deg.abs() > 90.0 {
+            dec_deg = (180.0 - dec_deg.abs()).copysign(dec_deg);
+            ra_deg += 180.0;
+        }
+        EQPoint { ra_deg, dec_deg }.with_wrapped_ra()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Projection {
     Gnomonic,
     Stereographic,
+    /// The orthographic projection: the sky as a globe seen from far away,
+    /// a point `z` from the center landing `sin z` out. Only the hemisphere
+    /// facing the viewer is drawn, out to its limb 90° from the center,
+    /// which a field of view of 180° fills.
     Spherical,
     AltAz,
+    Mercator,
 }
 
 impl Projection {
-    pub fn from_str(s: &str) -> Option<Self> {
+    /// Every projection with its command-line name
+    pub fn all() -> &'static [(&'static str, Projection)] {
+        const fn named(p: Projection) -> (&'static str, Projection) {
+            (p.as_str(), p)
+        }
+        const ALL: [(&str, Projection); 5] = [
+            named(Projection::Gnomonic),
+            named(Projection::Stereographic),
+            named(Projection::Spherical),
+            named(Projection::AltAz),
+            named(Projection::Mercator),
+        ];
+        &ALL
+    }
+
+    /// The command-line name, which `from_str` parses back
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gnomonic => "gnomonic",
+            Self::Stereographic => "stereographic",
+            Self::Spherical => "spherical",
+            Self::AltAz => "altaz",
+            Self::Mercator => "mercator",
+        }
+    }
+}
+
+impl FromStr for Projection {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::all()
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|&(_, projection)| projection)
+            .ok_or_else(|| ChartError::Parse(format!("unknown projection '{s}'")))
+    }
+}
+
+impl std::fmt::Display for Projection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Which way RA increases across the chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RaDirection {
+    /// East to the left, as on the sky seen from the ground
+    Left,
+    /// East to the right, as in some atlases
+    Right,
+}
+
+impl FromStr for RaDirection {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
         match s {
-            "gnomonic" => Some(Self::Gnomonic),
-            "stereographic" => Some(Self::Stereographic),
-            "spherical" => Some(Self::Spherical),
-            "altaz" => Some(Self::AltAz),
-            _ => None,
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            _ => Err(ChartError::Parse(format!("unknown RA direction '{s}'"))),
+        }
+    }
+}
+
+/// How a bare number given for a coordinate is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AngleUnit {
+    /// Hours of RA, 15° each
+    Hours,
+    /// Degrees
+    Degrees,
+}
+
+impl FromStr for AngleUnit {
+    type Err = ChartError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hours" => Ok(Self::Hours),
+            "degrees" => Ok(Self::Degrees),
+            _ => Err(ChartError::Parse(format!("unknown angle unit '{s}'"))),
         }
     }
 }
@@ -81,6 +368,29 @@ pub fn sexagesimal_dms_to_degrees(d: f64, m: f64, s: f64) -> f64 {
     sign * (ad + (m * 60.0 + s) / 3600.0)
 }
 
+/// Format RA degrees as hours, minutes and seconds, e.g. "05h35m17s"
+pub fn format_hms(ra_deg: f64) -> String {
+    let total = (ra_deg.rem_euclid(360.0) / 15.0 * 3600.0).round() as u32 % (24 * 3600);
+    format!(
+        "{:02}h{:02}m{:02}s",
+        total / 3600,
+        total / 60 % 60,
+        total % 60
+    )
+}
+
+/// Format Dec degrees as signed degrees, arcminutes and arcseconds, e.g. "-05°23′28″"
+pub fn format_dms(dec_deg: f64) -> String {
+    let sign = if dec_deg < 0.0 { '-' } else { '+' };
+    let total = (dec_deg.abs() * 3600.0).round() as u32;
+    format!(
+        "{sign}{:02}°{:02}′{:02}″",
+        total / 3600,
+        total / 60 % 60,
+        total % 60
+    )
+}
+
 pub fn parse_hms(s: &str) -> Option<(f64, f64, f64)> {
     let parts: Vec<&str> = s.split(':').collect();
     if parts.len() != 3 {
@@ -105,30 +415,297 @@ pub fn parse_dms(s: &str) -> Option<(f64, f64, f64)> {
     ))
 }
 
+// Unit suffixes for each sexagesimal component, as typed or as printed in
+// catalogs and by `format_hms`/`format_dms`
+const HMS_UNITS: [&[char]; 3] = [&['h', 'ʰ'], &['m', 'ᵐ'], &['s', 'ˢ']];
+const DMS_UNITS: [&[char]; 3] = [&['d', '°'], &['m', '\'', '′'], &['s', '"', '″']];
+
+/// Parse sexagesimal components each marked by a unit suffix, such as
+/// "5h35m17.3s" or "-5°23′28″". Components may be separated by spaces and
+/// trailing ones left off ("5h35m"), but they must come in order, each with
+/// its suffix, and only the first may carry a sign.
+fn parse_suffixed(s: &str, units: [&[char]; 3]) -> Option<(f64, f64, f64)> {
+    let mut values = [0.0; 3];
+    let mut next = 0;
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let signed = next == 0;
+        let end = rest
+            .char_indices()
+            .find(|&(i, c)| {
+                !(c.is_ascii_digit() || c == '.' || (signed && i == 0 && "+-".contains(c)))
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        let value: f64 = rest[..end].parse().ok()?;
+        let unit = rest[end..].chars().next()?;
+        let slot = (next..3).find(|&i| units[i].contains(&unit))?;
+        values[slot] = value;
+        next = slot + 1;
+        rest = rest[end + unit.len_utf8()..].trim_start();
+    }
+    Some((values[0], values[1], values[2]))
+}
+
+/// Parse an RA given as "HH:MM:SS", "5h35m17.3s" or decimal degrees, wrapped
+/// into [0, 360)
+pub fn parse_ra_deg(s: &str) -> Result<f64> {
+    let hms = if s.contains(':') {
+        parse_hms(s).ok_or_else(|| ChartError::Parse(format!("bad RA HMS: {s}")))?
+    } else if let Ok(deg) = s.trim().parse::<f64>() {
+        return Ok(deg.rem_euclid(360.0));
+    } else {
+        parse_suffixed(s, HMS_UNITS)
+            .ok_or_else(|| ChartError::Parse(format!("bad RA: {s}. RA must be HMS or degrees")))?
+    };
+    let (h, m, sec) = hms;
+    Ok(hours_to_degrees(sexagesimal_hms_to_hours(h, m, sec)).rem_euclid(360.0))
+}
+
+/// Like `parse_ra_deg`, but a bare number is read in `unit`, so "5.58" can
+/// mean 5.58h. Sexagesimal and suffixed forms carry their own units
+pub fn parse_ra_deg_in(s: &str, unit: AngleUnit) -> Result<f64> {
+    match (unit, s.trim().parse::<f64>()) {
+        (AngleUnit::Hours, Ok(hours)) => Ok(hours_to_degrees(hours).rem_euclid(360.0)),
+        _ => parse_ra_deg(s),
+    }
+}
+
+/// Parse a Dec given as "±DD:MM:SS", "-5°23′28″" or decimal degrees,
+/// rejecting any past the poles
+pub fn parse_dec_deg(s: &str) -> Result<f64> {
+    let dec = if s.contains(':') {
+        let (d, m, sec) =
+            parse_dms(s).ok_or_else(|| ChartError::Parse(format!("bad Dec DMS: {s}")))?;
+        sexagesimal_dms_to_degrees(d, m, sec)
+    } else if let Ok(deg) = s.trim().parse::<f64>() {
+        deg
+    } else {
+        let (d, m, sec) = parse_suffixed(s, DMS_UNITS).ok_or_else(|| {
+            ChartError::Parse(format!("bad Dec: {s}. Dec must be DMS or degrees"))
+        })?;
+        sexagesimal_dms_to_degrees(d, m, sec)
+    };
+    if !(-90.0..=90.0).contains(&dec) {
+        return Err(ChartError::Parse(format!(
+            "bad Dec: {s}. Dec must be between -90° and 90°"
+        )));
+    }
+    Ok(dec)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::approx;
+    use crate::test_utils::{approx, make_object, make_star};
+
+    #[test]
+    fn angular_size_converts_arcminutes_to_degrees() {
+        let mut galaxy = make_star(0.0, 0.0, 10.0);
+        assert_eq!(galaxy.angular_size_deg(), 0.0);
+        galaxy.size = Size {
+            major: 90.0,
+            minor: 30.0,
+        };
+        assert!(approx(galaxy.angular_size_deg(), 1.5, 1e-12));
+    }
+
+    #[test]
+    fn surface_brightness_spreads_magnitude_over_the_ellipse() {
+        let mut galaxy = make_star(0.0, 0.0, 10.0);
+        assert_eq!(galaxy.surface_brightness(), None);
+
+        // A round object 2/sqrt(pi)' across covers one square arcminute
+        let one = 2.0 / std::f64::consts::PI.sqrt();
+        galaxy.size = Size {
+            major: one,
+            minor: 0.0,
+        };
+        assert!(approx(galaxy.surface_brightness().unwrap(), 10.0, 1e-9));
+        // A hundred times the area is five magnitudes dimmer
+        galaxy.size = Size {
+            major: 10.0 * one,
+            minor: 10.0 * one,
+        };
+        assert!(approx(galaxy.surface_brightness().unwrap(), 15.0, 1e-9));
+    }
+
+    #[test]
+    fn ra_wraps_into_one_turn() {
+        let p = |ra_deg, dec_deg| EQPoint { ra_deg, dec_deg };
+        assert_eq!(p(-30.0, 0.0).with_wrapped_ra(), p(330.0, 0.0));
+        assert_eq!(p(360.0, 0.0).with_wrapped_ra(), p(0.0, 0.0));
+        assert_eq!(p(725.0, 0.0).with_wrapped_ra(), p(5.0, 0.0));
+        assert_eq!(p(-1e-20, 0.0).with_wrapped_ra(), p(0.0, 0.0));
+        assert!(p(-30.0, 0.0).with_wrapped_ra().is_valid());
+        assert!(!p(-30.0, 0.0).is_valid());
+        assert!(!p(360.0, 0.0).is_valid());
+    }
+
+    #[test]
+    fn wrapping_ra_leaves_dec_for_is_valid_to_reject() {
+        let p = |ra_deg, dec_deg| EQPoint { ra_deg, dec_deg };
+        assert_eq!(p(-10.0, -95.0).with_wrapped_ra(), p(350.0, -95.0));
+        assert_eq!(p(10.0, 45.0).with_wrapped_ra(), p(10.0, 45.0));
+        assert!(p(10.0, -90.0).is_valid());
+        assert!(!p(10.0, 91.0).with_wrapped_ra().is_valid());
+        assert!(!p(10.0, 90.000001).with_wrapped_ra().is_valid());
+        // Missing values stay invalid
+        assert!(!p(f64::NAN, 0.0).with_wrapped_ra().is_valid());
+        assert!(!p(0.0, f64::NAN).with_wrapped_ra().is_valid());
+    }
+
+    #[test]
+    fn points_add_subtract_and_scale() {
+        let a = Point { x: 1.0, y: 2.0 };
+        let b = Point { x: 4.0, y: -2.0 };
+        assert_eq!(a + b, Point { x: 5.0, y: 0.0 });
+        assert_eq!(b - a, Point { x: 3.0, y: -4.0 });
+        assert_eq!(a * 2.5, Point { x: 2.5, y: 5.0 });
+        assert_eq!(a.distance(b), 5.0);
+        assert_eq!(b.distance(a), 5.0);
+        assert_eq!(a.distance(a), 0.0);
+    }
+
+    #[test]
+    fn designations_come_from_either_catalog() {
+        let mut m42 = make_object("bright-nebula", 83.8, -5.4, 4.0, 90.0);
+        m42.catalog = "M".to_string();
+        m42.identifier = "42".to_string();
+        m42.alt_designation = Some("NGC 1976".to_string());
+        assert_eq!(m42.designation_in("M"), Some("42"));
+        assert_eq!(m42.designation_in("NGC"), Some("1976"));
+        assert_eq!(m42.designation_in("IC"), None);
+        m42.alt_designation = None;
+        assert_eq!(m42.designation_in("NGC"), None);
+    }
+
+    #[test]
+    fn display_label_prefers_the_proper_name() {
+        let mut star = make_star(0.0, 0.0, 1.0);
+        star.name = "Betelgeuse".to_string();
+        assert_eq!(star.display_label(), "Betelgeuse");
+    }
+
+    #[test]
+    fn display_label_falls_back_to_catalog_and_identifier() {
+        let star = make_star(0.0, 0.0, 1.0);
+        assert_eq!(star.display_label(), "HIP 1");
+        let mut star = make_star(0.0, 0.0, 1.0);
+        star.catalog.clear();
+        assert_eq!(star.display_label(), "1");
+    }
+
+    #[test]
+    fn display_label_is_empty_without_a_name_or_identifier() {
+        let mut star = make_star(0.0, 0.0, 1.0);
+        star.catalog.clear();
+        star.identifier.clear();
+        assert_eq!(star.display_label(), "");
+    }
+
+    #[test]
+    fn aspect_ratio_guards_missing_axes() {
+        let size = |major, minor| Size { major, minor };
+        assert!(approx(size(16.0, 1.9).aspect_ratio(), 0.11875, 1e-9));
+        assert_eq!(size(10.0, 10.0).aspect_ratio(), 1.0);
+        assert_eq!(Size::zero().aspect_ratio(), 1.0);
+        // A minor axis over the major is bad data, not a wider-than-long shape
+        assert_eq!(size(5.0, 8.0).aspect_ratio(), 1.0);
+    }
+
+    #[test]
+    fn all_projections_cover_every_variant() {
+        // Adding a variant breaks this match, as a reminder to add it to all()
+        let index = |p: Projection| match p {
+            Projection::Gnomonic => 0,
+            Projection::Stereographic => 1,
+            Projection::Spherical => 2,
+            Projection::AltAz => 3,
+            Projection::Mercator => 4,
+        };
+        let mut seen: Vec<usize> = Projection::all().iter().map(|&(_, p)| index(p)).collect();
+        seen.sort();
+        assert_eq!(seen, vec![0, 1, 2, 3, 4]);
+
+        for &(name, p) in Projection::all() {
+            assert_eq!(Projection::from_str(name).ok(), Some(p));
+        }
+    }
+
+    #[test]
+    fn projection_names_round_trip() {
+        for &(name, p) in Projection::all() {
+            assert_eq!(p.as_str(), name);
+            assert_eq!(Projection::from_str(p.as_str()).ok(), Some(p));
+            assert_eq!(p.to_string(), name);
+        }
+    }
 
     #[test]
     fn projection_from_str_recognizes_known_values() {
-        assert_eq!(Projection::from_str("gnomonic"), Some(Projection::Gnomonic));
         assert_eq!(
-            Projection::from_str("stereographic"),
+            Projection::from_str("gnomonic").ok(),
+            Some(Projection::Gnomonic)
+        );
+        assert_eq!(
+            Projection::from_str("stereographic").ok(),
             Some(Projection::Stereographic)
         );
         assert_eq!(
-            Projection::from_str("spherical"),
+            Projection::from_str("spherical").ok(),
             Some(Projection::Spherical)
         );
-        assert_eq!(Projection::from_str("altaz"), Some(Projection::AltAz));
+        assert_eq!(Projection::from_str("altaz").ok(), Some(Projection::AltAz));
+        assert_eq!(
+            Projection::from_str("mercator").ok(),
+            Some(Projection::Mercator)
+        );
     }
 
     #[test]
     fn projection_from_str_is_case_sensitive_and_handles_unknown() {
-        assert_eq!(Projection::from_str("Gnomonic"), None);
-        assert_eq!(Projection::from_str("unknown"), None);
-        assert_eq!(Projection::from_str(""), None);
+        assert_eq!(Projection::from_str("Gnomonic").ok(), None);
+        assert_eq!(Projection::from_str("unknown").ok(), None);
+        assert_eq!(Projection::from_str("").ok(), None);
+    }
+
+    #[test]
+    fn dec_range_is_inclusive() {
+        let p = |dec_deg| EQPoint {
+            ra_deg: 0.0,
+            dec_deg,
+        };
+        assert!(p(-10.0).in_dec_range(-10.0, 10.0));
+        assert!(p(10.0).in_dec_range(-10.0, 10.0));
+        assert!(p(0.0).in_dec_range(-10.0, 10.0));
+        assert!(!p(10.001).in_dec_range(-10.0, 10.0));
+        assert!(!p(-45.0).in_dec_range(-10.0, 10.0));
+    }
+
+    #[test]
+    fn ra_range_plain_and_wrapping() {
+        let p = |ra_deg| EQPoint {
+            ra_deg,
+            dec_deg: 0.0,
+        };
+        // Plain band
+        assert!(p(60.0).in_ra_range(30.0, 90.0));
+        assert!(p(30.0).in_ra_range(30.0, 90.0));
+        assert!(!p(120.0).in_ra_range(30.0, 90.0));
+
+        // Wrapping band 350..10 covers both sides of 0°
+        assert!(p(355.0).in_ra_range(350.0, 10.0));
+        assert!(p(0.0).in_ra_range(350.0, 10.0));
+        assert!(p(10.0).in_ra_range(350.0, 10.0));
+        assert!(p(-5.0).in_ra_range(350.0, 10.0)); // -5 ≡ 355
+        assert!(!p(180.0).in_ra_range(350.0, 10.0));
+        assert!(!p(11.0).in_ra_range(350.0, 10.0));
+
+        // A full turn keeps everything
+        assert!(p(180.0).in_ra_range(0.0, 360.0));
     }
 
     #[test]
@@ -144,6 +721,8 @@ mod tests {
     }
 
     #[test]
+    // Any float would do; this one only happens to look like π
+    #[allow(clippy::approx_constant)]
     fn parse_or_parses_floats_and_defaults_on_error() {
         let v: f64 = parse_or("3.14159", 0.0);
         assert!(approx(v, 3.14159, 1e-12));
@@ -204,6 +783,16 @@ mod tests {
         assert!(approx(result, -0.5, 1e-12));
     }
 
+    #[test]
+    fn format_hms_and_dms_round_and_carry() {
+        assert_eq!(format_hms(83.82208333333332), "05h35m17s");
+        assert_eq!(format_hms(359.99999), "00h00m00s"); // carries past 24h
+        assert_eq!(format_hms(-15.0), "23h00m00s");
+        assert_eq!(format_dms(-5.391111111111111), "-05°23′28″");
+        assert_eq!(format_dms(10.5), "+10°30′00″");
+        assert_eq!(format_dms(29.99999), "+30°00′00″"); // carries into degrees
+    }
+
     #[test]
     fn parse_hms_ok_and_wrong_lengths() {
         // OK
@@ -231,4 +820,190 @@ mod tests {
         assert!(parse_dms("10:30:00:00").is_none());
         assert!(parse_dms("xx").is_none());
     }
+
+    #[test]
+    fn ra_hms_parses_and_normalizes() {
+        // 5:35:17.3 -> 5.588138... hours -> 83.822083... deg
+        let ra = parse_ra_deg("5:35:17.3").unwrap();
+        assert!(approx(ra, 83.82208333333332, 1e-9));
+
+        // 24:00:00 -> 360 deg -> normalized to 0
+        let ra = parse_ra_deg("24:00:00").unwrap();
+        assert!(approx(ra, 0.0, 1e-12));
+
+        // Negative degrees should wrap into [0, 360)
+        let ra = parse_ra_deg("-30").unwrap();
+        assert!(approx(ra, 330.0, 1e-12));
+    }
+
+    #[test]
+    fn ra_degrees_parses_direct() {
+        let ra = parse_ra_deg("83.82208333333332").unwrap();
+        assert!(approx(ra, 83.82208333333332, 1e-12));
+
+        // >360 wraps
+        let ra = parse_ra_deg("720").unwrap();
+        assert!(approx(ra, 0.0, 1e-12));
+    }
+
+    #[test]
+    fn proper_motion_carries_stars_over_the_pole() {
+        // 2° north of a star 1° from the north pole
+        let pm = ProperMotion {
+            ra_mas_yr: 0.0,
+            dec_mas_yr: 1000.0,
+        };
+        let at = EQPoint {
+            ra_deg: 10.0,
+            dec_deg: 89.0,
+        };
+        let after = pm.position_after(at, 7200.0);
+        assert!(approx(after.ra_deg, 190.0, 1e-9) && approx(after.dec_deg, 89.0, 1e-9));
+
+        let south = ProperMotion {
+            ra_mas_yr: 0.0,
+            dec_mas_yr: -1000.0,
+        };
+        let at = EQPoint {
+            ra_deg: 270.0,
+            dec_deg: -89.5,
+        };
+        let after = south.position_after(at, 7200.0);
+        assert!(approx(after.ra_deg, 90.0, 1e-9) && approx(after.dec_deg, -88.5, 1e-9));
+    }
+
+    #[test]
+    fn ra_unit_decides_how_bare_numbers_read() {
+        let ra = parse_ra_deg_in("5.58", AngleUnit::Hours).unwrap();
+        assert!(approx(ra, 83.7, 1e-9));
+        let ra = parse_ra_deg_in("5.58", AngleUnit::Degrees).unwrap();
+        assert!(approx(ra, 5.58, 1e-12));
+        // Hours past 24 wrap like degrees past 360
+        let ra = parse_ra_deg_in("25", AngleUnit::Hours).unwrap();
+        assert!(approx(ra, 15.0, 1e-12));
+        // Forms with their own units ignore it
+        let ra = parse_ra_deg_in("5:35:17.3", AngleUnit::Degrees).unwrap();
+        assert!(approx(ra, parse_ra_deg("5:35:17.3").unwrap(), 1e-12));
+        assert!(parse_ra_deg_in("not-a-number", AngleUnit::Hours).is_err());
+    }
+
+    #[test]
+    fn ra_bad_input_errors() {
+        // Completely non-numeric degrees is an error
+        assert!(parse_ra_deg("not-a-number").is_err());
+        // Wrong HMS arity (needs exactly 3 fields)
+        assert!(parse_ra_deg("1:2").is_err());
+    }
+
+    #[test]
+    fn dec_dms_parses_with_sign() {
+        // -5:23:28 -> -5.391111... deg
+        let dec = parse_dec_deg("-5:23:28").unwrap();
+        assert!(approx(dec, -5.391111111111111, 1e-9));
+
+        // +10:00:00 -> 10 deg
+        let dec = parse_dec_deg("+10:00:00").unwrap();
+        assert!(approx(dec, 10.0, 1e-12));
+    }
+
+    #[test]
+    fn suffixed_forms_match_the_colon_forms() {
+        for (suffixed, colon) in [
+            ("5h35m17.3s", "5:35:17.3"),
+            ("5h 35m 17.3s", "5:35:17.3"),
+            ("5ʰ35ᵐ17.3ˢ", "5:35:17.3"),
+            ("23h", "23:00:00"),
+        ] {
+            let ra = parse_ra_deg(suffixed).unwrap();
+            assert!(
+                approx(ra, parse_ra_deg(colon).unwrap(), 1e-12),
+                "{suffixed}"
+            );
+        }
+        for (suffixed, colon) in [
+            ("-5°23′28″", "-5:23:28"),
+            ("-5d23m28s", "-5:23:28"),
+            ("+41° 16' 9\"", "41:16:09"),
+            ("-0°30′", "-0:30:00"),
+            ("10°", "10:00:00"),
+        ] {
+            let dec = parse_dec_deg(suffixed).unwrap();
+            assert!(
+                approx(dec, parse_dec_deg(colon).unwrap(), 1e-12),
+                "{suffixed}"
+            );
+        }
+        // The formatter's output reads back
+        let dec = parse_dec_deg(&format_dms(-5.391111)).unwrap();
+        assert!(approx(dec, -5.391111, 1e-3));
+    }
+
+    #[test]
+    fn malformed_suffixed_forms_are_rejected() {
+        for bad in ["5h35", "35m5h", "5h5h", "5h-35m", "5x", "h", "5h35m17s2"] {
+            assert!(parse_ra_deg(bad).is_err(), "{bad}");
+            let bad = bad.replace('h', "°");
+            assert!(parse_dec_deg(&bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn dec_degrees_parses_direct() {
+        let dec = parse_dec_deg("-5.3911111111").unwrap();
+        assert!(approx(dec, -5.3911111111, 1e-12));
+    }
+
+    #[test]
+    fn dec_bad_input_errors() {
+        assert!(parse_dec_deg("bad").is_err());
+        assert!(parse_dec_deg("1:2").is_err()); // not DMS (needs 3 fields)
+    }
+
+    #[test]
+    fn dec_past_the_poles_errors() {
+        assert_eq!(parse_dec_deg("-90").unwrap(), -90.0);
+        assert_eq!(parse_dec_deg("+90:00:00").unwrap(), 90.0);
+        for bad in ["95", "-90.5", "91:00:00", "-100°", "NaN"] {
+            assert!(parse_dec_deg(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn separation_handles_wrap_and_poles() {
+        let p = |ra_deg, dec_deg| EQPoint { ra_deg, dec_deg };
+        assert!(approx(p(359.5, 0.0).separation_deg(p(0.5, 0.0)), 1.0, 1e-9));
+        assert!(approx(
+            p(0.0, 89.0).separation_deg(p(180.0, 89.0)),
+            2.0,
+            1e-9
+        ));
+        assert!(approx(
+            p(10.0, 20.0).separation_deg(p(10.0, 20.0)),
+            0.0,
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn eqpoint_from_strings_mixes_notations() {
+        let p = EQPoint::from_strings("5:35:17.3", "-5.3911111111").unwrap();
+        assert!(approx(p.ra_deg, 83.82208333333332, 1e-9));
+        assert!(approx(p.dec_deg, -5.3911111111, 1e-12));
+
+        let p = EQPoint::from_strings("-30", "+10:00:00").unwrap();
+        assert!(approx(p.ra_deg, 330.0, 1e-12));
+        assert!(approx(p.dec_deg, 10.0, 1e-12));
+    }
+
+    #[test]
+    fn eqpoint_from_strings_reports_either_bad_half() {
+        assert!(matches!(
+            EQPoint::from_strings("1:2", "0"),
+            Err(ChartError::Parse(_))
+        ));
+        assert!(matches!(
+            EQPoint::from_strings("0", "bad"),
+            Err(ChartError::Parse(_))
+        ));
+    }
 }